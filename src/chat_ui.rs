@@ -1,14 +1,43 @@
-use druid::Widget;
+use druid::{Widget, WidgetExt};
 use druid::widget;
 use crate::AppState;
-use crate::widgets::chat_window_widget;
+use crate::helper::layout_settings::LayoutSettings;
+use crate::helper::replay::Replay;
+use crate::widgets::{audit_widget::AuditWidget, benchmark_widget::BenchmarkWidget, chat_window_widget, performance_overlay::PerformanceOverlay, replay_controller::ReplayController, settings_save_controller::SettingsSaveController};
 
-pub(crate) fn build_chat_ui() -> impl Widget<AppState> {
-    let chat_widget = chat_window_widget::ChatWindowWidget::new();
+/// Builds the chat window. A replay, when given, is played into the timeline once the window is shown.
+/// The performance overlay shows the frame time and memory use on top of it.
+pub(crate) fn build_chat_ui(replay: Option<Replay>, show_performance_overlay: bool) -> Box<dyn Widget<AppState>> {
+    let chat_ui = build_chat_window().controller(SettingsSaveController::new());
+    let chat_ui = match replay {
+        Some(replay) => chat_ui.controller(ReplayController::new(replay)).boxed(),
+        None => chat_ui.boxed(),
+    };
+    let chat_ui = if show_performance_overlay {
+        PerformanceOverlay::new(chat_ui).boxed()
+    } else {
+        chat_ui
+    };
+    // Outside of everything else, so the audit counts the whole window
+    AuditWidget::new(chat_ui).boxed()
+}
+
+/// Builds the chat window for the benchmark, which runs it through each measurement then quits.
+/// Nothing saves the settings the benchmark changes.
+pub(crate) fn build_benchmark_ui(sizes: Vec<usize>, changed_settings: LayoutSettings) -> Box<dyn Widget<AppState>> {
+    BenchmarkWidget::new(build_chat_window(), sizes, changed_settings).boxed()
+}
+
+/// The chat window widget, with the env set from the state
+fn build_chat_window() -> impl Widget<AppState> {
     widget::EnvScope::new(
         |env: &mut druid::env::Env, data: &AppState| {
-            data.layout_settings.set_env(env);
+            LayoutSettings::set_env(&data.layout_settings, env);
+            data.theme.colors.set_env(env);
+            env.set(crate::SELF_DISPLAY_NAME_KEY, data.self_display_name.clone());
+            env.set(crate::SELECTED_MESSAGE_KEY, data.selected_message.unwrap_or(crate::NO_SELECTED_MESSAGE));
+            env.set(crate::TEXT_SELECTION_KEY, std::sync::Arc::new(data.text_selection));
         },
-        chat_widget
+        chat_window_widget::ChatWindowWidget::new()
     )
-}
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Set to anything to turn the audit on
+const AUDIT_ENV_VAR: &str = "DRUID_CHAT_AUDIT";
+
+/// The work that is counted while the audit is on
+#[derive(Clone, Copy)]
+pub enum Counter {
+    /// Any timer firing, in any window
+    TimerWakeup,
+    /// A layout pass over a whole window
+    Layout,
+    /// A paint pass over a whole window
+    Paint,
+    /// A timeline item that was laid out, rather than using its last layout
+    ItemLayout,
+}
+
+const COUNTER_NAMES: [&str; 4] = ["Timer wakeups", "Window layouts", "Window paints", "Item layouts"];
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+static AUDIT: Mutex<Option<AuditState>> = Mutex::new(None);
+
+struct AuditState {
+    start: Instant,
+    totals: [u64; 4],
+    /// The second since the start that is being counted, and its counts
+    current_second: u64,
+    current_counts: [u64; 4],
+    /// The most counted in any one second
+    peaks: [u64; 4],
+}
+
+/// Turns the audit on when the environment variable is set.
+///
+/// It counts how often the app wakes up and draws, so the idle CPU use can be checked:
+/// an idle app should show no timer wakeups and no paints.
+pub fn init_from_env() {
+    if std::env::var_os(AUDIT_ENV_VAR).is_some() {
+        *AUDIT.lock().unwrap() = Some(AuditState {
+            start: Instant::now(),
+            totals: [0; 4],
+            current_second: 0,
+            current_counts: [0; 4],
+            peaks: [0; 4],
+        });
+        IS_ENABLED.store(true, Ordering::Relaxed);
+        println!("Audit mode is on. A summary is printed when the app quits.");
+    }
+}
+
+/// Counts one of something. Does nothing unless the audit is on.
+pub fn record(counter: Counter) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(state) = AUDIT.lock().unwrap().as_mut() {
+        let second = state.start.elapsed().as_secs();
+        if second != state.current_second {
+            state.current_second = second;
+            state.current_counts = [0; 4];
+        }
+        let index = counter as usize;
+        state.totals[index] += 1;
+        state.current_counts[index] += 1;
+        state.peaks[index] = state.peaks[index].max(state.current_counts[index]);
+    }
+}
+
+/// Prints the totals, the average per second, and the busiest second of each counter
+pub fn print_summary() {
+    let audit = AUDIT.lock().unwrap();
+    let state = match audit.as_ref() {
+        Some(state) => state,
+        None => return,
+    };
+    let seconds = state.start.elapsed().as_secs_f64();
+    println!("Audit summary over {:.1} seconds:", seconds);
+    for (i, name) in COUNTER_NAMES.iter().enumerate() {
+        println!("  {:<15} {:>8} total, {:>8.2} per second, {:>6} in the busiest second",
+            name, state.totals[i], state.totals[i] as f64 / seconds.max(1.0), state.peaks[i]);
+    }
+}
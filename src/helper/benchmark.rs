@@ -0,0 +1,132 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use druid::{AppLauncher, ImageBuf, WindowDesc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::{AppState, LayoutSettings, Message};
+use crate::helper::{demo_data::{self, DemoOptions}, helper_functions, offscreen, preset_registry::PresetRegistry, presets, theme, timeline};
+
+/// The timeline sizes measured when none are given
+const DEFAULT_SIZES: [usize; 4] = [100, 1_000, 10_000, 100_000];
+/// The same size as the chat window opens at
+const WINDOW_SIZE: (f64, f64) = (300.0, 450.0);
+/// How many places in the timeline are jumped to
+pub const SCROLL_JUMPS: usize = 20;
+/// The same seed every run, so runs can be compared
+pub const SEED: u64 = 1;
+/// When the last message was sent. Fixed, so the dates in the timeline are the same every run too.
+const END_TIME: i64 = 1_600_000_000;
+const SELF_USER_ID: u32 = 0;
+const USER_COUNT: u32 = 5;
+/// The layout switched to when measuring a settings change
+const CHANGED_PRESET: &str = "IRC";
+
+/// Measures how long the timeline takes to group, lay out and paint, then quits.
+///
+/// The sizes are the numbers of messages to measure with. Each measurement is printed
+/// as a line of JSON, with the average time it took and the peak memory use so far:
+/// ```text
+/// {"messages":1000,"measurement":"layout","operations":1,"milliseconds":2.345,"peak_memory_kb":40960}
+/// ```
+/// Without a window, the items are laid out and painted offscreen the same way as the golden image tests,
+/// so it runs without a display. In the window, the chat window with its real widgets is driven
+/// by the benchmark widget one frame at a time. The settings it changes aren't saved.
+pub fn run(sizes: &[usize], in_window: bool) -> Result<(), String> {
+    let sizes = if sizes.is_empty() { DEFAULT_SIZES.to_vec() } else { sizes.to_vec() };
+    let registry = PresetRegistry::load();
+    let changed_settings = registry.get(CHANGED_PRESET)
+        .map(|preset| preset.settings.clone())
+        .ok_or_else(|| format!("The preset {} is missing", CHANGED_PRESET))?;
+    if in_window {
+        return run_in_window(sizes, changed_settings);
+    }
+    for size in sizes {
+        run_offscreen(size, &changed_settings)?;
+    }
+    Ok(())
+}
+
+fn run_in_window(sizes: Vec<usize>, changed_settings: LayoutSettings) -> Result<(), String> {
+    let window = WindowDesc::new(crate::chat_ui::build_benchmark_ui(sizes, changed_settings))
+        .window_size(WINDOW_SIZE);
+    AppLauncher::with_window(window)
+        .configure_env(|env, _| crate::configure_env(env, SELF_USER_ID as u64))
+        .launch(new_state())
+        .map_err(|e| format!("The benchmark window couldn't be opened: {}", e))
+}
+
+fn run_offscreen(size: usize, changed_settings: &LayoutSettings) -> Result<(), String> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let messages = generate_messages(size);
+    let mut state = new_state();
+
+    // Grouping, as messages arrive one by one
+    let start = Instant::now();
+    for (user_id, message) in messages {
+        timeline::push_message(&mut state, user_id, message);
+    }
+    report(size, "grouping", 1, start.elapsed());
+
+    let start = Instant::now();
+    let layout = offscreen::layout_timeline(&state, SELF_USER_ID, WINDOW_SIZE.0);
+    report(size, "layout", 1, start.elapsed());
+
+    // The timeline opens scrolled to the newest messages
+    let bottom = (layout.height - WINDOW_SIZE.1).max(0.0);
+    let start = Instant::now();
+    offscreen::paint_timeline(&state, &layout, bottom, WINDOW_SIZE.1)?;
+    report(size, "paint", 1, start.elapsed());
+
+    let start = Instant::now();
+    for jump in 0..SCROLL_JUMPS {
+        let scroll_y = bottom * rng.gen_range(0.0..1.0);
+        offscreen::paint_timeline(&state, &layout, scroll_y, WINDOW_SIZE.1)
+            .map_err(|e| format!("Scroll jump {} failed: {}", jump, e))?;
+    }
+    report(size, "scroll_jump", SCROLL_JUMPS, start.elapsed());
+
+    // Every item is laid out again, then the view is painted
+    let start = Instant::now();
+    presets::apply_preset(&mut state, changed_settings);
+    let layout = offscreen::layout_timeline(&state, SELF_USER_ID, WINDOW_SIZE.0);
+    offscreen::paint_timeline(&state, &layout, (layout.height - WINDOW_SIZE.1).max(0.0), WINDOW_SIZE.1)?;
+    report(size, "settings_change", 1, start.elapsed());
+    Ok(())
+}
+
+/// An empty timeline with the default settings, as the chat window opens without a config file
+pub fn new_state() -> AppState {
+    let mut state = AppState::new(LayoutSettings::default(), theme::default_theme());
+    state.profile_pics = load_profile_pics();
+    state
+}
+
+/// The messages of a timeline of the given size. Always the same for the same size.
+pub fn generate_messages(size: usize) -> Vec<(u32, Message)> {
+    let options = DemoOptions {
+        seed: SEED,
+        message_count: size,
+        user_count: USER_COUNT,
+        // A few minutes apart on average, like a busy channel
+        time_span_seconds: size as i64 * 120,
+    };
+    demo_data::generate_messages(&options, END_TIME)
+}
+
+/// The same pictures as the app, or none when it isn't run from the project directory
+fn load_profile_pics() -> druid::im::Vector<ImageBuf> {
+    (1..=USER_COUNT)
+        .map(|i| ImageBuf::from_file(Path::new(&format!("./images/user_{}_55px.png", i))).unwrap_or_else(|_| ImageBuf::empty()))
+        .collect()
+}
+
+/// Prints a measurement as a line of JSON. The duration is the total of all the operations.
+pub fn report(size: usize, measurement: &str, operations: usize, duration: Duration) {
+    let milliseconds = duration.as_secs_f64() * 1000.0 / operations as f64;
+    let peak_memory = match helper_functions::get_process_memory_kb("VmHWM") {
+        Some(kb) => kb.to_string(),
+        None => "null".to_string(),
+    };
+    println!("{{\"messages\":{},\"measurement\":\"{}\",\"operations\":{},\"milliseconds\":{:.3},\"peak_memory_kb\":{}}}",
+        size, measurement, operations, milliseconds, peak_memory);
+}
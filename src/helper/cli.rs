@@ -0,0 +1,260 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use clap::{CommandFactory, Parser, ValueEnum};
+use druid::im;
+use crate::LayoutSettings;
+use crate::helper::config;
+use crate::helper::demo_data::DemoOptions;
+use crate::helper::preset_registry::PresetRegistry;
+use crate::helper::presets::UserPreset;
+
+/// A chat client, filled with made up messages to try out the layouts
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// The ID of the user you are. It must be less than --users.
+    #[arg(long, default_value_t = 0, value_name = "ID")]
+    pub user: u32,
+
+    /// Starts with a layout preset instead of the saved layout, such as "IRC" or the name of a saved preset
+    #[arg(long, value_name = "PRESET")]
+    pub layout: Option<String>,
+
+    /// The layout file to load and save, instead of layout.toml in the store directory
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// The directory with the layout, the saved presets and the themes, instead of the user's config directory
+    #[arg(long, value_name = "DIR")]
+    pub store: Option<PathBuf>,
+
+    /// Where the messages in the timeline come from
+    #[arg(long, value_enum, default_value_t = Backend::Demo)]
+    pub backend: Backend,
+
+    /// Makes the demo messages the same as an earlier run. Otherwise a random seed is used, and printed at startup.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// How many demo messages the timeline starts with
+    #[arg(long, default_value_t = 50, value_name = "COUNT")]
+    pub messages: usize,
+
+    /// How many users send the demo messages
+    #[arg(long, default_value_t = 5, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    pub users: u32,
+
+    /// How many seconds before now the first demo message was sent
+    #[arg(long, default_value_t = 7 * 24 * 60 * 60, value_name = "SECONDS", value_parser = clap::value_parser!(i64).range(0..))]
+    pub time_span: i64,
+
+    /// The size the chat window opens at
+    #[arg(long, default_value = "300x450", value_name = "WIDTHxHEIGHT", value_parser = parse_window_size)]
+    pub window_size: (f64, f64),
+
+    /// The transcript that the replay backend plays. See transcripts/grouping.toml for the format.
+    #[arg(long, value_name = "FILE")]
+    pub transcript: Option<PathBuf>,
+
+    /// How many times faster than real time the transcript is played
+    #[arg(long, default_value_t = 1.0, value_name = "FACTOR", value_parser = parse_positive_number)]
+    pub speed: f64,
+
+    /// Starts the replay paused, so it can be stepped through with `/replay step`
+    #[arg(long)]
+    pub paused: bool,
+
+    /// Adds this many made up messages per second, and shows the frame time and memory use
+    #[arg(long, value_name = "PER_SECOND", value_parser = parse_positive_number)]
+    pub flood: Option<f64>,
+
+    /// Opens the settings window at launch
+    #[arg(long)]
+    pub settings: bool,
+
+    /// Measures the timeline without a window, then quits. Takes the numbers of messages to measure with.
+    #[arg(long, num_args = 0.., value_name = "MESSAGES")]
+    pub benchmark: Option<Vec<usize>>,
+
+    /// Runs --benchmark in the chat window, to measure its real widgets. Needs a display.
+    #[arg(long)]
+    pub benchmark_window: bool,
+}
+
+/// Where the messages in the timeline come from
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum Backend {
+    /// Made up messages, set up with --seed, --messages, --users and --time-span
+    Demo,
+    /// The events in --transcript, played as they happen
+    Replay,
+}
+
+impl Args {
+    /// Checks what clap can't check by itself, such as options that depend on each other or on files.
+    ///
+    /// The paths are made absolute, since the config watcher compares them to the paths of changed files.
+    pub fn validate(&mut self) -> Result<(), String> {
+        if self.user >= self.users {
+            return Err(format!("--user must be less than --users, which is {}", self.users));
+        }
+        match (self.backend, &self.transcript) {
+            (Backend::Replay, None) => return Err("--backend replay needs a --transcript to play".to_string()),
+            (Backend::Demo, Some(_)) => return Err("--transcript only works with --backend replay".to_string()),
+            _ => (),
+        }
+        if self.benchmark_window && self.benchmark.is_none() {
+            return Err("--benchmark-window only works with --benchmark".to_string());
+        }
+        if self.benchmark.is_some() && (self.backend == Backend::Replay || self.flood.is_some()) {
+            return Err("--benchmark makes up its own messages, so it can't be used with --backend replay or --flood".to_string());
+        }
+        if let Some(store) = &self.store {
+            if store.exists() && !store.is_dir() {
+                return Err(format!("--store must be a directory, but {} is a file", store.display()));
+            }
+            self.store = Some(make_absolute(store)?);
+        }
+        if let Some(config) = &self.config {
+            check_config_file(config)?;
+            self.config = Some(make_absolute(config)?);
+        }
+        Ok(())
+    }
+
+    pub fn demo_options(&self) -> DemoOptions {
+        DemoOptions {
+            seed: self.seed.unwrap_or_else(rand::random),
+            message_count: self.messages,
+            user_count: self.users,
+            time_span_seconds: self.time_span,
+        }
+    }
+}
+
+/// Prints the error with the usage, the same way as clap's own errors, and exits
+pub fn exit_with_error(message: &str) -> ! {
+    Args::command().error(clap::error::ErrorKind::ValueValidation, message).exit()
+}
+
+/// Finds the settings of --layout, the same way as `/layout`. Saved presets are found by name, ignoring case.
+pub fn find_layout(name: &str, registry: &PresetRegistry, user_presets: &im::Vector<UserPreset>) -> Result<LayoutSettings, String> {
+    if let Some(preset) = registry.get(name) {
+        return Ok(preset.settings.clone());
+    }
+    if let Some(preset) = user_presets.iter().find(|preset| preset.name.eq_ignore_ascii_case(name.trim())) {
+        return Ok(preset.settings.clone());
+    }
+    let names: Vec<&str> = registry.iter().map(|preset| preset.id.as_str())
+        .chain(user_presets.iter().map(|preset| preset.name.as_str()))
+        .collect();
+    Err(format!("Unknown layout \"{}\". Available layouts: {}", name, names.join(", ")))
+}
+
+/// Parses sizes like `300x450`
+fn parse_window_size(text: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("expected a size like 300x450, but got \"{}\"", text);
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: f64 = width.trim().parse().map_err(|_| invalid())?;
+    let height: f64 = height.trim().parse().map_err(|_| invalid())?;
+    if !(width >= 1.0 && height >= 1.0 && width.is_finite() && height.is_finite()) {
+        return Err(format!("the window size must be at least 1x1, but it is {}x{}", width, height));
+    }
+    Ok((width, height))
+}
+
+fn parse_positive_number(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("expected a number above 0, but got \"{}\"", text)),
+    }
+}
+
+/// A config file given on the command line doesn't have to exist yet, since it's created when the layout is saved.
+/// When it does exist, it has to be readable, instead of being silently replaced with the defaults.
+fn check_config_file(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let file = config::parse_layout_file(&text).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    file.settings.validate().map_err(|e| format!("{} has invalid settings: {}", path.display(), e))
+}
+
+fn make_absolute(path: &Path) -> Result<PathBuf, String> {
+    std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .map_err(|e| format!("Could not find the current directory: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("druid-chat-proof-of-concept").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn the_arguments_are_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn defaults_are_used_without_arguments() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.user, 0);
+        assert_eq!(args.backend, Backend::Demo);
+        assert_eq!(args.window_size, (300.0, 450.0));
+        assert!(!args.settings);
+        assert!(args.benchmark.is_none());
+        assert!(!args.benchmark_window);
+    }
+
+    #[test]
+    fn options_are_read() {
+        let mut args = parse(&["--user", "2", "--users", "3", "--seed", "42", "--window-size", "640x480", "--settings", "--layout", "irc"]).unwrap();
+        assert!(args.validate().is_ok());
+        assert_eq!((args.user, args.users, args.seed), (2, 3, Some(42)));
+        assert_eq!(args.window_size, (640.0, 480.0));
+        assert!(args.settings);
+        assert_eq!(args.layout.as_deref(), Some("irc"));
+        let args = parse(&["--backend", "replay", "--transcript", "transcript.toml", "--speed", "2.5", "--paused"]).unwrap();
+        assert_eq!((args.backend, args.speed, args.paused), (Backend::Replay, 2.5, true));
+        assert_eq!(parse(&["--flood", "50"]).unwrap().flood, Some(50.0));
+        assert_eq!(parse(&["--benchmark"]).unwrap().benchmark, Some(Vec::new()));
+        assert_eq!(parse(&["--benchmark", "10", "20"]).unwrap().benchmark, Some(vec![10, 20]));
+        let mut args = parse(&["--benchmark", "10", "--benchmark-window"]).unwrap();
+        assert!(args.validate().is_ok());
+        assert!(args.benchmark_window);
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        assert!(parse(&["2"]).is_err());
+        assert!(parse(&["--user", "me"]).is_err());
+        assert!(parse(&["--users", "0"]).is_err());
+        assert!(parse(&["--time-span", "-1"]).is_err());
+        assert!(parse(&["--window-size", "300"]).is_err());
+        assert!(parse(&["--window-size", "0x450"]).is_err());
+        assert!(parse(&["--backend", "irc"]).is_err());
+        assert!(parse(&["--benchmark", "many"]).is_err());
+        assert!(parse(&["--user", "5"]).unwrap().validate().is_err());
+        assert!(parse(&["--speed", "0"]).is_err());
+        assert!(parse(&["--flood", "-5"]).is_err());
+        assert!(parse(&["--backend", "replay"]).unwrap().validate().is_err());
+        assert!(parse(&["--transcript", "transcript.toml"]).unwrap().validate().is_err());
+        assert!(parse(&["--benchmark-window"]).unwrap().validate().is_err());
+        assert!(parse(&["--benchmark", "--flood", "50"]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn layouts_are_found_by_id_or_name() {
+        let registry = PresetRegistry::load();
+        let first = registry.iter().next().unwrap();
+        assert!(find_layout(&first.id.to_uppercase(), &registry, &im::Vector::new()).is_ok());
+        let user_preset = UserPreset { name: "Mine".to_string(), file_name: "Mine.toml".to_string(), settings: LayoutSettings::default() };
+        assert!(find_layout("mine", &registry, &im::vector![user_preset]).is_ok());
+        assert!(find_layout("no such layout", &registry, &im::Vector::new()).is_err());
+    }
+}
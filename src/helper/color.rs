@@ -0,0 +1,134 @@
+use druid::Color;
+
+/// The lowest contrast that WCAG AA allows for normal sized text
+pub const WCAG_AA_CONTRAST: f64 = 4.5;
+
+/// A color that can be edited and saved. It is saved as a hex string, like "#RRGGBB" or "#RRGGBBAA".
+#[derive(Clone, PartialEq, druid::Data, druid::Lens)]
+pub struct SimpleColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl SimpleColor {
+    pub fn from_druid_color(color: &Color) -> SimpleColor {
+        let (r, g, b, a) = color.as_rgba8();
+        SimpleColor { r, g, b, a }
+    }
+
+    pub fn to_druid_color(&self) -> Color {
+        Color::rgba8(self.r, self.g, self.b, self.a)
+    }
+
+    /// Formats it like #RRGGBB, or #RRGGBBAA if it isn't opaque
+    pub fn to_hex(&self) -> String {
+        if self.a == 255 {
+            format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+        } else {
+            format!("#{:02X}{:02X}{:02X}{:02X}", self.r, self.g, self.b, self.a)
+        }
+    }
+
+    /// Reads #RRGGBB or #RRGGBBAA. The # is optional.
+    pub fn from_hex(hex: &str) -> Option<SimpleColor> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok();
+        Some(SimpleColor {
+            r: channel(0)?,
+            g: channel(1)?,
+            b: channel(2)?,
+            a: if hex.len() == 8 { channel(3)? } else { 255 },
+        })
+    }
+}
+
+impl serde::Serialize for SimpleColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SimpleColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<SimpleColor, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        SimpleColor::from_hex(&hex).ok_or_else(|| {
+            serde::de::Error::custom(format!("\"{}\" is not a #RRGGBB or #RRGGBBAA color", hex))
+        })
+    }
+}
+
+/// Converts hue (0 to 360), saturation, and value (0 to 1) to RGB.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
+    let chroma = value * saturation;
+    let hue_section = (hue.rem_euclid(360.0)) / 60.0;
+    let x = chroma * (1.0 - (hue_section % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue_section as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let to_u8 = |channel: f64| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// Converts RGB to hue (0 to 360), saturation, and value (0 to 1).
+/// Grays have no hue, so they get a hue of 0.
+pub fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
+
+/// Draws the color over the background, to get the color that is seen when the color is see-through.
+pub fn blend(color: &Color, background: &Color) -> Color {
+    let (r, g, b, a) = color.as_rgba8();
+    let (bg_r, bg_g, bg_b, _) = background.as_rgba8();
+    let alpha = a as f64 / 255.0;
+    let mix = |channel: u8, bg_channel: u8| {
+        (channel as f64 * alpha + bg_channel as f64 * (1.0 - alpha)).round() as u8
+    };
+    Color::rgb8(mix(r, bg_r), mix(g, bg_g), mix(b, bg_b))
+}
+
+/// The relative luminance of the color, as defined by WCAG
+fn relative_luminance(color: &Color) -> f64 {
+    let (r, g, b, _) = color.as_rgba8();
+    let linear = |channel: u8| {
+        let channel = channel as f64 / 255.0;
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// The WCAG contrast ratio between two opaque colors, from 1 to 21.
+pub fn contrast_ratio(first: &Color, second: &Color) -> f64 {
+    let first = relative_luminance(first);
+    let second = relative_luminance(second);
+    (first.max(second) + 0.05) / (first.min(second) + 0.05)
+}
@@ -0,0 +1,316 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use druid::Data;
+use crate::helper::layout_settings::{self, LayoutSettings};
+use crate::helper::theme::{self, Theme};
+
+/// Increased when the format of the file changes in a way that older versions can't read.
+/// Adding or removing a setting doesn't need a new version, since missing and unknown settings are handled.
+const CONFIG_VERSION: i64 = 1;
+const CONFIG_DIR_NAME: &str = "druid-chat-proof-of-concept";
+const CONFIG_FILE_NAME: &str = "layout.toml";
+
+/// Set from the command line, to keep the files somewhere other than the user's config directory
+static STORE_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+/// The text last written to the config file, so the app's own saves can be told apart from changes made by hand
+static LAST_SAVED_TEXT: Mutex<Option<String>> = Mutex::new(None);
+
+/// The format of the config file, and of exported presets.
+/// Presets also have a name, so they can be listed without relying on the file name.
+/// The config file also has the theme, with all of its colors, so changed colors are kept.
+#[derive(serde::Serialize)]
+struct LayoutFile<'a> {
+    version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme: Option<&'a Theme>,
+    layout: &'a LayoutSettings,
+}
+
+/// A layout read from a file
+pub struct ParsedLayoutFile {
+    pub name: Option<String>,
+    pub settings: LayoutSettings,
+    /// Only the config file has a theme
+    pub theme: Option<Theme>,
+    /// The settings that could not be read, and so use their defaults
+    pub warnings: Vec<String>,
+}
+
+/// Uses other places for the files than the defaults. Called once at startup, before anything is loaded.
+///
+/// The store directory replaces the directory returned by [get_config_dir],
+/// and the config path replaces the file in it.
+pub fn set_paths(store_dir: Option<PathBuf>, config_path: Option<PathBuf>) {
+    if let Some(dir) = store_dir {
+        let _ = STORE_DIR_OVERRIDE.set(dir);
+    }
+    if let Some(path) = config_path {
+        let _ = CONFIG_PATH_OVERRIDE.set(path);
+    }
+}
+
+/// The directory with the config file and the presets, in the user's config directory, such as ~/.config on Linux.
+pub fn get_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = STORE_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME))
+}
+
+pub fn get_config_path() -> Option<PathBuf> {
+    if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+        return Some(path.clone());
+    }
+    get_config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+/// Loads the layout settings and the theme saved by the last run.
+///
+/// When there is no file yet, the defaults are used.
+/// Any setting that can't be read falls back to its default, with a warning.
+pub fn load_config() -> (LayoutSettings, Theme) {
+    let defaults = || (LayoutSettings::default(), theme::default_theme());
+    let path = match get_config_path() {
+        Some(path) => path,
+        None => {
+            tracing::warn!("Could not find the config directory. Using the default layout.");
+            return defaults();
+        }
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return defaults(),
+        Err(e) => {
+            tracing::warn!("Could not read {}: {}. Using the default layout.", path.display(), e);
+            return defaults();
+        }
+    };
+    match parse_layout_file(&text) {
+        Ok(file) => {
+            for warning in file.warnings {
+                tracing::warn!("{}", warning);
+            }
+            (file.settings, file.theme.unwrap_or_else(theme::default_theme))
+        },
+        Err(e) => {
+            tracing::warn!("Could not read {}: {}. Using the default layout.", path.display(), e);
+            defaults()
+        }
+    }
+}
+
+/// Reads a config file or an exported preset.
+///
+/// It only fails when the file isn't TOML, or has no layout.
+/// Settings that can't be read use their defaults, with a warning for each.
+pub fn parse_layout_file(text: &str) -> Result<ParsedLayoutFile, String> {
+    let mut table = match text.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => table,
+        Ok(_) => return Err("Expected a table".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut warnings = Vec::new();
+    match table.get("version").and_then(toml::Value::as_integer) {
+        Some(CONFIG_VERSION) => {},
+        Some(version) => warnings.push(format!(
+            "The layout file is version {}, but version {} is expected. Reading what can be read.",
+            version, CONFIG_VERSION
+        )),
+        None => warnings.push("The layout file has no version. Reading what can be read.".to_string()),
+    }
+    let name = table.get("name").and_then(toml::Value::as_str).map(str::to_string);
+    // The theme is saved with every color, so it doesn't need the other themes to be read
+    let theme = table.remove("theme").and_then(|value| match theme::parse_theme(value, &[]) {
+        Ok(theme) => Some(theme),
+        Err(e) => {
+            warnings.push(format!("Could not read the saved theme: {}. Using the default theme.", e));
+            None
+        }
+    });
+    match table.remove("layout") {
+        Some(toml::Value::Table(layout)) => {
+            let settings = layout_from_table(layout, &mut warnings);
+            Ok(ParsedLayoutFile { name, settings, theme, warnings })
+        },
+        _ => Err("There is no layout in the file".to_string()),
+    }
+}
+
+/// Builds the layout settings one setting at a time, starting from the defaults.
+///
+/// Each saved setting is only kept if the settings can still be read with it, and it is in the range the settings allow,
+/// so one bad value doesn't cause the rest of the file to be lost.
+fn layout_from_table(saved: toml::value::Table, warnings: &mut Vec<String>) -> LayoutSettings {
+    let mut merged = layout_to_table(&LayoutSettings::default());
+    for (key, value) in saved {
+        if !merged.contains_key(&key) {
+            warnings.push(format!("Ignoring unknown layout setting \"{}\"", key));
+            continue;
+        }
+        let previous = merged.insert(key.clone(), value.clone());
+        let problem = match toml::Value::Table(merged.clone()).try_into::<LayoutSettings>() {
+            Ok(_) => get_range_problem(&key, &value),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(problem) = problem {
+            warnings.push(format!("Invalid value {} for layout setting \"{}\": {}. Using the default.", value, key, problem));
+            if let Some(previous) = previous {
+                merged.insert(key, previous);
+            }
+        }
+    }
+    toml::Value::Table(merged).try_into().unwrap_or_else(|_| LayoutSettings::default())
+}
+
+/// Describes why a size is not allowed, or returns none if it is
+fn get_range_problem(key: &str, value: &toml::Value) -> Option<String> {
+    let (min, max) = layout_settings::get_range(key)?;
+    let number = value.as_float().or_else(|| value.as_integer().map(|integer| integer as f64))?;
+    if (min..=max).contains(&number) {
+        None
+    } else {
+        Some(format!("it must be between {} and {}", min, max))
+    }
+}
+
+fn layout_to_table(settings: &LayoutSettings) -> toml::value::Table {
+    match toml::Value::try_from(settings) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("Layout settings are always serialized to a table"),
+    }
+}
+
+/// Replaces the given settings of the base layout.
+///
+/// Unlike reading a saved file, this is strict: an unknown or invalid setting is an error.
+pub fn apply_layout_overrides(base: &LayoutSettings, overrides: toml::value::Table) -> Result<LayoutSettings, String> {
+    let mut merged = layout_to_table(base);
+    for (key, value) in overrides {
+        if !merged.contains_key(&key) {
+            return Err(format!("Unknown layout setting \"{}\"", key));
+        }
+        merged.insert(key, value);
+    }
+    toml::Value::Table(merged).try_into().map_err(|e: toml::de::Error| e.to_string())
+}
+
+/// Writes the layout settings and the theme to the config file, creating the directory if needed.
+///
+/// Nothing is written when the file already has them, such as right after it was reloaded,
+/// so a file that is being edited by hand isn't rewritten under the editor.
+pub fn save_config(settings: &LayoutSettings, theme: &Theme) {
+    let path = match get_config_path() {
+        Some(path) => path,
+        None => {
+            tracing::warn!("Could not find the config directory. The layout was not saved.");
+            return;
+        }
+    };
+    if file_has_config(&path, settings, theme) {
+        return;
+    }
+    let file = LayoutFile { version: CONFIG_VERSION, name: None, theme: Some(theme), layout: settings };
+    let result = file_to_string(&file)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            write_file(&path, &text).map_err(|e| e.to_string())?;
+            if let Ok(mut last_saved_text) = LAST_SAVED_TEXT.lock() {
+                *last_saved_text = Some(text);
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        tracing::warn!("Could not save the layout to {}: {}", path.display(), e);
+    }
+}
+
+/// Returns true if the text is what the app last wrote to the config file
+pub fn is_own_save(text: &str) -> bool {
+    LAST_SAVED_TEXT.lock().is_ok_and(|last_saved_text| last_saved_text.as_deref() == Some(text))
+}
+
+fn file_has_config(path: &Path, settings: &LayoutSettings, theme: &Theme) -> bool {
+    match fs::read_to_string(path).map(|text| parse_layout_file(&text)) {
+        Ok(Ok(file)) => file.settings.same(settings) && file.theme.as_ref() == Some(theme),
+        _ => false,
+    }
+}
+
+/// Gets the text of an exported preset.
+pub fn layout_to_string(name: Option<&str>, settings: &LayoutSettings) -> Result<String, toml::ser::Error> {
+    file_to_string(&LayoutFile { version: CONFIG_VERSION, name, theme: None, layout: settings })
+}
+
+fn file_to_string(file: &LayoutFile) -> Result<String, toml::ser::Error> {
+    // Going through a value puts the tables after the plain values, since TOML needs tables to be last
+    toml::Value::try_from(file).and_then(|value| toml::to_string_pretty(&value))
+}
+
+/// Writes the file next to the old one and then renames it over it,
+/// so a crash while saving doesn't leave a half written file.
+/// The directory is created if needed.
+pub fn write_file(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&temp_path, text)?;
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_layout(layout: &str) -> ParsedLayoutFile {
+        parse_layout_file(&format!("version = {}\n[layout]\n{}", CONFIG_VERSION, layout)).unwrap()
+    }
+
+    #[test]
+    fn valid_settings_are_read() {
+        let file = parse_layout("bubble_padding = 8.0\ngroup_spacing = 10\nshow_left_line = true");
+        assert!(file.warnings.is_empty(), "{:?}", file.warnings);
+        assert_eq!(file.settings.bubble_padding, 8.0);
+        assert_eq!(file.settings.group_spacing, 10.0);
+        assert!(file.settings.show_left_line);
+    }
+
+    #[test]
+    fn a_value_of_the_wrong_type_uses_the_default() {
+        let file = parse_layout("picture_size = \"big\"\nbubble_padding = 8.0");
+        assert_eq!(file.settings.picture_size, LayoutSettings::default().picture_size);
+        assert_eq!(file.settings.bubble_padding, 8.0);
+        assert_eq!(file.warnings.len(), 1);
+        assert!(file.warnings[0].contains("picture_size"));
+    }
+
+    #[test]
+    fn a_value_out_of_range_uses_the_default() {
+        let file = parse_layout("group_spacing = 500.0\ncontent_font_size = 2\nbubble_padding = 8.0");
+        let defaults = LayoutSettings::default();
+        assert_eq!(file.settings.group_spacing, defaults.group_spacing);
+        assert_eq!(file.settings.content_font_size, defaults.content_font_size);
+        assert_eq!(file.settings.bubble_padding, 8.0);
+        assert_eq!(file.warnings.len(), 2);
+        assert!(file.settings.validate().is_ok());
+    }
+
+    #[test]
+    fn an_unknown_setting_is_ignored() {
+        let file = parse_layout("no_such_setting = 3\nbubble_padding = 8.0");
+        assert_eq!(file.settings.bubble_padding, 8.0);
+        assert_eq!(file.warnings, ["Ignoring unknown layout setting \"no_such_setting\""]);
+    }
+
+    #[test]
+    fn a_file_without_a_layout_is_an_error() {
+        assert!(parse_layout_file("version = 1").is_err());
+        assert!(parse_layout_file("not toml").is_err());
+        assert_eq!(parse_layout_file("[layout]").unwrap().warnings.len(), 1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{self, mpsc};
+use std::thread;
+use std::time::Duration;
+use druid::{Command, Data, ExtEventSink, Handled, Selector, Target};
+use notify::{EventKind, RecursiveMode, Watcher};
+use crate::AppState;
+use crate::helper::{config, theme};
+
+/// Sent when the config file or a theme file changes. The payload is the path of the file.
+pub const CONFIG_FILE_CHANGED: Selector<PathBuf> = Selector::new("polysoft.druid-demo.config_file_changed");
+
+/// Editors often write a file in several steps, so changes are only reloaded once they stop for this long.
+const SETTLE_DELAY: Duration = Duration::from_millis(150);
+
+/// Watches the config file and the themes directory, and sends CONFIG_FILE_CHANGED when a file in them changes.
+///
+/// The watcher uses the operating system's file notifications, and the thread that passes them on
+/// is blocked while nothing changes, so watching costs nothing at idle.
+/// Watching stops when the returned watcher is dropped.
+pub fn start_watching(sink: ExtEventSink) -> Option<notify::RecommendedWatcher> {
+    // The config file can be outside of the config directory when it's given on the command line
+    let config_file_dir = config::get_config_path()?.parent()?.to_path_buf();
+    let themes_dir = theme::get_themes_dir()?;
+    // The directories are watched instead of the files, since saving replaces the file
+    if let Err(e) = fs::create_dir_all(&themes_dir) {
+        tracing::warn!("Could not create {}: {}. Config files won't be reloaded.", themes_dir.display(), e);
+        return None;
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            tracing::warn!("Could not watch the config files: {}. They won't be reloaded.", e);
+            return None;
+        }
+    };
+    for dir in [&config_file_dir, &themes_dir] {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            tracing::warn!("Could not watch {}: {}. Changes to it won't be reloaded.", dir.display(), e);
+        }
+    }
+    thread::spawn(move || forward_changes(receiver, sink));
+    Some(watcher)
+}
+
+/// Passes on the changed files once they settle, until the watcher is dropped.
+fn forward_changes(receiver: mpsc::Receiver<notify::Result<notify::Event>>, sink: ExtEventSink) {
+    // Blocks until the first change
+    while let Ok(event) = receiver.recv() {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        let mut next = Some(event);
+        while let Some(event) = next {
+            match event {
+                Ok(event) => {
+                    for path in event.paths.into_iter().filter(|path| is_watched_file(&event.kind, path)) {
+                        if !changed.contains(&path) {
+                            changed.push(path);
+                        }
+                    }
+                },
+                Err(e) => tracing::warn!("Error while watching the config files: {}", e),
+            }
+            next = receiver.recv_timeout(SETTLE_DELAY).ok();
+        }
+        for path in changed {
+            if sink.submit_command(CONFIG_FILE_CHANGED, path, Target::Global).is_err() {
+                // The app has quit
+                return;
+            }
+        }
+    }
+}
+
+/// Only changes to the config file and to theme files are reloaded.
+/// Reading a file isn't a change, and temporary files are replaced by the real file when they are done.
+fn is_watched_file(kind: &EventKind, path: &Path) -> bool {
+    if matches!(kind, EventKind::Access(_)) {
+        return false;
+    }
+    if config::get_config_path().as_deref() == Some(path) {
+        return true;
+    }
+    path.parent() == theme::get_themes_dir().as_deref() && theme::is_theme_file(path)
+}
+
+/// Reloads the changed file. Called by the app delegate.
+///
+/// Problems with the file are shown in a banner, and the settings that could not be read use their defaults.
+pub fn handle_command(cmd: &Command, data: &mut AppState) -> Handled {
+    let path = match cmd.get(CONFIG_FILE_CHANGED) {
+        Some(path) => path,
+        None => return Handled::No,
+    };
+    let result = if config::get_config_path().as_deref() == Some(path.as_path()) {
+        reload_config(path, data)
+    } else {
+        theme::reload_theme_file(path, data).map(|_| Vec::new())
+    };
+    data.config_banner = match result {
+        Ok(warnings) if warnings.is_empty() => String::new(),
+        Ok(warnings) => format!("Some settings in {} were skipped:\n{}", path.display(), warnings.join("\n")),
+        Err(e) => format!("Could not reload {}: {}", path.display(), e),
+    };
+    Handled::Yes
+}
+
+/// Applies the config file. Returns the settings that were skipped.
+fn reload_config(path: &Path, data: &mut AppState) -> Result<Vec<String>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        // Removing the file keeps the current settings. They are written back on the next change.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    // Saving from the app also changes the file, but there is nothing new in it
+    if config::is_own_save(&text) {
+        return Ok(Vec::new());
+    }
+    let file = config::parse_layout_file(&text)?;
+    // Replacing the settings with equal ones would still lay out the whole timeline again
+    if !data.layout_settings.as_ref().same(&file.settings) {
+        data.layout_settings = sync::Arc::new(file.settings);
+    }
+    if let Some(theme) = file.theme {
+        if theme != data.theme {
+            data.theme = theme;
+        }
+    }
+    Ok(file.warnings)
+}
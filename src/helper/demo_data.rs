@@ -0,0 +1,264 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::{AppState, Message, MessageKind};
+use crate::helper::{emoji, timeline};
+
+/// How far apart the messages in a group are, at most
+const MAX_SECONDS_IN_GROUP: i64 = 30;
+
+const NOUNS: &[&str] = &[
+    "time", "person", "year", "way", "day", "thing", "man", "world", "life",
+    "hand", "part", "child", "eye", "woman", "place", "work", "week", "case",
+    "point",  "government", "company", "number", "group", "problem", "fact"
+];
+const VERBS: &[&str] = &[
+    "be", "have", "do", "say", "get", "make", "go", "know", "take", "see",
+    "come", "think", "look", "want", "give", "use", "find", "tell", "ask",
+    "work", "seem", "feel", "try", "leave", "call"
+];
+const ADJECTIVES: &[&str] = &[
+    "good", "new", "first", "long", "great", "little", "own", "other", "old",
+    "right", "big", "high", "different", "small", "large", "next", "early",
+    "important", "few", "public", "bad", "same", "able",
+];
+const PREPOSITIONS: &[&str] = &[
+    "to", "of", "in", "for", "on", "with", "at", "by", "from", "up", "about",
+    "info", "over", "after",
+];
+const OTHERS: &[&str] = &[
+    "the", "and", "a", "that", "I", "it", "not", "he", "as", "you", "this",
+    "but", "his", "they", "her", "she", "or", "an", "will", "my", "one",
+    "all", "would", "there", "their",
+];
+const ALL_WORD_LISTS: &[&[&str]] = &[
+    NOUNS, VERBS, ADJECTIVES, PREPOSITIONS, OTHERS,
+];
+const SINGLE_WORDS: &[&str] = &[
+    "Nice", "Thanks", "Okay", "ok", "Hey", "lol",
+];
+
+fn uppercase_first_letter(s: String) -> String {
+    let mut c = s.chars();
+    match c.next() {
+        None => String::new(),
+        Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+    }
+}
+
+/// A sentence of random words, with the length of a typical chat message
+pub fn generate_random_message(rng: &mut impl Rng, capital_probability: f64) -> String {
+    let mut msg_len = rng.gen_range(1..13);
+    if rng.gen_bool(0.1) {
+        msg_len *= 2;
+    } else if rng.gen_bool(0.1) {
+        msg_len *= 4;
+    }
+
+    let mut message = String::new();
+
+    if msg_len <= 3 {
+        if rng.gen_bool(0.5) {
+            if rng.gen_bool(0.5) {
+                message.push_str("Hi ");
+            } else {
+                message.push_str("Hello ");
+            }
+            if msg_len == 3 {
+                message.push_str(ADJECTIVES[rng.gen_range(0..ADJECTIVES.len())]);
+                message.push(' ');
+            }
+            if msg_len > 1 {
+                message.push_str(NOUNS[rng.gen_range(0..NOUNS.len())]);
+                message.push(' ');
+            }
+            return message;
+        } else if msg_len == 1 {
+            message.push_str(SINGLE_WORDS[rng.gen_range(0..SINGLE_WORDS.len())]);
+            return message;
+        }
+    }
+
+    for _i in 0..msg_len {
+        let word_list = ALL_WORD_LISTS[rng.gen_range(0..ALL_WORD_LISTS.len())];
+        message += word_list[rng.gen_range(0..word_list.len())];
+        message.push(' ');
+    }
+
+    if rng.gen_bool(0.4) {
+        let str = message[0..message.len() - 1].to_string();
+        message = str;
+        message.push('.');
+    }
+
+    if rng.gen_bool(capital_probability) {
+        uppercase_first_letter(message)
+    } else {
+        message
+    }
+}
+
+const LINK_MESSAGES: &[&str] = &[
+    "Have a look at https://example.com/docs/layouts?page=2&sort=new when you can",
+    "https://www.example.org/a/very/deeply/nested/path/to/some/page.html",
+    "The notes are at www.example.net/notes, near the bottom",
+];
+const EMOJI_SENTENCES: &[&str] = &[
+    "That went well 🎉",
+    "See you tomorrow 👋 don't be late 😅",
+    "Coffee first ☕ then the rest",
+];
+const CODE_SNIPPETS: &[&str] = &[
+    "```\nfn main() {\n    println!(\"Hello, world!\");\n}\n```",
+    "Try `cargo run --release -- --benchmark` instead",
+    "```\nfor item in items.iter().filter(|item| item.is_visible()) {\n    item.paint(ctx);\n}\n```",
+];
+/// Text in scripts other than Latin, including right to left ones
+const NON_LATIN_MESSAGES: &[&str] = &[
+    "こんにちは、元気ですか？",
+    "Привет, как дела?",
+    "مرحبا، كيف حالك؟",
+    "नमस्ते, आप कैसे हैं?",
+    "你好，今天的天气很好。",
+    "안녕하세요, 잘 지내세요?",
+    "Γεια σου, τι κάνεις;",
+    "שלום, מה שלומך?",
+];
+
+/// What to fill the timeline with when the app starts
+pub struct DemoOptions {
+    /// The same seed always gives the same timeline
+    pub seed: u64,
+    pub message_count: usize,
+    pub user_count: u32,
+    /// How long before the last message the first one was sent
+    pub time_span_seconds: i64,
+}
+
+/// Adds the demo messages to the end of the timeline, with the last one sent at the end time
+pub fn fill_timeline(state: &mut AppState, options: &DemoOptions, end_time: i64) {
+    for (user_id, message) in generate_messages(options, end_time) {
+        timeline::push_message(state, user_id, message);
+    }
+}
+
+/// Messages from random users, in groups of one to four, in the order they were sent.
+/// They are spread over the time span before the end time.
+pub fn generate_messages(options: &DemoOptions, end_time: i64) -> Vec<(u32, Message)> {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let count = options.message_count;
+    // Groups have two or three messages on average
+    let average_gap = options.time_span_seconds / (count as i64 * 2 / 5).max(1);
+    let mut time = end_time - options.time_span_seconds;
+    let mut messages = Vec::with_capacity(count);
+    while messages.len() < count {
+        let user_id = rng.gen_range(0..options.user_count);
+        let group_size = rng.gen_range(1..5).min(count - messages.len());
+        for i in 0..group_size {
+            let mut message = generate_message(&mut rng, i == 0, time.min(end_time));
+            // The last one is always sent at the end, so the newest message is from just now
+            if messages.len() + 1 == count {
+                message.timestamp_epoch_seconds = end_time;
+            }
+            messages.push((user_id, message));
+            time += rng.gen_range(1..=MAX_SECONDS_IN_GROUP);
+        }
+        time += rng.gen_range(0..=average_gap * 2);
+    }
+    messages
+}
+
+/// A message with any of the kinds of content that are hard to lay out, though most are plain sentences
+pub fn generate_message(rng: &mut impl Rng, is_first_in_group: bool, time: i64) -> Message {
+    let capital_probability = if is_first_in_group { 0.95 } else { 0.7 };
+    let (text, kind) = match rng.gen_range(0..100) {
+        0..=54 => (generate_random_message(rng, capital_probability), MessageKind::Text),
+        55..=61 => (pick(rng, LINK_MESSAGES).to_string(), MessageKind::Text),
+        62..=65 => (generate_jumbomoji(rng), MessageKind::Text),
+        66..=69 => (pick(rng, EMOJI_SENTENCES).to_string(), MessageKind::Text),
+        70..=75 => (pick(rng, CODE_SNIPPETS).to_string(), MessageKind::Text),
+        76..=82 => (generate_paragraphs(rng), MessageKind::Text),
+        83..=86 => (generate_unbroken_string(rng), MessageKind::Text),
+        87..=93 => (pick(rng, NON_LATIN_MESSAGES).to_string(), MessageKind::Text),
+        94..=96 => (generate_narrow_message(rng), MessageKind::Text),
+        _ => (generate_random_message(rng, 0.0), MessageKind::Action),
+    };
+    Message::new(text, time, kind)
+}
+
+fn pick<'a>(rng: &mut impl Rng, texts: &[&'a str]) -> &'a str {
+    texts[rng.gen_range(0..texts.len())]
+}
+
+/// One to three emoji on their own, which are shown enlarged
+fn generate_jumbomoji(rng: &mut impl Rng) -> String {
+    (0..rng.gen_range(1..=emoji::MAX_JUMBOMOJI_COUNT))
+        .map(|_| emoji::EMOJI[rng.gen_range(0..emoji::EMOJI.len())].emoji)
+        .collect()
+}
+
+/// Two to four paragraphs, separated by blank lines
+fn generate_paragraphs(rng: &mut impl Rng) -> String {
+    let paragraphs: Vec<String> = (0..rng.gen_range(2..=4))
+        .map(|_| {
+            let sentences: Vec<String> = (0..rng.gen_range(1..=3))
+                .map(|_| generate_random_message(rng, 1.0))
+                .collect();
+            sentences.join(" ")
+        })
+        .collect();
+    paragraphs.join("\n\n")
+}
+
+/// A long string without spaces, which can't be wrapped at a word
+fn generate_unbroken_string(rng: &mut impl Rng) -> String {
+    let length = rng.gen_range(60..200);
+    if rng.gen_bool(0.5) {
+        (0..length).map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap()).collect()
+    } else {
+        format!("N{}!", "o".repeat(length))
+    }
+}
+
+/// One word per line, so the message is narrow but tall
+fn generate_narrow_message(rng: &mut impl Rng) -> String {
+    let words: Vec<&str> = (0..rng.gen_range(5..13))
+        .map(|_| {
+            let word_list = ALL_WORD_LISTS[rng.gen_range(0..ALL_WORD_LISTS.len())];
+            word_list[rng.gen_range(0..word_list.len())]
+        })
+        .collect();
+    words.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(seed: u64) -> DemoOptions {
+        DemoOptions { seed, message_count: 200, user_count: 3, time_span_seconds: 24 * 60 * 60 }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_messages() {
+        let texts = |seed| -> Vec<(u32, String, i64)> {
+            generate_messages(&options(seed), 1_600_000_000).into_iter()
+                .map(|(user_id, message)| (user_id, message.message, message.timestamp_epoch_seconds))
+                .collect()
+        };
+        assert_eq!(texts(7), texts(7));
+        assert_ne!(texts(7), texts(8));
+    }
+
+    #[test]
+    fn messages_follow_the_options() {
+        let end_time = 1_600_000_000;
+        let options = options(1);
+        let messages = generate_messages(&options, end_time);
+        assert_eq!(messages.len(), options.message_count);
+        assert!(messages.iter().all(|(user_id, _)| *user_id < options.user_count));
+        let times: Vec<i64> = messages.iter().map(|(_, message)| message.timestamp_epoch_seconds).collect();
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(times[0] >= end_time - options.time_span_seconds);
+        assert_eq!(*times.last().unwrap(), end_time);
+    }
+}
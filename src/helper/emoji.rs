@@ -0,0 +1,463 @@
+/// The most emoji a message can have and still be shown enlarged.
+pub const MAX_JUMBOMOJI_COUNT: usize = 3;
+/// How many emoji are remembered in the recently used section of the picker.
+pub const MAX_RECENT_EMOJI: usize = 16;
+/// How many characters need to be typed after the colon before shortcodes are suggested.
+const MIN_SHORTCODE_SEARCH_LEN: usize = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EmojiCategory {
+    Smileys,
+    People,
+    Animals,
+    Food,
+    Activities,
+    Travel,
+    Objects,
+    Symbols,
+}
+
+impl EmojiCategory {
+    pub const ALL: [EmojiCategory; 8] = [
+        EmojiCategory::Smileys,
+        EmojiCategory::People,
+        EmojiCategory::Animals,
+        EmojiCategory::Food,
+        EmojiCategory::Activities,
+        EmojiCategory::Travel,
+        EmojiCategory::Objects,
+        EmojiCategory::Symbols,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmojiCategory::Smileys => "Smileys",
+            EmojiCategory::People => "People",
+            EmojiCategory::Animals => "Animals & Nature",
+            EmojiCategory::Food => "Food & Drink",
+            EmojiCategory::Activities => "Activities",
+            EmojiCategory::Travel => "Travel & Places",
+            EmojiCategory::Objects => "Objects",
+            EmojiCategory::Symbols => "Symbols",
+        }
+    }
+}
+
+pub struct Emoji {
+    pub emoji: &'static str,
+    /// The name used in `:shortcode:` form, without the colons
+    pub shortcode: &'static str,
+    /// Extra words that find the emoji when searching
+    pub keywords: &'static [&'static str],
+    pub category: EmojiCategory,
+}
+
+macro_rules! emoji {
+    ($emoji:expr, $shortcode:expr, $category:ident, [$($keyword:expr),*]) => {
+        Emoji { emoji: $emoji, shortcode: $shortcode, keywords: &[$($keyword),*], category: EmojiCategory::$category }
+    };
+}
+
+pub const EMOJI: &[Emoji] = &[
+    emoji!("😀", "grinning", Smileys, ["happy", "smile"]),
+    emoji!("😃", "smiley", Smileys, ["happy", "joy"]),
+    emoji!("😄", "smile", Smileys, ["happy", "joy", "laugh"]),
+    emoji!("😁", "grin", Smileys, ["happy", "teeth"]),
+    emoji!("😆", "laughing", Smileys, ["happy", "lol"]),
+    emoji!("😅", "sweat_smile", Smileys, ["relief", "phew"]),
+    emoji!("🤣", "rofl", Smileys, ["lol", "laugh", "floor"]),
+    emoji!("😂", "joy", Smileys, ["tears", "laugh", "lol"]),
+    emoji!("🙂", "slightly_smiling_face", Smileys, ["smile"]),
+    emoji!("🙃", "upside_down_face", Smileys, ["silly", "sarcasm"]),
+    emoji!("😉", "wink", Smileys, ["flirt"]),
+    emoji!("😊", "blush", Smileys, ["happy", "shy"]),
+    emoji!("😇", "innocent", Smileys, ["angel", "halo"]),
+    emoji!("🥰", "smiling_face_with_hearts", Smileys, ["love", "crush"]),
+    emoji!("😍", "heart_eyes", Smileys, ["love", "crush"]),
+    emoji!("😘", "kissing_heart", Smileys, ["kiss", "love"]),
+    emoji!("😋", "yum", Smileys, ["tasty", "delicious"]),
+    emoji!("😛", "stuck_out_tongue", Smileys, ["silly", "tongue"]),
+    emoji!("😜", "stuck_out_tongue_winking_eye", Smileys, ["silly", "joke"]),
+    emoji!("🤪", "zany_face", Smileys, ["crazy", "silly"]),
+    emoji!("🤔", "thinking", Smileys, ["hmm", "think"]),
+    emoji!("🤨", "raised_eyebrow", Smileys, ["suspicious", "skeptic"]),
+    emoji!("😐", "neutral_face", Smileys, ["meh"]),
+    emoji!("😑", "expressionless", Smileys, ["blank"]),
+    emoji!("😶", "no_mouth", Smileys, ["silent", "speechless"]),
+    emoji!("🙄", "roll_eyes", Smileys, ["eyeroll", "whatever"]),
+    emoji!("😏", "smirk", Smileys, ["smug"]),
+    emoji!("😬", "grimacing", Smileys, ["awkward", "eek"]),
+    emoji!("😌", "relieved", Smileys, ["calm", "content"]),
+    emoji!("😴", "sleeping", Smileys, ["tired", "zzz"]),
+    emoji!("😷", "mask", Smileys, ["sick", "ill"]),
+    emoji!("🤒", "face_with_thermometer", Smileys, ["sick", "fever"]),
+    emoji!("🥵", "hot_face", Smileys, ["heat", "sweating"]),
+    emoji!("🥶", "cold_face", Smileys, ["freezing", "ice"]),
+    emoji!("🤯", "exploding_head", Smileys, ["mind_blown", "shocked"]),
+    emoji!("🥳", "partying_face", Smileys, ["party", "celebrate"]),
+    emoji!("😎", "sunglasses", Smileys, ["cool"]),
+    emoji!("🤓", "nerd_face", Smileys, ["geek", "glasses"]),
+    emoji!("😕", "confused", Smileys, ["unsure"]),
+    emoji!("😟", "worried", Smileys, ["nervous", "concern"]),
+    emoji!("😮", "open_mouth", Smileys, ["wow", "surprise"]),
+    emoji!("😲", "astonished", Smileys, ["shocked", "wow"]),
+    emoji!("😳", "flushed", Smileys, ["embarrassed", "blush"]),
+    emoji!("🥺", "pleading_face", Smileys, ["please", "puppy_eyes"]),
+    emoji!("😢", "cry", Smileys, ["sad", "tear"]),
+    emoji!("😭", "sob", Smileys, ["sad", "crying"]),
+    emoji!("😱", "scream", Smileys, ["scared", "horror"]),
+    emoji!("😤", "triumph", Smileys, ["huff", "proud"]),
+    emoji!("😡", "rage", Smileys, ["angry", "mad"]),
+    emoji!("😠", "angry", Smileys, ["mad", "annoyed"]),
+    emoji!("💀", "skull", Smileys, ["dead", "dying"]),
+    emoji!("💩", "poop", Smileys, ["poo"]),
+    emoji!("🤡", "clown_face", Smileys, ["clown"]),
+    emoji!("👻", "ghost", Smileys, ["boo", "halloween"]),
+    emoji!("🤖", "robot", Smileys, ["bot", "machine"]),
+    emoji!("👋", "wave", People, ["hello", "hi", "bye"]),
+    emoji!("👍", "thumbsup", People, ["yes", "ok", "like", "+1"]),
+    emoji!("👎", "thumbsdown", People, ["no", "dislike", "-1"]),
+    emoji!("👌", "ok_hand", People, ["ok", "perfect"]),
+    emoji!("✌️", "v", People, ["peace", "victory"]),
+    emoji!("🤞", "crossed_fingers", People, ["luck", "hope"]),
+    emoji!("🤘", "metal", People, ["rock"]),
+    emoji!("👉", "point_right", People, ["right"]),
+    emoji!("👈", "point_left", People, ["left"]),
+    emoji!("👆", "point_up", People, ["up"]),
+    emoji!("👇", "point_down", People, ["down"]),
+    emoji!("👏", "clap", People, ["applause", "congrats"]),
+    emoji!("🙌", "raised_hands", People, ["hooray", "celebrate"]),
+    emoji!("🙏", "pray", People, ["please", "thanks", "hope"]),
+    emoji!("🤝", "handshake", People, ["deal", "agreement"]),
+    emoji!("💪", "muscle", People, ["strong", "flex"]),
+    emoji!("👀", "eyes", People, ["look", "see", "watching"]),
+    emoji!("🤷", "shrug", People, ["whatever", "dunno"]),
+    emoji!("🤦", "facepalm", People, ["ugh", "disbelief"]),
+    emoji!("🐶", "dog", Animals, ["puppy", "pet"]),
+    emoji!("🐱", "cat", Animals, ["kitten", "pet"]),
+    emoji!("🐭", "mouse", Animals, ["rodent"]),
+    emoji!("🦊", "fox_face", Animals, ["fox"]),
+    emoji!("🐻", "bear", Animals, ["teddy"]),
+    emoji!("🐼", "panda_face", Animals, ["panda"]),
+    emoji!("🐸", "frog", Animals, ["toad"]),
+    emoji!("🐵", "monkey_face", Animals, ["monkey"]),
+    emoji!("🐔", "chicken", Animals, ["bird", "hen"]),
+    emoji!("🐧", "penguin", Animals, ["bird", "linux"]),
+    emoji!("🦀", "crab", Animals, ["rust", "ferris"]),
+    emoji!("🐍", "snake", Animals, ["python"]),
+    emoji!("🦄", "unicorn", Animals, ["magic"]),
+    emoji!("🐝", "bee", Animals, ["honeybee", "insect"]),
+    emoji!("🌲", "evergreen_tree", Animals, ["tree", "forest"]),
+    emoji!("🌵", "cactus", Animals, ["desert", "plant"]),
+    emoji!("🌸", "cherry_blossom", Animals, ["flower", "spring"]),
+    emoji!("🌻", "sunflower", Animals, ["flower"]),
+    emoji!("🍁", "maple_leaf", Animals, ["autumn", "fall", "leaf"]),
+    emoji!("🍎", "apple", Food, ["fruit", "red"]),
+    emoji!("🍌", "banana", Food, ["fruit"]),
+    emoji!("🍉", "watermelon", Food, ["fruit", "summer"]),
+    emoji!("🍓", "strawberry", Food, ["fruit", "berry"]),
+    emoji!("🥑", "avocado", Food, ["guacamole"]),
+    emoji!("🍕", "pizza", Food, ["slice"]),
+    emoji!("🍔", "hamburger", Food, ["burger"]),
+    emoji!("🍟", "fries", Food, ["chips"]),
+    emoji!("🌮", "taco", Food, ["mexican"]),
+    emoji!("🍣", "sushi", Food, ["fish", "japanese"]),
+    emoji!("🍩", "doughnut", Food, ["donut", "sweet"]),
+    emoji!("🍪", "cookie", Food, ["biscuit", "sweet"]),
+    emoji!("🎂", "birthday", Food, ["cake", "party"]),
+    emoji!("☕", "coffee", Food, ["cafe", "drink", "morning"]),
+    emoji!("🍵", "tea", Food, ["drink", "green"]),
+    emoji!("🍺", "beer", Food, ["drink", "cheers"]),
+    emoji!("🍷", "wine_glass", Food, ["wine", "drink"]),
+    emoji!("⚽", "soccer", Activities, ["football", "ball", "sport"]),
+    emoji!("🏀", "basketball", Activities, ["ball", "sport"]),
+    emoji!("🎮", "video_game", Activities, ["controller", "gaming"]),
+    emoji!("🎲", "game_die", Activities, ["dice", "random"]),
+    emoji!("🎯", "dart", Activities, ["target", "bullseye"]),
+    emoji!("🎸", "guitar", Activities, ["music", "rock"]),
+    emoji!("🎨", "art", Activities, ["paint", "palette"]),
+    emoji!("🎉", "tada", Activities, ["party", "congrats", "celebrate"]),
+    emoji!("🎁", "gift", Activities, ["present", "birthday"]),
+    emoji!("🏆", "trophy", Activities, ["win", "award"]),
+    emoji!("🚗", "car", Travel, ["drive", "automobile"]),
+    emoji!("🚲", "bike", Travel, ["bicycle", "cycling"]),
+    emoji!("✈️", "airplane", Travel, ["flight", "plane", "travel"]),
+    emoji!("🚀", "rocket", Travel, ["launch", "ship", "space"]),
+    emoji!("🏠", "house", Travel, ["home"]),
+    emoji!("🏖️", "beach", Travel, ["vacation", "sun"]),
+    emoji!("🌍", "earth_africa", Travel, ["globe", "world"]),
+    emoji!("🌙", "crescent_moon", Travel, ["night", "moon"]),
+    emoji!("⭐", "star", Travel, ["favorite"]),
+    emoji!("☀️", "sunny", Travel, ["sun", "weather"]),
+    emoji!("🌧️", "cloud_with_rain", Travel, ["rain", "weather"]),
+    emoji!("⚡", "zap", Travel, ["lightning", "fast"]),
+    emoji!("🔥", "fire", Travel, ["lit", "hot", "flame"]),
+    emoji!("🌈", "rainbow", Travel, ["pride"]),
+    emoji!("❄️", "snowflake", Travel, ["snow", "cold", "winter"]),
+    emoji!("📱", "iphone", Objects, ["phone", "mobile"]),
+    emoji!("💻", "computer", Objects, ["laptop", "pc"]),
+    emoji!("⌨️", "keyboard", Objects, ["typing"]),
+    emoji!("📷", "camera", Objects, ["photo"]),
+    emoji!("💡", "bulb", Objects, ["idea", "light"]),
+    emoji!("📚", "books", Objects, ["read", "library"]),
+    emoji!("✏️", "pencil2", Objects, ["write", "edit"]),
+    emoji!("📎", "paperclip", Objects, ["attachment"]),
+    emoji!("🔒", "lock", Objects, ["secure", "private"]),
+    emoji!("🔑", "key", Objects, ["password", "unlock"]),
+    emoji!("🔨", "hammer", Objects, ["tool", "build"]),
+    emoji!("⏰", "alarm_clock", Objects, ["time", "wake"]),
+    emoji!("💰", "moneybag", Objects, ["money", "cash"]),
+    emoji!("📦", "package", Objects, ["box", "shipping"]),
+    emoji!("🔔", "bell", Objects, ["notification"]),
+    emoji!("❤️", "heart", Symbols, ["love", "like"]),
+    emoji!("🧡", "orange_heart", Symbols, ["love"]),
+    emoji!("💛", "yellow_heart", Symbols, ["love"]),
+    emoji!("💚", "green_heart", Symbols, ["love"]),
+    emoji!("💙", "blue_heart", Symbols, ["love"]),
+    emoji!("💜", "purple_heart", Symbols, ["love"]),
+    emoji!("🖤", "black_heart", Symbols, ["love"]),
+    emoji!("💔", "broken_heart", Symbols, ["sad", "breakup"]),
+    emoji!("💯", "100", Symbols, ["perfect", "score", "hundred"]),
+    emoji!("✅", "white_check_mark", Symbols, ["done", "yes", "check"]),
+    emoji!("❌", "x", Symbols, ["no", "cross", "wrong"]),
+    emoji!("❓", "question", Symbols, ["what", "huh"]),
+    emoji!("❗", "exclamation", Symbols, ["important", "bang"]),
+    emoji!("⚠️", "warning", Symbols, ["caution", "alert"]),
+    emoji!("🚫", "no_entry_sign", Symbols, ["forbidden", "stop"]),
+    emoji!("♻️", "recycle", Symbols, ["environment"]),
+    emoji!("✨", "sparkles", Symbols, ["shiny", "new", "magic"]),
+    emoji!("💤", "zzz", Symbols, ["sleep", "tired"]),
+    emoji!("🆗", "ok", Symbols, ["okay"]),
+];
+
+pub fn find_by_shortcode(shortcode: &str) -> Option<&'static Emoji> {
+    EMOJI.iter().find(|emoji| emoji.shortcode.eq_ignore_ascii_case(shortcode))
+}
+
+pub fn get_emoji_in_category(category: EmojiCategory) -> impl Iterator<Item = &'static Emoji> {
+    EMOJI.iter().filter(move |emoji| emoji.category == category)
+}
+
+/// Finds emoji whose shortcode or keywords match the query.
+///
+/// Shortcodes starting with the query come first, followed by the other matches.
+pub fn search(query: &str) -> Vec<&'static Emoji> {
+    let query = query.trim().trim_matches(':').to_lowercase();
+    if query.is_empty() {
+        return EMOJI.iter().collect();
+    }
+    let mut prefix_matches = Vec::new();
+    let mut other_matches = Vec::new();
+    for emoji in EMOJI {
+        if emoji.shortcode.starts_with(&query) {
+            prefix_matches.push(emoji);
+        } else if emoji.shortcode.contains(&query)
+            || emoji.keywords.iter().any(|keyword| keyword.starts_with(&query))
+        {
+            other_matches.push(emoji);
+        }
+    }
+    prefix_matches.append(&mut other_matches);
+    prefix_matches
+}
+
+/// Gets the partially typed `:shortcode` at the end of the text, without the colon.
+///
+/// Returns none when the end of the text is not a shortcode being typed.
+pub fn get_partial_shortcode(text: &str) -> Option<&str> {
+    let colon_index = text.rfind(':')?;
+    let partial = &text[colon_index + 1..];
+    let is_word_start = match text[..colon_index].chars().next_back() {
+        Some(previous) => previous.is_whitespace(),
+        None => true,
+    };
+    let is_shortcode_chars = partial.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-');
+    if is_word_start && is_shortcode_chars && partial.len() >= MIN_SHORTCODE_SEARCH_LEN {
+        Some(partial)
+    } else {
+        None
+    }
+}
+
+/// Replaces every known `:shortcode:` in the text with its emoji.
+/// Unknown shortcodes are left as they are.
+pub fn expand_shortcodes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        result.push_str(&rest[..start]);
+        let after_colon = &rest[start + 1..];
+        let expanded = after_colon.find(':').and_then(|end| {
+            find_by_shortcode(&after_colon[..end]).map(|emoji| (emoji, end))
+        });
+        match expanded {
+            Some((emoji, end)) => {
+                result.push_str(emoji.emoji);
+                rest = &after_colon[end + 1..];
+            },
+            None => {
+                result.push(':');
+                rest = after_colon;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Finds the known emoji in the text, in the order they appear. Each emoji is listed once, where it first appears.
+///
+/// This finds the expanded shortcodes as well as emoji that were completed or pasted into the composer.
+pub fn find_used_emoji(text: &str) -> Vec<&'static Emoji> {
+    let mut used: Vec<&'static Emoji> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        // Some emoji start with another, like "✌" and "✌️", so the longest match is used
+        let found = EMOJI.iter()
+            .filter(|known| rest.starts_with(known.emoji))
+            .max_by_key(|known| known.emoji.len());
+        match found {
+            Some(emoji) => {
+                if !used.iter().any(|existing| existing.emoji == emoji.emoji) {
+                    used.push(emoji);
+                }
+                rest = &rest[emoji.emoji.len()..];
+            },
+            None => rest = &rest[c.len_utf8()..],
+        }
+    }
+    used
+}
+
+/// Moves the emoji to the front of the recently used list.
+pub fn push_recent(recent: &mut druid::im::Vector<String>, emoji: &str) {
+    recent.retain(|existing| existing != emoji);
+    recent.push_front(emoji.to_string());
+    // im's truncate panics when the vector is shorter than the new length
+    if recent.len() > MAX_RECENT_EMOJI {
+        recent.truncate(MAX_RECENT_EMOJI);
+    }
+}
+
+/// Counts the emoji in the text, or returns none if there is anything other than emoji and whitespace.
+///
+/// Modifiers like skin tones and variation selectors, and emoji joined with a zero width joiner,
+/// are counted as part of the emoji before them. Two regional indicators make up one flag.
+pub fn count_emoji(text: &str) -> Option<usize> {
+    let mut count = 0;
+    let mut joining = false;
+    let mut unpaired_regional_indicator = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            joining = false;
+            unpaired_regional_indicator = false;
+            continue;
+        }
+        match c as u32 {
+            // Zero width joiner
+            0x200D => joining = true,
+            // Variation selectors, skin tones, and the keycap modifier
+            0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0x20E3 => {},
+            // Regional indicators, which come in pairs for flags
+            0x1F1E6..=0x1F1FF => {
+                if !unpaired_regional_indicator {
+                    count += 1;
+                }
+                unpaired_regional_indicator = !unpaired_regional_indicator;
+            },
+            code if is_emoji_code_point(code) => {
+                if !joining {
+                    count += 1;
+                }
+                joining = false;
+                unpaired_regional_indicator = false;
+            },
+            _ => return None,
+        }
+    }
+    Some(count)
+}
+
+fn is_emoji_code_point(code: u32) -> bool {
+    matches!(code,
+        0x1F000..=0x1FAFF
+        | 0x2600..=0x27BF
+        | 0x2300..=0x23FF
+        | 0x2B00..=0x2BFF
+        | 0x2190..=0x21FF
+        | 0x3030 | 0x303D | 0x3297 | 0x3299
+        | 0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139
+    )
+}
+
+/// Messages with only one to three emoji are shown enlarged.
+pub fn is_jumbomoji(text: &str) -> bool {
+    match count_emoji(text) {
+        Some(count) => count > 0 && count <= MAX_JUMBOMOJI_COUNT,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcodes_are_expanded() {
+        assert_eq!(expand_shortcodes("well done :tada:"), "well done 🎉");
+        assert_eq!(expand_shortcodes(":TADA:"), "🎉");
+        assert_eq!(expand_shortcodes(":no_such_emoji: here"), ":no_such_emoji: here");
+        assert_eq!(expand_shortcodes(":tada::fire:"), "🎉🔥");
+        assert_eq!(expand_shortcodes("::tada:"), ":🎉");
+        assert_eq!(expand_shortcodes(":unknown:tada:"), ":unknown🎉");
+        assert_eq!(expand_shortcodes("at 12:30:45"), "at 12:30:45");
+        assert_eq!(expand_shortcodes("a lone : colon"), "a lone : colon");
+        assert_eq!(expand_shortcodes(":tada"), ":tada");
+    }
+
+    #[test]
+    fn partial_shortcodes_are_found_at_the_end() {
+        assert_eq!(get_partial_shortcode("hello :ta"), Some("ta"));
+        assert_eq!(get_partial_shortcode(":thumbsu"), Some("thumbsu"));
+        assert_eq!(get_partial_shortcode(":tada: :fi"), Some("fi"));
+        // Too short to search for
+        assert_eq!(get_partial_shortcode("hello :t"), None);
+        // Already finished
+        assert_eq!(get_partial_shortcode("done :tada:"), None);
+        // Not at the start of a word, like in times
+        assert_eq!(get_partial_shortcode("at 12:30"), None);
+        assert_eq!(get_partial_shortcode("::tada"), None);
+        assert_eq!(get_partial_shortcode(":ta da"), None);
+        assert_eq!(get_partial_shortcode("no colon"), None);
+    }
+
+    #[test]
+    fn emoji_are_counted() {
+        assert_eq!(count_emoji(""), Some(0));
+        assert_eq!(count_emoji("👍"), Some(1));
+        assert_eq!(count_emoji("👍 🎉🔥"), Some(3));
+        assert_eq!(count_emoji("❤️"), Some(1));
+        assert_eq!(count_emoji("👍🏽"), Some(1));
+        assert_eq!(count_emoji("👨\u{200D}👩\u{200D}👧"), Some(1));
+        assert_eq!(count_emoji("🇫🇷🇯🇵"), Some(2));
+        assert_eq!(count_emoji("hi 👍"), None);
+        assert!(is_jumbomoji("🎉🎉🎉"));
+        assert!(!is_jumbomoji("🎉🎉🎉🎉"));
+        assert!(!is_jumbomoji(" "));
+    }
+
+    #[test]
+    fn used_emoji_are_found_in_order() {
+        let used: Vec<&str> = find_used_emoji("🔥 then 🎉, ✌️ and 🔥 again").iter().map(|emoji| emoji.shortcode).collect();
+        assert_eq!(used, ["fire", "tada", "v"]);
+        let mut recent = druid::im::Vector::new();
+        for emoji in find_used_emoji(&expand_shortcodes(":fire: :tada:")) {
+            push_recent(&mut recent, emoji.emoji);
+        }
+        assert_eq!(recent, druid::im::vector!["🎉".to_string(), "🔥".to_string()]);
+        for emoji in EMOJI {
+            push_recent(&mut recent, emoji.emoji);
+        }
+        assert_eq!(recent.len(), MAX_RECENT_EMOJI);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,96 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use druid::{Command, ExtEventSink, Handled, Selector, Target};
+use rand::Rng;
+use crate::{AppState, Message};
+use crate::helper::{demo_data, timeline};
+
+/// Sent by the flood thread with the messages that are due. The payload is the sender and message of each.
+pub const FLOOD_MESSAGES: Selector<Vec<(u32, Message)>> = Selector::new("polysoft.druid-demo.flood_messages");
+
+/// The shortest time the thread sleeps. Faster rates send several messages at once.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+/// How likely a message is from the same user as the one before, so groups form as well
+const SAME_USER_PROBABILITY: f64 = 0.5;
+
+/// Makes up messages from random users at a steady rate
+struct FloodGenerator {
+    messages_per_second: f64,
+    user_count: u32,
+    last_user_id: Option<u32>,
+    /// How many messages have been made so far
+    sent: u64,
+}
+
+impl FloodGenerator {
+    /// The messages that are due after the time has passed since the start.
+    /// Counting from the start keeps the rate steady, even when the thread wakes up late.
+    fn generate_due(&mut self, rng: &mut impl Rng, elapsed: Duration, timestamp: i64) -> Vec<(u32, Message)> {
+        let due = (elapsed.as_secs_f64() * self.messages_per_second) as u64;
+        let mut messages = Vec::new();
+        while self.sent < due {
+            let user_id = match self.last_user_id {
+                Some(user_id) if rng.gen_bool(SAME_USER_PROBABILITY) => user_id,
+                _ => rng.gen_range(0..self.user_count),
+            };
+            let is_first_in_group = self.last_user_id != Some(user_id);
+            messages.push((user_id, demo_data::generate_message(rng, is_first_in_group, timestamp)));
+            self.last_user_id = Some(user_id);
+            self.sent += 1;
+        }
+        messages
+    }
+}
+
+/// Starts sending messages to the app, to see how it copes with a busy chat.
+///
+/// The messages are made on a separate thread, so only adding them to the timeline is done on the UI thread.
+/// The thread stops when the app quits.
+pub fn start_flood(sink: ExtEventSink, messages_per_second: f64, user_count: u32) {
+    let mut generator = FloodGenerator { messages_per_second, user_count, last_user_id: None, sent: 0 };
+    let interval = Duration::from_secs_f64(1.0 / messages_per_second).max(MIN_INTERVAL);
+    thread::spawn(move || {
+        let mut rng = rand::thread_rng();
+        let start = Instant::now();
+        loop {
+            thread::sleep(interval);
+            let now = chrono::offset::Local::now().timestamp();
+            let messages = generator.generate_due(&mut rng, start.elapsed(), now);
+            if !messages.is_empty() && sink.submit_command(FLOOD_MESSAGES, messages, Target::Global).is_err() {
+                // The app has quit
+                return;
+            }
+        }
+    });
+}
+
+/// Adds the flooded messages to the timeline. Called by the app delegate.
+pub fn handle_command(cmd: &Command, data: &mut AppState) -> Handled {
+    match cmd.get(FLOOD_MESSAGES) {
+        Some(messages) => {
+            for (user_id, message) in messages {
+                timeline::push_message(data, *user_id, message.clone());
+            }
+            Handled::Yes
+        },
+        None => Handled::No,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn messages_are_made_at_the_rate() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut generator = FloodGenerator { messages_per_second: 4.0, user_count: 3, last_user_id: None, sent: 0 };
+        assert!(generator.generate_due(&mut rng, Duration::from_millis(200), 0).is_empty());
+        let messages = generator.generate_due(&mut rng, Duration::from_millis(2500), 0);
+        assert_eq!(messages.len(), 10);
+        assert!(messages.iter().all(|(user_id, _)| *user_id < 3));
+        assert_eq!(generator.generate_due(&mut rng, Duration::from_secs(3), 0).len(), 2);
+    }
+}
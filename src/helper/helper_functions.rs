@@ -11,7 +11,43 @@ pub fn to_full_height_area(width: f64) -> BoxConstraints {
     )
 }
 
-#[derive(Clone, Copy, PartialEq, druid::Data, num_derive::FromPrimitive)]
+/// Gets the name that is shown for a user.
+///
+/// The self user can pick their own name with /nick. Everyone else is named after their ID.
+pub fn get_display_name(user_id: u32, env: &druid::Env) -> String {
+    format_display_name(
+        user_id,
+        env.get(crate::SELF_USER_ID_KEY) as u32,
+        &env.get(crate::SELF_DISPLAY_NAME_KEY)
+    )
+}
+
+/// Same as [get_display_name], but for when the names are not in the env.
+pub fn format_display_name(user_id: u32, self_id: u32, self_name: &str) -> String {
+    if self_id == user_id && !self_name.is_empty() {
+        self_name.to_string()
+    } else {
+        let mut username = "User".to_string();
+        username.push_str(user_id.to_string().as_str());
+        username
+    }
+}
+
+/// Finds the links in a message. A link is a word starting with http://, https://, or www.
+pub fn find_urls(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '<', '"', '\'']))
+        .map(|word| word.trim_end_matches(|c| ".,!?:;)]}>\"'".contains(c)))
+        .filter(|word| {
+            let lowercase = word.to_lowercase();
+            (lowercase.starts_with("http://") || lowercase.starts_with("https://") || lowercase.starts_with("www."))
+                && word.len() > "www.".len()
+        })
+        .map(|word| word.to_string())
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, druid::Data, num_derive::FromPrimitive, serde::Serialize, serde::Deserialize)]
 
 pub enum TimestampFormat {
     TimeOnlyAmPm = 0,
@@ -98,4 +134,11 @@ pub fn timestamp_to_display_msg(epoch: i64, format: TimestampFormat) -> String {
         chrono::LocalResult::Ambiguous(_a, _b) => { return "Amiguous".to_string(); },
         chrono::LocalResult::None => { return "Invalid Time".to_string(); },
     }
-}
\ No newline at end of file
+}
+/// Reads a memory figure of this process, such as `VmRSS` for the memory in use now,
+/// or `VmHWM` for the most it has used so far. Only known on Linux.
+pub fn get_process_memory_kb(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field) && line[field.len()..].starts_with(':'))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
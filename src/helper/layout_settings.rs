@@ -1,3 +1,4 @@
+use std::sync;
 use druid;
 use druid::{BoxConstraints, Size, Point};
 use super::helper_functions::{self, TimestampFormat};
@@ -5,25 +6,7 @@ use crate::widgets::timeline_item_widget::{PictureShape, TailShape, ItemLayoutOp
 
 const DOT_SPACING: f64 = 5.0;
 
-const DARK_ON_DARK_COLOR: SimpleColor = SimpleColor { r: 175, g: 175, b: 175 };
-const DARK_ON_MEDIUM_COLOR: SimpleColor = SimpleColor { r: 200, g: 200, b: 200 };
-const DARK_ON_BLUE_COLOR: SimpleColor = SimpleColor { r: 210, g: 230, b: 255 };
-const WHITE_COLOR: SimpleColor = SimpleColor { r: 255, g: 255, b: 255 };
-
-#[derive(Clone, druid::Data, druid::Lens)]
-pub struct SimpleColor {
-    r: u8,
-    g: u8,
-    b: u8,
-}
-
-impl SimpleColor {
-    pub fn to_druid_color(&self) -> druid::Color {
-        druid::Color::rgb8(self.r, self.g, self.b)
-    }
-}
-
-#[derive(Clone, druid::Data, druid::Lens)]
+#[derive(Clone, Debug, druid::Data, druid::Lens, serde::Serialize, serde::Deserialize)]
 pub struct LayoutSettings {
     /// General layout
     pub item_layout: ItemLayoutOption,
@@ -70,11 +53,6 @@ pub struct LayoutSettings {
     pub metadata_font_bolded: bool,
     pub datetime_format: TimestampFormat,
     pub side_time_format: TimestampFormat,
-    pub sender_color: SimpleColor,
-    pub datetime_color: SimpleColor,
-    /// For when the self bubble's color messes with the visibility of the text
-    pub self_sender_color: SimpleColor,
-    pub self_datetime_color: SimpleColor,
     /// How far to move the left meta (time) to left of message
     pub left_meta_offset: f64,
     /// How wide should be required for it to no longer be stacked.
@@ -83,29 +61,6 @@ pub struct LayoutSettings {
     pub irc_header_width: f64,
 }
 
-
-#[derive(Clone, Copy, PartialEq, druid::Data)]
-pub enum PredefinedLayout {
-    ModernHangouts,
-    ModernBubble,
-    LargeBubble,
-    OldHangouts,
-    Telegram,
-    IMessage,
-    OldKik,
-    TearDrop,
-    Tailless,
-    Relaxed,
-    OtherBubble,
-    Slack,
-    Discord,
-    CompactDiscord,
-    Compact,
-    IRC,
-    LargeIRC,
-    SpacedIRC,
-}
-
 impl LayoutSettings {
     pub fn default() -> LayoutSettings {
         LayoutSettings {
@@ -136,85 +91,22 @@ impl LayoutSettings {
             left_meta_offset: 2.0,
             irc_stack_width: 400.0,
             irc_header_width: 160.0,
-            sender_color: SimpleColor { r: 175, g: 175, b: 175 },
-            datetime_color: SimpleColor { r: 175, g: 175, b: 175 },
-            self_sender_color: SimpleColor { r: 175, g: 175, b: 175 },
-            self_datetime_color: SimpleColor { r: 175, g: 175, b: 175 },
         }
     }
 
-    pub fn from_env(env: &druid::Env) -> LayoutSettings{
-        let sender_color = env.get(crate::SENDER_COLOR_KEY).as_rgba8();
-        let datetime_color = env.get(crate::DATETIME_COLOR_KEY).as_rgba8();
-        let self_datetime_color = env.get(crate::SELF_DATETIME_COLOR_KEY).as_rgba8();
-        let self_sender_color = env.get(crate::SELF_SENDER_COLOR_KEY).as_rgba8();
-        LayoutSettings {
-            item_layout: num_traits::FromPrimitive::from_u64(env.get(crate::ITEM_LAYOUT_KEY)).expect("Invalid layout index"),
-            metadata_layout: num_traits::FromPrimitive::from_u64(env.get(crate::METADATA_LAYOUT_KEY)).expect("Invalid layout index"),
-            picture_shape: num_traits::FromPrimitive::from_u64(env.get(crate::PICTURE_SHAPE_KEY)).expect("Invalid picture shape index"),
-            picture_size: env.get(crate::PICTURE_SIZE_KEY),
-            chat_bubble_tail_shape: num_traits::FromPrimitive::from_u64(env.get(crate::CHAT_BUBBLE_TAIL_SHAPE_KEY)).expect("Invalid bubble tail shape index"),
-            chat_bubble_tail_size: env.get(crate::CHAT_BUBBLE_TAIL_SIZE_KEY),
-            chat_bubble_radius: env.get(crate::CHAT_BUBBLE_RADIUS_KEY),
-            chat_picture_spacing: env.get(crate::CHAT_BUBBLE_IMG_SPACING_KEY),
-            show_self_pic: env.get(crate::SHOW_SELF_PROFILE_PIC_KEY),
-            bubble_padding: env.get(crate::BUBBLE_PADDING_KEY),
-            metadata_content_spacing: env.get(crate::METADATA_CONTENT_SPACING_KEY),
-            align_to_picture: env.get(crate::ALIGN_TO_PICTURE),
-            group_spacing: env.get(crate::GROUP_SPACING_KEY),
-            single_message_spacing: env.get(crate::SINGLE_MESSAGE_SPACING_KEY),
-            show_left_line: env.get(crate::SHOW_LEFT_LINE_KEY),
-            left_spacing: env.get(crate::LEFT_SPACING_KEY),
-            left_bubble_flipped: env.get(crate::LEFT_BUBBLE_FLIPPED_KEY),
-            right_bubble_flipped: env.get(crate::RIGHT_BUBBLE_FLIPPED_KEY),
-            content_font_size: env.get(crate::CONTENT_FONT_SIZE_KEY),
-            sender_font_size: env.get(crate::SENDER_FONT_SIZE_KEY),
-            datetime_font_size: env.get(crate::DATETIME_FONT_SIZE_KEY),
-            metadata_font_bolded: env.get(crate::HEADER_FONT_BOLDED_KEY),
-            datetime_format: num_traits::FromPrimitive::from_u64(env.get(crate::DATETIME_FORMAT_KEY)).expect("Invalid datetime format index"),
-            side_time_format: num_traits::FromPrimitive::from_u64(env.get(crate::SIDE_TIME_FORMAT_KEY)).expect("Invalid side time format index"),
-            left_meta_offset: env.get(crate::LEFT_META_OFFSET_KEY),
-            irc_stack_width: env.get(crate::IRC_STACK_WIDTH_KEY),
-            irc_header_width: env.get(crate::IRC_HEADER_WIDTH_KEY),
-            sender_color: SimpleColor { r: sender_color.0, g: sender_color.1, b: sender_color.2 },
-            datetime_color: SimpleColor { r: datetime_color.0, g: datetime_color.1, b: datetime_color.2 },
-            self_datetime_color: SimpleColor { r: self_datetime_color.0, g: self_datetime_color.1, b: self_datetime_color.2 },
-            self_sender_color: SimpleColor { r: self_sender_color.0, g: self_sender_color.1, b: self_sender_color.2 },
-        }
+    /// Gets the settings shared through the env. It doesn't copy them.
+    pub fn from_env(env: &druid::Env) -> sync::Arc<LayoutSettings> {
+        env.get(crate::LAYOUT_SETTINGS_KEY)
     }
 
-    pub fn set_env(&self, env: &mut druid::Env) {
-        env.set(crate::ITEM_LAYOUT_KEY, self.item_layout as u64);
-        env.set(crate::METADATA_LAYOUT_KEY, self.metadata_layout as u64);
-        env.set(crate::PICTURE_SHAPE_KEY, self.picture_shape as u64);
-        env.set(crate::PICTURE_SIZE_KEY, self.picture_size as f64);
-        env.set(crate::CHAT_BUBBLE_TAIL_SHAPE_KEY, self.chat_bubble_tail_shape as u64);
-        env.set(crate::CHAT_BUBBLE_TAIL_SIZE_KEY, self.chat_bubble_tail_size as f64);
-        env.set(crate::CHAT_BUBBLE_RADIUS_KEY, self.chat_bubble_radius as f64);
-        env.set(crate::CHAT_BUBBLE_IMG_SPACING_KEY, self.chat_picture_spacing as f64);
-        env.set(crate::SHOW_SELF_PROFILE_PIC_KEY, self.show_self_pic);
-        env.set(crate::BUBBLE_PADDING_KEY, self.bubble_padding as f64);
-        env.set(crate::METADATA_CONTENT_SPACING_KEY, self.metadata_content_spacing as f64);
-        env.set(crate::ALIGN_TO_PICTURE, self.align_to_picture as bool);
-        env.set(crate::GROUP_SPACING_KEY, self.group_spacing as f64);
-        env.set(crate::SINGLE_MESSAGE_SPACING_KEY, self.single_message_spacing as f64);
-        env.set(crate::SHOW_LEFT_LINE_KEY, self.show_left_line as bool);
-        env.set(crate::LEFT_SPACING_KEY, self.left_spacing as f64);
-        env.set(crate::LEFT_BUBBLE_FLIPPED_KEY, self.left_bubble_flipped as bool);
-        env.set(crate::RIGHT_BUBBLE_FLIPPED_KEY, self.right_bubble_flipped as bool);
-        env.set(crate::CONTENT_FONT_SIZE_KEY, self.content_font_size as f64);
-        env.set(crate::SENDER_FONT_SIZE_KEY, self.sender_font_size as f64);
-        env.set(crate::DATETIME_FONT_SIZE_KEY, self.datetime_font_size as f64);
-        env.set(crate::HEADER_FONT_BOLDED_KEY, self.metadata_font_bolded as bool);
-        env.set(crate::DATETIME_FORMAT_KEY, self.datetime_format as u64);
-        env.set(crate::SIDE_TIME_FORMAT_KEY, self.side_time_format as u64);
-        env.set(crate::LEFT_META_OFFSET_KEY, self.left_meta_offset);
-        env.set(crate::IRC_STACK_WIDTH_KEY, self.irc_stack_width);
-        env.set(crate::IRC_HEADER_WIDTH_KEY, self.irc_header_width);
-        env.set(crate::SENDER_COLOR_KEY, self.sender_color.to_druid_color());
-        env.set(crate::DATETIME_COLOR_KEY, self.datetime_color.to_druid_color());
-        env.set(crate::SELF_DATETIME_COLOR_KEY, self.self_datetime_color.to_druid_color());
-        env.set(crate::SELF_SENDER_COLOR_KEY, self.self_sender_color.to_druid_color());
+    /// Shares the settings through the env, along with the few values that druid's widgets read by key
+    pub fn set_env(settings: &sync::Arc<LayoutSettings>, env: &mut druid::Env) {
+        env.set(crate::LAYOUT_SETTINGS_KEY, settings.clone());
+        env.set(crate::GROUP_SPACING_KEY, settings.group_spacing);
+        env.set(crate::SINGLE_MESSAGE_SPACING_KEY, settings.single_message_spacing);
+        env.set(crate::CONTENT_FONT_SIZE_KEY, settings.content_font_size);
+        env.set(crate::SENDER_FONT_SIZE_KEY, settings.sender_font_size);
+        env.set(crate::DATETIME_FONT_SIZE_KEY, settings.datetime_font_size);
     }
 
     /// Gets the font for the title
@@ -253,6 +145,11 @@ impl LayoutSettings {
         }
     }
 
+    /// Only the self user's bubbles are on the right side.
+    pub fn is_right_aligned(&self, is_self_user: bool) -> bool {
+        is_self_user && self.is_bubble()
+    }
+
     /// Gets the proper width of a profile picture
     /// If there is no profile picture given the current situation or setting, it returns 0.0
     pub fn actual_profile_pic_width(&self, is_self_user: bool) -> f64 {
@@ -294,6 +191,12 @@ impl LayoutSettings {
         self.item_layout == ItemLayoutOption::IRCStyle && width_available > self.irc_stack_width
     }
 
+    /// Where the content starts when it's beside the IRC header.
+    /// Usually that's the header width, but a large picture can take up more than the header.
+    pub fn get_irc_content_x(&self, is_self_user: bool) -> f64 {
+        self.irc_header_width.max(self.get_profile_pic_area_width(is_self_user))
+    }
+
     /// The area that the content can take up.
     /// 
     /// Under most layouts, that's the total width minus the space taken up by
@@ -303,7 +206,7 @@ impl LayoutSettings {
     pub fn get_available_content_width(&self, width_available: f64, is_self_user: bool) -> f64 {
         let mut width: f64 = width_available;
         width -= if self.is_side_by_side(width_available) {
-            self.irc_header_width
+            self.get_irc_content_x(is_self_user)
         } else {
             self.get_profile_pic_area_width(is_self_user)
         };
@@ -314,7 +217,8 @@ impl LayoutSettings {
             // Leave room for left labels
             width -= 25.0;
         }
-        width
+        // Large pictures and padding can take up more than a narrow window has
+        width.max(0.0)
     }
 
     /// Returns the available bounding area for the content.
@@ -331,7 +235,8 @@ impl LayoutSettings {
     /// the width is either the total width minus the left spacing, or the IRC width minus the picture size
     pub fn get_sender_label_area(&self, width_available: f64) -> BoxConstraints {
         let width = if self.is_side_by_side(width_available) {
-            self.irc_header_width - self.picture_size
+            // The picture can be wider than the header
+            (self.irc_header_width - self.picture_size).max(0.0)
         } else {
             width_available
         };
@@ -347,7 +252,7 @@ impl LayoutSettings {
     pub fn get_unpadded_content_x_left_position(&self, is_self_user: bool, width_available: f64,
         actual_max_content_width: f64, total_metadata_width: f64) -> f64
     {
-        if is_self_user && self.is_bubble() { // Only shift if using a bubble layout
+        if self.is_right_aligned(is_self_user) { // Only shift if using a bubble layout
             let required_width = if self.item_layout != ItemLayoutOption::BubbleExternBottomMeta
                 && total_metadata_width > actual_max_content_width
             {
@@ -356,9 +261,11 @@ impl LayoutSettings {
             } else {
                 actual_max_content_width
             };
-            // Offset so that the profile pic is pushed all the way to the right
-            width_available - required_width
-                - self.bubble_padding * 2.0 - self.get_profile_pic_area_width(is_self_user)
+            // Offset so that the profile pic is pushed all the way to the right.
+            // The left spacing is inside the bubble, so it moves the bubble left too.
+            // Negative spacing can pull the bubble under the picture, but not past the edge.
+            width_available - required_width - self.left_spacing
+                - self.bubble_padding * 2.0 - self.get_profile_pic_area_width(is_self_user).max(0.0)
         } else {
             // Push to right of profile pic
             self.get_profile_pic_area_width(is_self_user)
@@ -373,6 +280,12 @@ impl LayoutSettings {
         let content_x_start = self.get_unpadded_content_x_left_position(
             is_self_user, width_available, widest_msg_content, total_metadata_width
         ) + self.left_spacing;
+        // Content below the metadata can go all the way to the left, but only if it then stays clear of the picture
+        let stacked_x_start = if self.actual_profile_pic_width(is_self_user) <= metadata_height + self.metadata_content_spacing {
+            0.0
+        } else {
+            content_x_start
+        };
         match self.item_layout {
             ItemLayoutOption::BubbleExternBottomMeta | ItemLayoutOption::BubbleInternalBottomMeta => {
                 Point::new(
@@ -393,10 +306,10 @@ impl LayoutSettings {
                 // else stack them
                 if self.is_side_by_side(width_available) {
                     // The msg content is to the right of the metadata
-                    Point::new(self.irc_header_width, y_top_offset)
+                    Point::new(self.get_irc_content_x(is_self_user), y_top_offset)
                 } else {
                     // Stacked, with picture above instead of to side, since this is the most compact layout
-                    Point::new(0.0, metadata_height + self.metadata_content_spacing + y_top_offset)
+                    Point::new(stacked_x_start, metadata_height + self.metadata_content_spacing + y_top_offset)
                 }
             },
             _ => {
@@ -406,7 +319,7 @@ impl LayoutSettings {
                     if self.align_to_picture {
                         content_x_start // Nothing special. Just aligned to context x start.
                     } else {
-                        0.0 // All the way to the left
+                        stacked_x_start // All the way to the left, if the picture allows it
                     },
                     // Just below the metadata
                     metadata_height + self.metadata_content_spacing + y_top_offset
@@ -421,7 +334,7 @@ impl LayoutSettings {
         sender_width: f64, datetime_width: f64, total_msg_height: f64, widest_msg_width: f64,
         y_top_offset: f64) -> Point
     {
-        let total_metadata_width = sender_width + datetime_width;
+        let total_metadata_width = self.get_metadata_width(sender_width, datetime_width);
         let msg_x_start = self.get_unpadded_content_x_left_position(is_self_user, width_available, 
             widest_msg_width, total_metadata_width);
         match self.item_layout {
@@ -458,6 +371,15 @@ impl LayoutSettings {
         }
     }
 
+    /// The width of the sender and the datetime side by side, including the dot between them
+    pub fn get_metadata_width(&self, sender_width: f64, datetime_width: f64) -> f64 {
+        if self.metadata_layout == MetadataLayout::LeftSideBySideWithDot {
+            sender_width + DOT_SPACING + datetime_width
+        } else {
+            sender_width + datetime_width
+        }
+    }
+
     /// Gets the origin of the datetime.
     /// It goes alongside the sender label, depending on the layout
     pub fn get_datetime_origin(&self, width_available: f64, widest_msg_width: f64, sender_label_origin: &Point,
@@ -489,11 +411,11 @@ impl LayoutSettings {
     }
 
     /// Gets the total height of a timeline item widget.
-    /// Accounts for everything, including layout, content sizes, other label sizes, and padding.
-    pub fn get_total_height(&self, width_available: f64, sender_label_size: &Size,
+    /// Accounts for everything, including layout, content sizes, other label sizes, padding, and the picture.
+    pub fn get_total_height(&self, is_self_user: bool, width_available: f64, sender_label_size: &Size,
         msg_label_size: &Size, y_top_offset: f64) -> f64
     {
-        if self.is_side_by_side(width_available) {
+        let height = if self.is_side_by_side(width_available) {
             sender_label_size.height.max(msg_label_size.height) + y_top_offset
         } else {
             y_top_offset + msg_label_size.height + sender_label_size.height + self.metadata_content_spacing + if self.is_bubble() {
@@ -501,7 +423,9 @@ impl LayoutSettings {
             } else {
                 0.0 // Not a bubble, so no padding.
             }
-        }
+        };
+        // A short message shouldn't let the picture hang over the next item
+        height.max(self.actual_profile_pic_width(is_self_user))
     }
 
     pub fn show_picture(&self, is_self_user: bool) -> bool {
@@ -536,546 +460,195 @@ impl LayoutSettings {
         }
     }
 
-    pub fn get_datetime_color(&self, is_self_user: bool) -> druid::Color {
-        if is_self_user {
-            self.self_datetime_color.to_druid_color()
-        } else {
-            self.datetime_color.to_druid_color()
+    /// Checks that every value is in the range that the settings allow.
+    /// Used to check the predefined layouts, which don't go through the sliders.
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("picture_size", self.picture_size),
+            ("chat_bubble_tail_size", self.chat_bubble_tail_size),
+            ("chat_bubble_radius", self.chat_bubble_radius),
+            ("chat_picture_spacing", self.chat_picture_spacing),
+            ("bubble_padding", self.bubble_padding),
+            ("metadata_content_spacing", self.metadata_content_spacing),
+            ("group_spacing", self.group_spacing),
+            ("single_message_spacing", self.single_message_spacing),
+            ("left_spacing", self.left_spacing),
+            ("content_font_size", self.content_font_size),
+            ("sender_font_size", self.sender_font_size),
+            ("datetime_font_size", self.datetime_font_size),
+            ("left_meta_offset", self.left_meta_offset),
+            ("irc_stack_width", self.irc_stack_width),
+            ("irc_header_width", self.irc_header_width),
+        ];
+        for (name, value) in values {
+            if let Some((min, max)) = get_range(name) {
+                if !(min..=max).contains(&value) {
+                    return Err(format!("{} is {}, but it must be between {} and {}", name, value, min, max));
+                }
+            }
         }
+        Ok(())
     }
+}
 
-    // Note: In the future, this could be determined by the server's preferences
-    pub fn get_sender_color(&self, is_self_user: bool) -> druid::Color {
-        if is_self_user {
-            self.self_sender_color.to_druid_color()
-        } else {
-            self.sender_color.to_druid_color()
+/// The smallest and largest value of each size setting
+const RANGES: [(&str, f64, f64); 15] = [
+    ("picture_size", 10.0, 100.0),
+    ("chat_bubble_tail_size", 2.0, 12.0),
+    ("chat_bubble_radius", 0.0, 12.0),
+    ("chat_picture_spacing", -15.0, 20.0),
+    ("bubble_padding", 0.0, 15.0),
+    ("metadata_content_spacing", 0.0, 10.0),
+    ("group_spacing", 0.0, 28.0),
+    ("single_message_spacing", 0.0, 28.0),
+    ("left_spacing", 0.0, 10.0),
+    ("content_font_size", 6.0, 40.0),
+    ("sender_font_size", 6.0, 40.0),
+    ("datetime_font_size", 6.0, 40.0),
+    ("left_meta_offset", 0.0, 30.0),
+    ("irc_stack_width", 100.0, 1000.0),
+    ("irc_header_width", 20.0, 250.0),
+];
+
+/// Gets the smallest and largest value a size setting can have, by the name it is saved with.
+/// Returns none for settings that aren't sizes.
+pub fn get_range(name: &str) -> Option<(f64, f64)> {
+    RANGES.iter()
+        .find(|(range_name, _, _)| *range_name == name)
+        .map(|(_, min, max)| (*min, *max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use druid::Rect;
+    use crate::widgets::timeline_item_widget::ItemGeometry;
+    use num_traits::FromPrimitive;
+    use proptest::prelude::*;
+
+    /// Enough room for rounding when positions are added up in a different order
+    const EPSILON: f64 = 1e-9;
+
+    /// Any variant of an enum with this many variants
+    fn any_enum<T: FromPrimitive + std::fmt::Debug>(count: u32) -> impl Strategy<Value = T> {
+        (0..count).prop_map(|index| T::from_u32(index).unwrap())
+    }
+
+    /// Any settings that the settings window allows. The sizes are anywhere in their ranges.
+    fn any_settings() -> impl Strategy<Value = LayoutSettings> {
+        let shapes = (any_enum::<ItemLayoutOption>(5), any_enum::<MetadataLayout>(3), any_enum::<PictureShape>(5), any_enum::<TailShape>(6));
+        let flags = (any::<bool>(), any::<bool>(), any::<bool>(), any::<bool>(), any::<bool>());
+        let sizes: Vec<_> = RANGES.iter().map(|(_, min, max)| *min..=*max).collect();
+        (shapes, flags, sizes).prop_map(|(
+            (item_layout, metadata_layout, picture_shape, chat_bubble_tail_shape),
+            (show_self_pic, align_to_picture, show_left_line, left_bubble_flipped, right_bubble_flipped),
+            sizes,
+        )| {
+            // The sizes are set by the names they are saved with, which are the names in the ranges
+            let mut table = match toml::Value::try_from(LayoutSettings::default()) {
+                Ok(toml::Value::Table(table)) => table,
+                _ => unreachable!("Layout settings are always serialized to a table"),
+            };
+            for ((name, _, _), size) in RANGES.iter().zip(sizes) {
+                table.insert(name.to_string(), toml::Value::Float(size));
+            }
+            let settings: LayoutSettings = toml::Value::Table(table).try_into().unwrap();
+            LayoutSettings {
+                item_layout,
+                metadata_layout,
+                picture_shape,
+                chat_bubble_tail_shape,
+                show_self_pic,
+                align_to_picture,
+                show_left_line,
+                left_bubble_flipped,
+                right_bubble_flipped,
+                ..settings
+            }
+        })
+    }
+
+    /// Where everything in a timeline item goes, placed by the timeline item's own geometry
+    struct Placement {
+        content: Rect,
+        sender: Rect,
+        picture: Option<Rect>,
+        bubble: Option<Rect>,
+        height: f64,
+    }
+
+    /// Lays out an item with labels of the given sizes. Like the labels, they are no wider than their area.
+    fn place(settings: &LayoutSettings, is_self_user: bool, width: f64, sender: Size, datetime: Size, content: Size) -> Placement {
+        let sender_area = settings.get_sender_label_area(width).max();
+        let sender = Size::new(sender.width.min(sender_area.width), sender.height);
+        let datetime = Size::new(datetime.width.min(sender_area.width), datetime.height);
+        let content = Size::new(content.width.min(settings.get_available_content_width(width, is_self_user)), content.height);
+        let geometry = ItemGeometry::new(settings, is_self_user, width, sender, datetime, content);
+        Placement {
+            content: geometry.content_rect,
+            sender: geometry.sender_rect,
+            picture: settings.show_picture(is_self_user).then(|| geometry.get_profile_pic_rect(settings, is_self_user)),
+            bubble: settings.is_bubble().then(|| geometry.get_bubble_rect(settings)),
+            height: geometry.size.height,
         }
     }
 
-    pub fn set_from_predefined_layout(&mut self, layout: PredefinedLayout) {
-        match layout {
-            PredefinedLayout::ModernHangouts => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 32.0;
-                self.chat_bubble_tail_shape = TailShape::ConcaveBottom;
-                self.chat_bubble_tail_size = 6.0;
-                self.chat_bubble_radius = 4.0;
-                self.chat_picture_spacing = 3.5;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 1.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 6.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::ModernBubble => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 32.0;
-                self.chat_bubble_tail_shape = TailShape::ConcaveBottom;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 10.0;
-                self.chat_picture_spacing = 6.5;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 7.0;
-                self.group_spacing = 10.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 3.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::LargeBubble => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 37.0;
-                self.chat_bubble_tail_shape = TailShape::ConcaveBottom;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 8.0;
-                self.chat_picture_spacing = 6.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 7.0;
-                self.group_spacing = 10.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 14.0;
-                self.sender_font_size = 12.0;
-                self.datetime_font_size = 12.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 3.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::OldHangouts => {
-                self.item_layout = ItemLayoutOption::BubbleInternalBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Rectangle;
-                self.picture_size = 35.0;
-                self.chat_bubble_tail_shape = TailShape::Straight;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 0.5;
-                self.chat_picture_spacing = 0.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 3.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 9.5;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 10.0;
-                self.datetime_font_size = 10.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_MEDIUM_COLOR;
-                self.datetime_color = DARK_ON_MEDIUM_COLOR;
-                self.self_datetime_color = DARK_ON_BLUE_COLOR;
-                self.self_sender_color = DARK_ON_BLUE_COLOR;
-            },
-            PredefinedLayout::IMessage => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 32.0;
-                self.chat_bubble_tail_shape = TailShape::Fancy;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 10.0;
-                self.chat_picture_spacing = 6.5;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 7.0;
-                self.group_spacing = 10.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = true;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::Telegram => {
-                self.item_layout = ItemLayoutOption::BubbleInternalTopMeta;
-                self.metadata_layout = MetadataLayout::LeftRightSpaced;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 32.0;
-                self.chat_bubble_tail_shape = TailShape::ConcaveBottom;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 4.0;
-                self.chat_picture_spacing = 8.0;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 5.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 9.5;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = true;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 12.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_BLUE_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::OldKik => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 30.0;
-                self.chat_bubble_tail_shape = TailShape::Symmetric;
-                self.chat_bubble_tail_size = 5.0;
-                self.chat_bubble_radius = 4.0;
-                self.chat_picture_spacing = 10.0;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 1.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 6.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::TearDrop => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 25.0;
-                self.chat_bubble_tail_shape = TailShape::Square;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 12.0;
-                self.chat_picture_spacing = 3.5;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 7.5;
-                self.group_spacing = 10.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::Tailless => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 25.0;
-                self.chat_bubble_tail_shape = TailShape::Hidden;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 8.0;
-                self.chat_picture_spacing = 3.5;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 10.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 11.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
+    fn overlaps(a: Rect, b: Rect) -> bool {
+        let intersection = a.intersect(b);
+        intersection.width() > EPSILON && intersection.height() > EPSILON
+    }
 
-            PredefinedLayout::Relaxed => {
-                self.item_layout = ItemLayoutOption::BubbleExternBottomMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySideWithDot;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 35.0;
-                self.chat_bubble_tail_shape = TailShape::Fancy;
-                self.chat_bubble_tail_size = 8.0;
-                self.chat_bubble_radius = 8.0;
-                self.chat_picture_spacing = 6.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 3.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 7.0;
-                self.group_spacing = 9.5;
-                self.single_message_spacing = 9.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = true;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 10.0;
-                self.datetime_font_size = 10.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = DARK_ON_DARK_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = DARK_ON_DARK_COLOR;
-            },
-            PredefinedLayout::OtherBubble => {
-                self.item_layout = ItemLayoutOption::BubbleInternalTopMeta;
-                self.metadata_layout = MetadataLayout::LeftSideBySide;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 28.0;
-                self.chat_bubble_tail_shape = TailShape::ConcaveBottom;
-                self.chat_bubble_tail_size = 7.0;
-                self.chat_bubble_radius = 3.0;
-                self.chat_picture_spacing = 6.0;
-                self.show_self_pic = false;
-                self.metadata_content_spacing = 5.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 5.0;
-                self.group_spacing = 9.5;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.left_bubble_flipped = false;
-                self.right_bubble_flipped = true;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 14.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::Discord => {
-                self.item_layout = ItemLayoutOption::Bubbleless;
-                self.metadata_layout = MetadataLayout::LeftSideBySide;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 40.0;
-                self.chat_picture_spacing = 13.0;
-                self.metadata_content_spacing = 7.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 0.0;
-                self.group_spacing = 23.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 14.0;
-                self.sender_font_size = 14.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Full12;
-                self.side_time_format = TimestampFormat::TimeOnlyAmPm;
-                self.left_meta_offset = 4.5;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::CompactDiscord => {
-                self.item_layout = ItemLayoutOption::Bubbleless;
-                self.metadata_layout = MetadataLayout::LeftSideBySide;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 36.0;
-                self.chat_picture_spacing = 8.0;
-                self.metadata_content_spacing = 7.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 0.0;
-                self.group_spacing = 13.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Full12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 10.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::Slack => {
-                self.item_layout = ItemLayoutOption::Bubbleless;
-                self.metadata_layout = MetadataLayout::LeftSideBySide;
-                self.picture_shape = PictureShape::RoundedRectangle;
-                self.picture_size = 36.0;
-                self.chat_picture_spacing = 5.5;
-                self.metadata_content_spacing = 5.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 0.0;
-                self.group_spacing = 14.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Full12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 5.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::Compact => {
-                self.item_layout = ItemLayoutOption::Bubbleless;
-                self.metadata_layout = MetadataLayout::LeftSideBySide;
-                self.picture_shape = PictureShape::Circle;
-                self.picture_size = 25.0;
-                self.chat_picture_spacing = 2.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 2.0;
-                self.align_to_picture = true;
-                self.bubble_padding = 0.0;
-                self.group_spacing = 8.0;
-                self.single_message_spacing = 5.0;
-                self.show_left_line = false;
-                self.left_spacing = 0.0;
-                self.metadata_font_bolded = true;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Full12;
-                self.side_time_format = TimestampFormat::TimeOnly24;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::IRC => {
-                self.item_layout = ItemLayoutOption::IRCStyle;
-                self.metadata_layout = MetadataLayout::LeftRightSpaced;
-                self.picture_shape = PictureShape::Rectangle;
-                self.picture_size = 16.0;
-                self.chat_picture_spacing = 3.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 3.0;
-                self.align_to_picture = false;
-                self.group_spacing = 6.0;
-                self.single_message_spacing = 5.0;
-                self.bubble_padding = 6.0;
-                self.show_left_line = true;
-                self.left_spacing = 4.0;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::LargeIRC => {
-                self.item_layout = ItemLayoutOption::IRCStyle;
-                self.metadata_layout = MetadataLayout::LeftRightSpaced;
-                self.picture_shape = PictureShape::Rectangle;
-                self.picture_size = 18.0;
-                self.chat_picture_spacing = 4.0;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 3.0;
-                self.align_to_picture = false;
-                self.group_spacing = 13.0;
-                self.single_message_spacing = 6.0;
-                self.bubble_padding = 6.0;
-                self.show_left_line = true;
-                self.left_spacing = 5.0;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 14.0;
-                self.sender_font_size = 14.0;
-                self.datetime_font_size = 12.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
-            PredefinedLayout::SpacedIRC => {
-                self.item_layout = ItemLayoutOption::IRCStyle;
-                self.metadata_layout = MetadataLayout::LeftRightSpaced;
-                self.picture_shape = PictureShape::Rectangle;
-                self.picture_size = 16.0;
-                self.chat_picture_spacing = 3.5;
-                self.show_self_pic = true;
-                self.metadata_content_spacing = 6.0;
-                self.align_to_picture = false;
-                self.group_spacing = 12.0;
-                self.single_message_spacing = 5.0;
-                self.bubble_padding = 6.0;
-                self.show_left_line = true;
-                self.left_spacing = 4.5;
-                self.metadata_font_bolded = false;
-                self.content_font_size = 13.0;
-                self.sender_font_size = 13.0;
-                self.datetime_font_size = 11.0;
-                self.datetime_format = TimestampFormat::Compact12;
-                self.side_time_format = TimestampFormat::TimeOnly12;
-                self.left_meta_offset = 2.0;
-                self.sender_color = WHITE_COLOR;
-                self.datetime_color = DARK_ON_DARK_COLOR;
-                self.self_datetime_color = DARK_ON_DARK_COLOR;
-                self.self_sender_color = WHITE_COLOR;
-            },
+    fn any_label() -> impl Strategy<Value = Size> {
+        (0.0..300.0, 5.0..60.0).prop_map(|(width, height)| Size::new(width, height))
+    }
+
+    proptest! {
+        #[test]
+        fn nothing_has_a_negative_width(settings in any_settings(), is_self_user: bool, width in 150.0..1000.0) {
+            prop_assert!(settings.get_available_content_width(width, is_self_user) >= 0.0);
+            prop_assert!(settings.get_sender_label_area(width).max().width >= 0.0);
+        }
+
+        /// Negative spacing pulls the content in under the picture on purpose, so it isn't checked
+        #[test]
+        fn content_does_not_overlap_the_picture(settings in any_settings(), is_self_user: bool, width in 150.0..1000.0,
+            sender in any_label(), datetime in any_label(), content in any_label())
+        {
+            let placement = place(&settings, is_self_user, width, sender, datetime, content);
+            if let (Some(picture), true) = (placement.picture, settings.chat_picture_spacing >= 0.0) {
+                prop_assert!(!overlaps(placement.content, picture), "{:?} {:?}", placement.content, picture);
+            }
+        }
+
+        /// Names so long that the metadata is wider than the timeline are left out, since nothing can fit them
+        #[test]
+        fn self_bubbles_stay_within_the_width(settings in any_settings(), width in 150.0..1000.0,
+            sender in any_label(), datetime in any_label(), content in any_label())
+        {
+            prop_assume!(settings.get_metadata_width(sender.width, datetime.width) <= settings.get_available_content_width(width, true));
+            let placement = place(&settings, true, width, sender, datetime, content);
+            if let Some(bubble) = placement.bubble {
+                prop_assert!(bubble.x1 <= width + EPSILON, "{:?} in {}", bubble, width);
+                if let (Some(picture), true) = (placement.picture, settings.chat_picture_spacing >= 0.0) {
+                    prop_assert!(bubble.x1 <= picture.x0 + EPSILON, "{:?} {:?}", bubble, picture);
+                }
+            }
+        }
+
+        /// The datetime is lined up with the bottom of the sender, so the sender is what's counted
+        #[test]
+        fn total_height_covers_everything(settings in any_settings(), is_self_user: bool, width in 150.0..1000.0,
+            sender in any_label(), datetime in any_label(), content in any_label())
+        {
+            let placement = place(&settings, is_self_user, width, sender, datetime, content);
+            let mut bottom = placement.content.y1.max(placement.sender.y1);
+            if let Some(bubble) = placement.bubble {
+                bottom = bottom.max(bubble.y1);
+            }
+            if let Some(picture) = placement.picture {
+                bottom = bottom.max(picture.y1);
+            }
+            prop_assert!(placement.height + EPSILON >= bottom, "{} < {}", placement.height, bottom);
         }
     }
-}
\ No newline at end of file
+}
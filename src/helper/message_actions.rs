@@ -0,0 +1,192 @@
+use druid::{Command, Env, Handled, HotKey, KbKey, KeyEvent, Menu, MenuItem, Selector, SysMods, Target};
+use chrono::TimeZone;
+use crate::{AppState, Message, Reaction, ReplyPreview};
+use crate::helper::{helper_functions, timeline};
+
+// Actions on a single message. The payload is the message ID.
+// They are shared by the context menu and the keyboard shortcuts.
+pub const COPY_MESSAGE_TEXT: Selector<u64> = Selector::new("polysoft.druid-demo.copy_message_text");
+pub const COPY_MESSAGE_TIMESTAMP: Selector<u64> = Selector::new("polysoft.druid-demo.copy_message_timestamp");
+pub const REPLY_TO_MESSAGE: Selector<u64> = Selector::new("polysoft.druid-demo.reply_to_message");
+pub const EDIT_MESSAGE: Selector<u64> = Selector::new("polysoft.druid-demo.edit_message");
+pub const DELETE_MESSAGE: Selector<u64> = Selector::new("polysoft.druid-demo.delete_message");
+pub const SELECT_MESSAGE: Selector<u64> = Selector::new("polysoft.druid-demo.select_message");
+/// Toggles the reaction of the self user. The payload is the message ID and the emoji.
+pub const REACT_TO_MESSAGE: Selector<(u64, String)> = Selector::new("polysoft.druid-demo.react_to_message");
+/// The payload is the URL to copy
+pub const COPY_LINK: Selector<String> = Selector::new("polysoft.druid-demo.copy_link");
+/// Stops replying or editing, and clears the selected message
+pub const CANCEL_COMPOSER_ACTION: Selector = Selector::new("polysoft.druid-demo.cancel_composer_action");
+
+// The keyboard shortcuts for the selected message. The context menu shows the same keys.
+// Shift makes the key an uppercase letter, which is what the key event has.
+const COPY_TEXT_SHORTCUT: (SysMods, &str) = (SysMods::CmdShift, "C");
+const REPLY_SHORTCUT: (SysMods, &str) = (SysMods::Cmd, "r");
+const EDIT_SHORTCUT: (SysMods, &str) = (SysMods::Cmd, "e");
+const DELETE_SHORTCUT: (SysMods, KbKey) = (SysMods::Cmd, KbKey::Delete);
+
+/// The reactions offered in the context menu and the hover action bar
+pub const QUICK_REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "😮", "😢", "🎉"];
+
+/// Builds the menu shown when right-clicking a message.
+/// Edit and Delete are only offered for the self user's messages.
+pub fn build_context_menu(message: &Message, is_own_message: bool) -> Menu<AppState> {
+    let id = message.id;
+    let mut react_menu = Menu::new("React");
+    for emoji in QUICK_REACTIONS {
+        react_menu = react_menu.entry(
+            MenuItem::new(emoji).command(REACT_TO_MESSAGE.with((id, emoji.to_string())))
+        );
+    }
+
+    let mut menu = Menu::empty()
+        .entry(MenuItem::new("Copy Text").command(COPY_MESSAGE_TEXT.with(id)).hotkey(COPY_TEXT_SHORTCUT.0, COPY_TEXT_SHORTCUT.1))
+        .entry(MenuItem::new("Copy Timestamp").command(COPY_MESSAGE_TIMESTAMP.with(id)))
+        .separator()
+        .entry(MenuItem::new("Reply").command(REPLY_TO_MESSAGE.with(id)).hotkey(REPLY_SHORTCUT.0, REPLY_SHORTCUT.1))
+        .entry(react_menu);
+    if is_own_message {
+        menu = menu
+            .entry(MenuItem::new("Edit").command(EDIT_MESSAGE.with(id)).hotkey(EDIT_SHORTCUT.0, EDIT_SHORTCUT.1))
+            .entry(MenuItem::new("Delete").command(DELETE_MESSAGE.with(id)).hotkey(DELETE_SHORTCUT.0, DELETE_SHORTCUT.1));
+    }
+    menu = menu
+        .separator()
+        .entry(MenuItem::new("Select").command(SELECT_MESSAGE.with(id)));
+
+    let urls = helper_functions::find_urls(&message.message);
+    if !urls.is_empty() {
+        menu = menu.separator();
+        for url in urls {
+            menu = menu.entry(
+                MenuItem::new(format!("Copy Link: {}", url)).command(COPY_LINK.with(url))
+            );
+        }
+    }
+    menu
+}
+
+/// Gets the command for a keyboard shortcut on the selected message, the same one the context menu sends.
+///
+/// Escape stops selecting, replying and editing. The shortcuts are only given to the timeline while it has focus,
+/// so they don't get in the way of typing.
+pub fn get_shortcut_command(key_event: &KeyEvent, data: &AppState) -> Option<Command> {
+    if key_event.key == KbKey::Escape && (data.selected_message.is_some()
+        || data.replying_to.is_some() || data.editing_message.is_some())
+    {
+        return Some(CANCEL_COMPOSER_ACTION.to(Target::Global));
+    }
+    let id = data.selected_message?;
+    let command: Command = if HotKey::new(COPY_TEXT_SHORTCUT.0, COPY_TEXT_SHORTCUT.1).matches(key_event) {
+        COPY_MESSAGE_TEXT.with(id)
+    } else if HotKey::new(REPLY_SHORTCUT.0, REPLY_SHORTCUT.1).matches(key_event) {
+        REPLY_TO_MESSAGE.with(id)
+    } else if HotKey::new(EDIT_SHORTCUT.0, EDIT_SHORTCUT.1).matches(key_event) {
+        EDIT_MESSAGE.with(id)
+    } else if HotKey::new(DELETE_SHORTCUT.0, DELETE_SHORTCUT.1).matches(key_event) {
+        DELETE_MESSAGE.with(id)
+    } else {
+        return None;
+    };
+    Some(command.to(Target::Global))
+}
+
+/// Handles the message action commands. Called by the app delegate.
+pub fn handle_command(cmd: &Command, data: &mut AppState, env: &Env) -> Handled {
+    let self_id = env.get(crate::SELF_USER_ID_KEY) as u32;
+    if let Some(id) = cmd.get(COPY_MESSAGE_TEXT) {
+        if let Some((_, message)) = timeline::find_message(data, *id) {
+            copy_to_clipboard(&message.message);
+        }
+    } else if let Some(id) = cmd.get(COPY_MESSAGE_TIMESTAMP) {
+        if let Some((_, message)) = timeline::find_message(data, *id) {
+            if let chrono::LocalResult::Single(time) = chrono::Local.timestamp_opt(message.timestamp_epoch_seconds, 0) {
+                copy_to_clipboard(&time.format("%Y-%m-%d %H:%M:%S").to_string());
+            }
+        }
+    } else if let Some(id) = cmd.get(REPLY_TO_MESSAGE) {
+        if let Some((user_id, message)) = timeline::find_message(data, *id) {
+            data.replying_to = Some(ReplyPreview {
+                message_id: message.id,
+                user_id,
+                text: message.message.clone(),
+            });
+            data.editing_message = None;
+        }
+    } else if let Some(id) = cmd.get(EDIT_MESSAGE) {
+        if let Some((user_id, message)) = timeline::find_message(data, *id) {
+            if user_id == self_id {
+                data.text_edit = message.message.clone().into();
+                data.editing_message = Some(*id);
+                data.replying_to = None;
+            }
+        }
+    } else if let Some(id) = cmd.get(DELETE_MESSAGE) {
+        let is_own_message = matches!(timeline::find_message(data, *id), Some((user_id, _)) if user_id == self_id);
+        if is_own_message {
+            remove_message(data, *id);
+        }
+    } else if let Some(id) = cmd.get(SELECT_MESSAGE) {
+        data.selected_message = Some(*id);
+    } else if let Some((id, emoji)) = cmd.get(REACT_TO_MESSAGE) {
+        if let Some((_, message)) = timeline::find_message_mut(data, *id) {
+            toggle_reaction(message, emoji, self_id);
+        }
+    } else if let Some(url) = cmd.get(COPY_LINK) {
+        copy_to_clipboard(url);
+    } else if cmd.is(CANCEL_COMPOSER_ACTION) {
+        if data.editing_message.take().is_some() {
+            data.text_edit = String::new().into();
+        }
+        data.replying_to = None;
+        data.selected_message = None;
+    } else {
+        return Handled::No;
+    }
+    Handled::Yes
+}
+
+/// Deletes a message, and stops selecting, editing or replying to it.
+/// Returns false if there is no message with the ID.
+pub fn remove_message(data: &mut AppState, id: u64) -> bool {
+    if !timeline::delete_message(data, id) {
+        return false;
+    }
+    if data.selected_message == Some(id) {
+        data.selected_message = None;
+    }
+    if data.editing_message == Some(id) {
+        data.editing_message = None;
+        data.text_edit = String::new().into();
+    }
+    if data.replying_to.as_ref().map(|reply| reply.message_id) == Some(id) {
+        data.replying_to = None;
+    }
+    true
+}
+
+/// Adds the user's reaction, or removes it if they already reacted with the emoji.
+pub fn toggle_reaction(message: &mut Message, emoji: &str, user_id: u32) {
+    match message.reactions.iter().position(|reaction| reaction.emoji == emoji) {
+        Some(index) => {
+            let reaction = &mut message.reactions[index];
+            match reaction.user_ids.iter().position(|id| *id == user_id) {
+                Some(user_index) => {
+                    reaction.user_ids.remove(user_index);
+                },
+                None => reaction.user_ids.push_back(user_id),
+            }
+            if reaction.user_ids.is_empty() {
+                message.reactions.remove(index);
+            }
+        },
+        None => message.reactions.push_back(Reaction {
+            emoji: emoji.to_string(),
+            user_ids: druid::im::vector![user_id],
+        }),
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    druid::Application::global().clipboard().put_string(text);
+}
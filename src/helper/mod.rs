@@ -1,2 +1,19 @@
 pub mod helper_functions;
-pub mod layout_settings;
\ No newline at end of file
+pub mod layout_settings;
+pub mod timeline;
+pub mod emoji;
+pub mod message_actions;
+pub mod text_selection;
+pub mod config;
+pub mod presets;
+pub mod preset_registry;
+pub mod color;
+pub mod theme;
+pub mod config_watcher;
+pub mod audit;
+pub mod offscreen;
+pub mod benchmark;
+pub mod demo_data;
+pub mod cli;
+pub mod replay;
+pub mod flood;
\ No newline at end of file
@@ -0,0 +1,359 @@
+use druid::kurbo::{Affine, RoundedRect};
+use druid::piet::{ImageFormat, RenderContext};
+use druid::{Color, ImageBuf, Point, Rect, Size};
+use piet_common::Device;
+use crate::AppState;
+use crate::MessageGroup;
+use crate::helper::layout_settings::LayoutSettings;
+use crate::helper::theme;
+use crate::widgets::single_message_widget;
+use crate::widgets::timeline_item_widget::{self, ItemGeometry};
+
+/// Space around the timeline
+const PADDING: f64 = 5.0;
+/// The width of an average character, relative to the font size. Used to wrap the text into lines.
+const CHAR_WIDTH: f64 = 0.5;
+/// The height of a line of text, relative to its font size
+const LINE_HEIGHT: f64 = 1.25;
+/// Text is drawn as a bar this tall, relative to the font size
+const TEXT_BAR_HEIGHT: f64 = 0.55;
+/// How wide the sender name and the date are taken to be, relative to their font sizes
+const SENDER_WIDTH: f64 = 4.0;
+const DATETIME_WIDTH: f64 = 4.5;
+
+/// Where each timeline item goes, found without a window
+pub struct TimelineLayout {
+    width: f64,
+    items: Vec<LaidOutItem>,
+    /// The height of the whole timeline
+    pub height: f64,
+}
+
+struct LaidOutItem {
+    group_index: usize,
+    is_self_user: bool,
+    lines: Vec<TextLine>,
+    geometry: ItemGeometry,
+    y: f64,
+}
+
+/// A line of message text, drawn as a bar
+struct TextLine {
+    width: f64,
+    /// From the top of the messages
+    y: f64,
+}
+
+impl TimelineLayout {
+    /// The index of the first item that reaches below the given y position
+    fn find_item_at(&self, y: f64) -> usize {
+        self.items.partition_point(|item| item.y + item.geometry.size.height < y)
+    }
+}
+
+/// Lays out the timeline of the state at the given width. The self user's messages are shown on the self user's side.
+///
+/// The items are placed by the same code as the timeline items in the window, from the sizes of their labels.
+/// Only the sizes of the text are estimated, from the number of characters, since there are no labels without a window.
+pub fn layout_timeline(state: &AppState, self_user_id: u32, width: f64) -> TimelineLayout {
+    let settings = &*state.layout_settings;
+    let item_width = width - PADDING * 2.0;
+    let mut items = Vec::with_capacity(state.timeline_data.len());
+    let mut y = PADDING;
+    for (group_index, group) in state.timeline_data.iter().enumerate() {
+        let is_self_user = group.user_id == self_user_id;
+        let lines = get_lines(group, settings, is_self_user, item_width);
+        let content_width = lines.iter().map(|line| line.width).fold(0.0, f64::max);
+        let content_height = lines.last().map_or(0.0, |line| line.y + settings.content_font_size * LINE_HEIGHT);
+        let geometry = ItemGeometry::new(settings, is_self_user, item_width,
+            Size::new(settings.sender_font_size * SENDER_WIDTH, settings.sender_font_size * LINE_HEIGHT),
+            Size::new(settings.datetime_font_size * DATETIME_WIDTH, settings.datetime_font_size * LINE_HEIGHT),
+            Size::new(content_width, content_height));
+        let height = geometry.size.height;
+        items.push(LaidOutItem { group_index, is_self_user, lines, geometry, y });
+        y += height + settings.group_spacing;
+    }
+    let height = if items.is_empty() { PADDING * 2.0 } else { y - settings.group_spacing + PADDING };
+    TimelineLayout { width, items, height }
+}
+
+/// Paints the part of the laid out timeline that starts at `scroll_y` and is `height` tall,
+/// without a window or GPU.
+///
+/// It uses the software renderer, and the same painting code as the timeline items for everything but the text.
+/// Text is drawn as bars, so the picture doesn't depend on which fonts are installed.
+pub fn paint_timeline(state: &AppState, layout: &TimelineLayout, scroll_y: f64, height: f64) -> Result<ImageBuf, String> {
+    let settings = &*state.layout_settings;
+    let colors = ItemColors::from_theme(&state.theme.colors);
+    let mut device = Device::new().map_err(|e| e.to_string())?;
+    let mut target = device.bitmap_target(layout.width.ceil() as usize, height.ceil() as usize, 1.0)
+        .map_err(|e| e.to_string())?;
+    {
+        let mut rc = target.render_context();
+        rc.clear(None, state.theme.colors.window_background.to_druid_color());
+        let visible_items = layout.items[layout.find_item_at(scroll_y)..].iter()
+            .take_while(|item| item.y < scroll_y + height);
+        for item in visible_items {
+            let group = &state.timeline_data[item.group_index];
+            rc.save().map_err(|e| e.to_string())?;
+            rc.transform(Affine::translate((PADDING, item.y - scroll_y)));
+            paint_item(&mut rc, settings, &colors, group, item);
+            rc.restore().map_err(|e| e.to_string())?;
+        }
+        rc.finish().map_err(|e| e.to_string())?;
+    }
+    target.to_image_buf(ImageFormat::RgbaSeparate).map_err(|e| e.to_string())
+}
+
+/// Lays out and paints the whole timeline of the state at the given width
+#[cfg(test)]
+pub fn render_timeline(state: &AppState, self_user_id: u32, width: f64) -> Result<ImageBuf, String> {
+    let layout = layout_timeline(state, self_user_id, width);
+    paint_timeline(state, &layout, 0.0, layout.height)
+}
+
+/// The colors the timeline is drawn with. Each pair is for other users, then the self user.
+struct ItemColors {
+    text: Color,
+    bubble: [Color; 2],
+    sender: [Color; 2],
+    datetime: [Color; 2],
+}
+
+impl ItemColors {
+    fn from_theme(colors: &theme::ThemeColors) -> ItemColors {
+        ItemColors {
+            text: colors.text.to_druid_color(),
+            bubble: [colors.get_bubble_color(false), colors.get_bubble_color(true)],
+            sender: [colors.get_sender_color(false), colors.get_sender_color(true)],
+            datetime: [colors.get_datetime_color(false), colors.get_datetime_color(true)],
+        }
+    }
+}
+
+/// Paints one item in the same order as the timeline item does
+fn paint_item(rc: &mut impl RenderContext, settings: &LayoutSettings, colors: &ItemColors, group: &MessageGroup, item: &LaidOutItem) {
+    let side = item.is_self_user as usize;
+    let geometry = &item.geometry;
+    let bubble_rect = geometry.get_bubble_rect(settings);
+    let only_jumbomoji = group.messages.iter().all(single_message_widget::is_jumbomoji);
+
+    if !only_jumbomoji {
+        timeline_item_widget::paint_bubble(rc, settings, bubble_rect, &colors.bubble[side]);
+    }
+    if settings.show_picture(item.is_self_user) && group.profile_pic.width() > 0 {
+        let image = group.profile_pic.to_image(rc);
+        timeline_item_widget::paint_profile_pic(rc, settings, item.is_self_user, geometry, &image);
+    }
+    if !only_jumbomoji {
+        timeline_item_widget::paint_bubble_tail(rc, settings, item.is_self_user, bubble_rect, &colors.bubble[side]);
+    }
+
+    let line_height = settings.content_font_size * LINE_HEIGHT;
+    for line in item.lines.iter() {
+        let line_origin = Point::new(geometry.content_rect.x0, geometry.content_rect.y0 + line.y);
+        let line_size = Size::new(line.width.min(geometry.content_rect.width()), line_height);
+        draw_text_bar(rc, Rect::from_origin_size(line_origin, line_size), settings.content_font_size, &colors.text);
+    }
+    draw_text_bar(rc, geometry.sender_rect, settings.sender_font_size, &colors.sender[side]);
+    draw_text_bar(rc, geometry.datetime_rect, settings.datetime_font_size, &colors.datetime[side]);
+    timeline_item_widget::paint_metadata_dot(rc, settings, geometry.datetime_rect, &colors.datetime[side]);
+}
+
+/// Draws a rounded bar in place of a line of text
+fn draw_text_bar(rc: &mut impl RenderContext, line_rect: Rect, font_size: f64, color: &Color) {
+    let bar_height = font_size * TEXT_BAR_HEIGHT;
+    let y0 = line_rect.center().y - bar_height / 2.0;
+    let bar = Rect::new(line_rect.x0, y0, line_rect.x1, y0 + bar_height);
+    rc.fill(RoundedRect::from_rect(bar, bar_height / 2.0), color);
+}
+
+/// Wraps the messages of the group into lines, with the same spacing between messages as the timeline.
+/// Each character is taken to be the same width, which is close enough to show the shape of the text.
+fn get_lines(group: &MessageGroup, settings: &LayoutSettings, is_self_user: bool, width: f64) -> Vec<TextLine> {
+    let available_width = settings.get_available_content_width(width, is_self_user);
+    let char_width = settings.content_font_size * CHAR_WIDTH;
+    let max_chars = ((available_width / char_width).floor() as usize).max(1);
+    let line_height = settings.content_font_size * LINE_HEIGHT;
+    let mut lines = Vec::new();
+    let mut y = 0.0;
+    for (i, message) in group.messages.iter().enumerate() {
+        if i > 0 {
+            y += settings.single_message_spacing;
+        }
+        for paragraph in message.message.split('\n') {
+            let mut chars = paragraph.chars().count().max(1);
+            while chars > max_chars {
+                lines.push(TextLine { width: max_chars as f64 * char_width, y });
+                y += line_height;
+                chars -= max_chars;
+            }
+            lines.push(TextLine { width: chars as f64 * char_width, y });
+            y += line_height;
+        }
+    }
+    lines
+}
+
+/// Writes the picture to a PNG file
+#[cfg(test)]
+pub fn save_png(image: &ImageBuf, path: &std::path::Path) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(image.raw_pixels()).map_err(|e| e.to_string())
+}
+
+/// Reads a PNG file written by [save_png]
+#[cfg(test)]
+pub fn load_png(path: &std::path::Path) -> Result<ImageBuf, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|e| e.to_string())?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{} is not an 8 bit RGBA image", path.display()));
+    }
+    pixels.truncate(info.buffer_size());
+    Ok(ImageBuf::from_raw(pixels, ImageFormat::RgbaSeparate, info.width as usize, info.height as usize))
+}
+
+/// Counts the pixels where any channel differs by more than the tolerance.
+/// Pictures of different sizes differ in every pixel.
+#[cfg(test)]
+pub fn count_different_pixels(a: &ImageBuf, b: &ImageBuf, tolerance: u8) -> usize {
+    if a.width() != b.width() || a.height() != b.height() {
+        return a.width().max(b.width()) * a.height().max(b.height());
+    }
+    a.raw_pixels().chunks_exact(4)
+        .zip(b.raw_pixels().chunks_exact(4))
+        .filter(|(pixel_a, pixel_b)| pixel_a.iter().zip(pixel_b.iter()).any(|(x, y)| x.abs_diff(*y) > tolerance))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use num_traits::FromPrimitive;
+    use crate::{Message, MessageKind};
+    use crate::helper::{theme, timeline};
+    use crate::widgets::timeline_item_widget::ItemLayoutOption;
+
+    /// Set to write the rendered pictures as the new goldens, instead of comparing them
+    const UPDATE_ENV_VAR: &str = "UPDATE_GOLDENS";
+    /// How much a channel can differ before the pixel counts as different.
+    /// Allows for small differences in anti-aliasing between versions of the renderer.
+    const CHANNEL_TOLERANCE: u8 = 16;
+    /// The fraction of pixels that can be different before the test fails
+    const MAX_DIFFERENT_FRACTION: f64 = 0.002;
+    /// Narrow enough that long messages wrap, and wide enough for the side by side layouts
+    const WIDTHS: [f64; 2] = [320.0, 640.0];
+    const SELF_USER_ID: u32 = 1;
+
+    fn goldens_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("goldens")
+    }
+
+    /// A short conversation with a grouped message, a wrapped message, and a narrow message
+    fn sample_state(settings: LayoutSettings) -> AppState {
+        let mut state = AppState::new(settings, theme::default_theme());
+        for user in 1..3 {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("images/user_{}_55px.png", user));
+            state.profile_pics.push_back(ImageBuf::from_file(path).expect("The profile pictures should load"));
+        }
+        let time = 1_600_000_000;
+        let messages = [
+            (0, 0, "Hi"),
+            (0, 10, "Did you see the new layouts? There are a lot of them now, and some are quite different from the others."),
+            (1, 20, "Yes!"),
+            (0, 120, "This\nis\na\nnarrow\nbut\nlong\nmessage."),
+            (1, 130, "I like the bubbles best, but the IRC style fits more on the screen."),
+        ];
+        for (user_id, seconds, text) in messages {
+            timeline::push_message(&mut state, user_id, Message::new(text.to_string(), time + seconds, MessageKind::Text));
+        }
+        state
+    }
+
+    /// Compares the picture to the golden with the same name, or writes it when updating.
+    /// A missing golden fails, so a golden that wasn't committed is noticed instead of quietly written.
+    fn check_golden(name: &str, image: &ImageBuf) -> Result<(), String> {
+        let path = goldens_dir().join(format!("{}.png", name));
+        if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+            std::fs::create_dir_all(goldens_dir()).map_err(|e| e.to_string())?;
+            eprintln!("Writing the golden {}", path.display());
+            return save_png(image, &path);
+        }
+        if !path.exists() {
+            return Err(format!("{} has no golden at {}. Run with {}=1 to write it, and commit it.",
+                name, path.display(), UPDATE_ENV_VAR));
+        }
+        let golden = load_png(&path)?;
+        let different = count_different_pixels(image, &golden, CHANNEL_TOLERANCE);
+        let allowed = (image.width() * image.height()) as f64 * MAX_DIFFERENT_FRACTION;
+        if different as f64 > allowed {
+            // Kept next to the golden so the two can be compared
+            let actual_path = goldens_dir().join(format!("{}.actual.png", name));
+            save_png(image, &actual_path)?;
+            return Err(format!("{} differs from its golden in {} pixels. The rendered picture is at {}. \
+                Run with {}=1 if the change is expected.", name, different, actual_path.display(), UPDATE_ENV_VAR));
+        }
+        Ok(())
+    }
+
+    /// Renders the sample at every width, and collects every mismatch so they are all reported at once
+    fn check_layout(name: &str, settings: &LayoutSettings, failures: &mut Vec<String>) {
+        let state = sample_state(settings.clone());
+        for width in WIDTHS {
+            let golden_name = format!("{}-{}", name, width);
+            let result = render_timeline(&state, SELF_USER_ID, width)
+                .and_then(|image| check_golden(&golden_name, &image));
+            if let Err(e) = result {
+                failures.push(e);
+            }
+        }
+    }
+
+    #[test]
+    fn presets_match_goldens() {
+        let registry = crate::helper::preset_registry::PresetRegistry::load();
+        let mut failures = Vec::new();
+        for preset in registry.iter() {
+            check_layout(&format!("preset-{}", preset.id), &preset.settings, &mut failures);
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn item_layouts_match_goldens() {
+        let mut failures = Vec::new();
+        let mut index = 0;
+        while let Some(item_layout) = ItemLayoutOption::from_u32(index) {
+            let mut settings = LayoutSettings::default();
+            settings.item_layout = item_layout;
+            check_layout(&format!("item-layout-{}", index), &settings, &mut failures);
+            index += 1;
+        }
+        assert!(index > 0);
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn small_differences_are_tolerated() {
+        let pixels = vec![100u8; 4 * 4 * 4];
+        let mut changed = pixels.clone();
+        changed[0] = 100 + CHANNEL_TOLERANCE;
+        changed[4] = 100 + CHANNEL_TOLERANCE + 1;
+        let image = ImageBuf::from_raw(pixels, ImageFormat::RgbaSeparate, 4, 4);
+        let changed = ImageBuf::from_raw(changed, ImageFormat::RgbaSeparate, 4, 4);
+        assert_eq!(count_different_pixels(&image, &image, 0), 0);
+        assert_eq!(count_different_pixels(&image, &changed, CHANNEL_TOLERANCE), 1);
+        let smaller = ImageBuf::from_raw(vec![100u8; 4 * 2 * 4], ImageFormat::RgbaSeparate, 4, 2);
+        assert_eq!(count_different_pixels(&image, &smaller, CHANNEL_TOLERANCE), 16);
+    }
+}
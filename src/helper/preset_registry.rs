@@ -0,0 +1,149 @@
+use crate::helper::config;
+use crate::helper::layout_settings::LayoutSettings;
+
+/// The predefined layouts, built into the app. They are shown in the settings in this order.
+///
+/// Each file only has the settings that differ from its base, which is either the default
+/// layout, or another preset named by `base`. A base has to come before the presets that use it.
+const PRESET_FILES: [(&str, &str); 18] = [
+    ("modern-hangouts.toml", include_str!("../assets/presets/modern-hangouts.toml")),
+    ("modern-bubble.toml", include_str!("../assets/presets/modern-bubble.toml")),
+    ("large-bubble.toml", include_str!("../assets/presets/large-bubble.toml")),
+    ("old-hangouts.toml", include_str!("../assets/presets/old-hangouts.toml")),
+    ("telegram.toml", include_str!("../assets/presets/telegram.toml")),
+    ("imessage.toml", include_str!("../assets/presets/imessage.toml")),
+    ("old-kik.toml", include_str!("../assets/presets/old-kik.toml")),
+    ("tear-drop.toml", include_str!("../assets/presets/tear-drop.toml")),
+    ("tailless.toml", include_str!("../assets/presets/tailless.toml")),
+    ("relaxed.toml", include_str!("../assets/presets/relaxed.toml")),
+    ("other-bubble.toml", include_str!("../assets/presets/other-bubble.toml")),
+    ("discord.toml", include_str!("../assets/presets/discord.toml")),
+    ("compact-discord.toml", include_str!("../assets/presets/compact-discord.toml")),
+    ("slack.toml", include_str!("../assets/presets/slack.toml")),
+    ("compact.toml", include_str!("../assets/presets/compact.toml")),
+    ("irc.toml", include_str!("../assets/presets/irc.toml")),
+    ("large-irc.toml", include_str!("../assets/presets/large-irc.toml")),
+    ("spaced-irc.toml", include_str!("../assets/presets/spaced-irc.toml")),
+];
+
+/// The format of a preset file
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+    id: String,
+    name: String,
+    base: Option<String>,
+    #[serde(default)]
+    layout: toml::value::Table,
+}
+
+pub struct LayoutPreset {
+    /// The name used to refer to the layout, such as in the `/layout` command
+    pub id: String,
+    /// The name shown in the settings
+    pub name: String,
+    pub settings: LayoutSettings,
+}
+
+/// The predefined layouts, loaded from the preset files when the app starts.
+pub struct PresetRegistry {
+    presets: Vec<LayoutPreset>,
+}
+
+impl PresetRegistry {
+    /// Loads the built in presets. A preset that can't be loaded is left out, with an error logged.
+    pub fn load() -> PresetRegistry {
+        let (registry, errors) = PresetRegistry::from_files(&PRESET_FILES);
+        for error in errors {
+            tracing::error!("{}", error);
+        }
+        registry
+    }
+
+    /// Loads the presets from the (file name, text) pairs, in order.
+    /// Returns the presets that loaded, and an error for each that didn't.
+    fn from_files(files: &[(&str, &str)]) -> (PresetRegistry, Vec<String>) {
+        let mut registry = PresetRegistry { presets: Vec::new() };
+        let mut errors = Vec::new();
+        for (file_name, text) in files {
+            match registry.parse_preset(text) {
+                Ok(preset) => registry.presets.push(preset),
+                Err(e) => errors.push(format!("Could not load the preset {}: {}", file_name, e)),
+            }
+        }
+        (registry, errors)
+    }
+
+    fn parse_preset(&self, text: &str) -> Result<LayoutPreset, String> {
+        let file: PresetFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        if self.get(&file.id).is_some() {
+            return Err(format!("There is already a preset with the ID \"{}\"", file.id));
+        }
+        let base = match &file.base {
+            Some(base_id) => self.get(base_id)
+                .map(|base| base.settings.clone())
+                .ok_or_else(|| format!("The base \"{}\" is not loaded before this preset", base_id))?,
+            None => LayoutSettings::default(),
+        };
+        let settings = config::apply_layout_overrides(&base, file.layout)?;
+        settings.validate()?;
+        Ok(LayoutPreset { id: file.id, name: file.name, settings })
+    }
+
+    /// Finds a preset by its ID, ignoring case
+    pub fn get(&self, id: &str) -> Option<&LayoutPreset> {
+        self.presets.iter().find(|preset| preset.id.eq_ignore_ascii_case(id.trim()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LayoutPreset> {
+        self.presets.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_presets_load() {
+        let (registry, errors) = PresetRegistry::from_files(&PRESET_FILES);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(registry.iter().count(), PRESET_FILES.len());
+    }
+
+    #[test]
+    fn presets_are_in_range() {
+        let (registry, _) = PresetRegistry::from_files(&PRESET_FILES);
+        for preset in registry.iter() {
+            assert!(preset.settings.validate().is_ok(), "{}: {:?}", preset.id, preset.settings.validate());
+        }
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        let (registry, _) = PresetRegistry::from_files(&PRESET_FILES);
+        for preset in registry.iter() {
+            assert_eq!(registry.iter().filter(|other| other.id.eq_ignore_ascii_case(&preset.id)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn unknown_settings_are_errors() {
+        let (registry, errors) = PresetRegistry::from_files(&[
+            ("bad.toml", "id = \"Bad\"\nname = \"Bad\"\n[layout]\nnot_a_setting = 1.0\n"),
+        ]);
+        assert_eq!(registry.iter().count(), 0);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn base_must_come_first() {
+        let (registry, errors) = PresetRegistry::from_files(&[
+            ("child.toml", "id = \"Child\"\nname = \"Child\"\nbase = \"Parent\"\n"),
+            ("parent.toml", "id = \"Parent\"\nname = \"Parent\"\n"),
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert!(registry.get("Parent").is_some());
+        assert!(registry.get("Child").is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use druid::{Command, FileInfo, Handled, Selector};
+use crate::AppState;
+use crate::helper::config;
+use crate::helper::layout_settings::LayoutSettings;
+
+/// Presets are stored one per file in this directory, inside the config directory.
+/// Any preset file dropped in here shows up the next time the settings are opened.
+const PRESETS_DIR_NAME: &str = "presets";
+const PRESET_EXTENSION: &str = "toml";
+
+// The payload of these is the name of the user preset.
+pub const APPLY_USER_PRESET: Selector<String> = Selector::new("polysoft.druid-demo.apply_user_preset");
+/// Renames to the name typed in the preset name box
+pub const RENAME_USER_PRESET: Selector<String> = Selector::new("polysoft.druid-demo.rename_user_preset");
+pub const DELETE_USER_PRESET: Selector<String> = Selector::new("polysoft.druid-demo.delete_user_preset");
+/// Remembers which preset to export, before the save dialog is shown
+pub const EXPORT_USER_PRESET: Selector<String> = Selector::new("polysoft.druid-demo.export_user_preset");
+/// Saves the current layout with the name typed in the preset name box
+pub const SAVE_USER_PRESET: Selector = Selector::new("polysoft.druid-demo.save_user_preset");
+pub const RELOAD_USER_PRESETS: Selector = Selector::new("polysoft.druid-demo.reload_user_presets");
+// Sent by the file dialogs
+pub const EXPORT_PRESET_TO: Selector<FileInfo> = Selector::new("polysoft.druid-demo.export_preset_to");
+pub const IMPORT_PRESET_FROM: Selector<FileInfo> = Selector::new("polysoft.druid-demo.import_preset_from");
+/// Forgets the preset to export, so a later export dialog can't write it by mistake
+pub const EXPORT_PRESET_CANCELLED: Selector = Selector::new("polysoft.druid-demo.export_preset_cancelled");
+
+#[derive(Clone, druid::Data, druid::Lens)]
+pub struct UserPreset {
+    pub name: String,
+    /// The file in the presets directory. It can differ from the name when the file was added by hand.
+    pub file_name: String,
+    pub settings: LayoutSettings,
+}
+
+pub fn get_presets_dir() -> Option<PathBuf> {
+    config::get_config_dir().map(|dir| dir.join(PRESETS_DIR_NAME))
+}
+
+/// Turns the preset name into a file name, by replacing anything that isn't safe in a file name.
+fn get_preset_path(dir: &Path, name: &str) -> PathBuf {
+    let file_stem: String = name.trim().chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' { c } else { '_' })
+        .collect();
+    dir.join(file_stem).with_extension(PRESET_EXTENSION)
+}
+
+/// Reads a preset file. Files without a name use the file name instead.
+fn read_preset_file(path: &Path) -> Result<UserPreset, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file = config::parse_layout_file(&text)?;
+    for warning in &file.warnings {
+        tracing::warn!("{}: {}", path.display(), warning);
+    }
+    let name = file.name
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    Ok(UserPreset { name, file_name, settings: file.settings })
+}
+
+/// Loads every preset in the presets directory, sorted by name.
+/// Files that can't be read are skipped with a warning.
+pub fn load_user_presets() -> druid::im::Vector<UserPreset> {
+    let mut presets = Vec::new();
+    let entries = match get_presets_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        // No presets have been saved yet
+        _ => return druid::im::Vector::new(),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension() != Some(std::ffi::OsStr::new(PRESET_EXTENSION)) {
+            continue;
+        }
+        match read_preset_file(&path) {
+            Ok(preset) => presets.push(preset),
+            Err(e) => tracing::warn!("Skipping preset {}: {}", path.display(), e),
+        }
+    }
+    presets.sort_by_key(|preset| preset.name.to_lowercase());
+    presets.into_iter().collect()
+}
+
+fn write_preset(preset: &UserPreset, path: &Path) -> Result<(), String> {
+    let text = config::layout_to_string(Some(&preset.name), &preset.settings).map_err(|e| e.to_string())?;
+    config::write_file(path, &text).map_err(|e| e.to_string())
+}
+
+/// Saves a new preset in the presets directory, named after the preset.
+fn save_new_user_preset(name: &str, settings: &LayoutSettings) -> Result<(), String> {
+    let dir = get_presets_dir().ok_or("Could not find the config directory")?;
+    let path = get_preset_path(&dir, name);
+    if path.exists() {
+        // Two names can turn into the same file name
+        return Err(format!("The file {} already exists", path.display()));
+    }
+    let preset = UserPreset { name: name.to_string(), file_name: String::new(), settings: settings.clone() };
+    write_preset(&preset, &path)
+}
+
+fn delete_user_preset(preset: &UserPreset) -> Result<(), String> {
+    let dir = get_presets_dir().ok_or("Could not find the config directory")?;
+    fs::remove_file(dir.join(&preset.file_name)).map_err(|e| e.to_string())
+}
+
+/// Saves the preset under the new name, and removes the old file.
+fn rename_user_preset(preset: &UserPreset, new_name: &str) -> Result<(), String> {
+    let dir = get_presets_dir().ok_or("Could not find the config directory")?;
+    let renamed = UserPreset { name: new_name.to_string(), ..preset.clone() };
+    let old_path = dir.join(&preset.file_name);
+    let new_path = get_preset_path(&dir, new_name);
+    // Only the case changed, which may be the same file, so it is rewritten in place
+    if old_path.to_string_lossy().to_lowercase() == new_path.to_string_lossy().to_lowercase() {
+        return write_preset(&renamed, &old_path);
+    }
+    write_preset(&renamed, &new_path)?;
+    fs::remove_file(old_path).map_err(|e| e.to_string())
+}
+
+fn find_user_preset(data: &AppState, name: &str) -> Result<UserPreset, String> {
+    data.user_presets.iter()
+        .find(|preset| preset.name == name)
+        .cloned()
+        .ok_or_else(|| format!("\"{}\" no longer exists", name))
+}
+
+fn check_new_name(data: &AppState, new_name: &str) -> Result<(), String> {
+    if new_name.is_empty() {
+        Err("Type a name for the preset first".to_string())
+    } else if data.user_presets.iter().any(|preset| preset.name.eq_ignore_ascii_case(new_name)) {
+        Err(format!("There is already a preset named \"{}\"", new_name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Switches to the settings of a preset, which the reset buttons in the settings window then go back to
+pub fn apply_preset(data: &mut AppState, settings: &LayoutSettings) {
+    let settings = std::sync::Arc::new(settings.clone());
+    data.layout_settings = settings.clone();
+    data.preset_baseline = settings;
+}
+
+/// Handles the preset commands. Called by the app delegate.
+///
+/// The presets are reloaded from the directory after every change,
+/// and the result is shown to the user in the status line of the Layouts tab.
+pub fn handle_command(cmd: &Command, data: &mut AppState) -> Handled {
+    let new_name = data.preset_name_edit.trim().to_string();
+    let result: Result<String, String> = if let Some(name) = cmd.get(APPLY_USER_PRESET) {
+        find_user_preset(data, name).map(|preset| {
+            apply_preset(data, &preset.settings);
+            format!("Applied \"{}\"", name)
+        })
+    } else if cmd.is(SAVE_USER_PRESET) {
+        check_new_name(data, &new_name)
+            .and_then(|_| save_new_user_preset(&new_name, &data.layout_settings))
+            .map(|_| format!("Saved \"{}\"", new_name))
+    } else if let Some(name) = cmd.get(RENAME_USER_PRESET) {
+        // Changing only the case of the name is allowed
+        let name_taken = if new_name.eq_ignore_ascii_case(name) { Ok(()) } else { check_new_name(data, &new_name) };
+        name_taken
+            .and_then(|_| find_user_preset(data, name))
+            .and_then(|preset| rename_user_preset(&preset, &new_name))
+            .map(|_| format!("Renamed \"{}\" to \"{}\"", name, new_name))
+    } else if let Some(name) = cmd.get(DELETE_USER_PRESET) {
+        find_user_preset(data, name)
+            .and_then(|preset| delete_user_preset(&preset))
+            .map(|_| format!("Deleted \"{}\"", name))
+    } else if let Some(name) = cmd.get(EXPORT_USER_PRESET) {
+        data.exporting_preset = Some(name.clone());
+        return Handled::Yes;
+    } else if cmd.is(EXPORT_PRESET_CANCELLED) {
+        data.exporting_preset = None;
+        return Handled::Yes;
+    } else if let Some(file_info) = cmd.get(EXPORT_PRESET_TO) {
+        let preset = data.exporting_preset.take()
+            .and_then(|name| data.user_presets.iter().find(|preset| preset.name == name).cloned());
+        match preset {
+            Some(preset) => write_preset(&preset, file_info.path())
+                .map(|_| format!("Exported \"{}\" to {}", preset.name, file_info.path().display())),
+            None => Err("Nothing to export".to_string()),
+        }
+    } else if let Some(file_info) = cmd.get(IMPORT_PRESET_FROM) {
+        read_preset_file(file_info.path())
+            .and_then(|preset| {
+                check_new_name(data, &preset.name)?;
+                save_new_user_preset(&preset.name, &preset.settings)?;
+                Ok(format!("Imported \"{}\"", preset.name))
+            })
+    } else if cmd.is(RELOAD_USER_PRESETS) {
+        Ok(String::new())
+    } else {
+        return Handled::No;
+    };
+    data.preset_status = match result {
+        Ok(status) => status,
+        Err(e) => format!("Error: {}", e),
+    };
+    data.user_presets = load_user_presets();
+    Handled::Yes
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use druid::{Selector, im};
+use crate::{AppState, Message, MessageKind, ReplyPreview};
+use crate::helper::{emoji, message_actions, timeline};
+
+/// Pauses, resumes, steps or changes the speed of the replay. Sent by `/replay`.
+pub const CONTROL_REPLAY: Selector<ReplayControl> = Selector::new("polysoft.druid-demo.control_replay");
+
+/// How long a user is shown as typing when the event doesn't say
+const DEFAULT_TYPING_SECONDS: f64 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReplayControl {
+    Pause,
+    Resume,
+    /// Plays the next event right away, and pauses
+    Step,
+    /// How many times faster than real time to play
+    Speed(f64),
+}
+
+/// The format of a transcript file. Each event is an `[[event]]` table.
+#[derive(serde::Deserialize)]
+struct TranscriptFile {
+    #[serde(default, rename = "event")]
+    events: Vec<TranscriptEvent>,
+}
+
+#[derive(serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct TranscriptEvent {
+    /// Seconds from the start of the transcript
+    pub at: f64,
+    /// The user that sends the message, or that edits, deletes, reacts or types
+    pub user: u32,
+    #[serde(flatten)]
+    pub action: TranscriptAction,
+}
+
+/// What happens in an event. Messages are referred to by their `id`, which is only needed
+/// on messages that later events change.
+#[derive(serde::Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TranscriptAction {
+    Message {
+        text: String,
+        #[serde(default)]
+        id: Option<String>,
+        /// The ID of the message that this is a reply to
+        #[serde(default)]
+        reply_to: Option<String>,
+    },
+    /// Shown like a message sent with `/me`
+    Action {
+        text: String,
+        #[serde(default)]
+        id: Option<String>,
+    },
+    Edit {
+        target: String,
+        text: String,
+    },
+    Delete {
+        target: String,
+    },
+    /// Reacting again with the same emoji takes the reaction back, the same as clicking it
+    React {
+        target: String,
+        emoji: String,
+    },
+    /// Shows the user as typing, until they send a message or the time runs out
+    Typing {
+        #[serde(default = "default_typing_seconds")]
+        seconds: f64,
+    },
+}
+
+fn default_typing_seconds() -> f64 {
+    DEFAULT_TYPING_SECONDS
+}
+
+pub fn load_transcript(path: &Path) -> Result<Vec<TranscriptEvent>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    parse_transcript(&text).map_err(|e| format!("Could not read {}: {}", path.display(), e))
+}
+
+/// Reads a transcript, and puts the events in the order they are played.
+/// Events at the same time are played in the order they are in the file.
+///
+/// Events that refer to a message that isn't sent before them are errors,
+/// so mistakes in a transcript are found before it's played.
+pub fn parse_transcript(text: &str) -> Result<Vec<TranscriptEvent>, String> {
+    let file: TranscriptFile = toml::from_str(text).map_err(|e| e.to_string())?;
+    let mut events = file.events;
+    for (index, event) in events.iter().enumerate() {
+        if !event.at.is_finite() || event.at < 0.0 {
+            return Err(format!("Event {} is at {}, but the time has to be 0 or more seconds", index + 1, event.at));
+        }
+        if let TranscriptAction::Typing { seconds } = event.action {
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(format!("Event {} types for {} seconds, which has to be 0 or more", index + 1, seconds));
+            }
+        }
+    }
+    events.sort_by(|a, b| a.at.total_cmp(&b.at));
+
+    let mut sent_ids: Vec<&str> = Vec::new();
+    for event in &events {
+        let describe = || format!("The event at {} seconds", event.at);
+        let (new_id, target) = match &event.action {
+            TranscriptAction::Message { id, reply_to, .. } => (id.as_deref(), reply_to.as_deref()),
+            TranscriptAction::Action { id, .. } => (id.as_deref(), None),
+            TranscriptAction::Edit { target, .. } | TranscriptAction::Delete { target } => (None, Some(target.as_str())),
+            TranscriptAction::React { target, emoji } => {
+                if emoji.trim().is_empty() {
+                    return Err(format!("{} reacts without an emoji", describe()));
+                }
+                (None, Some(target.as_str()))
+            },
+            TranscriptAction::Typing { .. } => (None, None),
+        };
+        if let Some(target) = target {
+            if !sent_ids.contains(&target) {
+                return Err(format!("{} refers to the message \"{}\", which isn't sent before it", describe(), target));
+            }
+        }
+        if let Some(id) = new_id {
+            if sent_ids.contains(&id) {
+                return Err(format!("{} uses the ID \"{}\", which another message already has", describe(), id));
+            }
+            sent_ids.push(id);
+        }
+    }
+    Ok(events)
+}
+
+/// Plays a transcript into the timeline. Owned by the replay controller, which calls it when its timer fires.
+///
+/// The position is in transcript time, so it moves faster than real time when the speed is above 1.
+/// Messages are timestamped with transcript time, so they are grouped the same way at any speed.
+pub struct Replay {
+    events: Vec<TranscriptEvent>,
+    next_event: usize,
+    /// How far into the transcript it has played, in seconds
+    position: f64,
+    speed: f64,
+    paused: bool,
+    /// The time that the transcript starts at, for the message timestamps
+    start_epoch_seconds: i64,
+    /// The timeline IDs of the transcript's messages
+    message_ids: HashMap<String, u64>,
+    /// The users that are typing, and the position at which they stop
+    typing_until: Vec<(u32, f64)>,
+}
+
+impl Replay {
+    pub fn new(events: Vec<TranscriptEvent>, speed: f64, paused: bool, start_epoch_seconds: i64) -> Replay {
+        Replay {
+            events,
+            next_event: 0,
+            position: 0.0,
+            speed,
+            paused,
+            start_epoch_seconds,
+            message_ids: HashMap::new(),
+            typing_until: Vec::new(),
+        }
+    }
+
+    /// Moves forward by the real time that passed, and plays the events that are due.
+    /// Does nothing while paused.
+    pub fn advance(&mut self, state: &mut AppState, elapsed: Duration) {
+        if self.paused {
+            return;
+        }
+        self.position += elapsed.as_secs_f64() * self.speed;
+        while let Some(event) = self.events.get(self.next_event) {
+            if event.at > self.position {
+                break;
+            }
+            let event = event.clone();
+            self.next_event += 1;
+            self.apply(state, &event);
+        }
+        self.update_typing(state);
+        state.replay_status = self.get_status();
+    }
+
+    pub fn control(&mut self, state: &mut AppState, control: ReplayControl) {
+        match control {
+            ReplayControl::Pause => self.paused = true,
+            ReplayControl::Resume => self.paused = false,
+            ReplayControl::Step => {
+                self.paused = true;
+                if let Some(event) = self.events.get(self.next_event).cloned() {
+                    self.next_event += 1;
+                    self.position = self.position.max(event.at);
+                    self.apply(state, &event);
+                    self.update_typing(state);
+                }
+            },
+            ReplayControl::Speed(speed) => self.speed = speed,
+        }
+        state.replay_status = self.get_status();
+    }
+
+    /// The real time until the next event, or until someone stops typing.
+    /// None when paused, or when there is nothing left to play.
+    pub fn get_time_until_next_change(&self) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let next_event = self.events.get(self.next_event).map(|event| event.at);
+        let next_typing_end = self.typing_until.iter().map(|(_, until)| *until).reduce(f64::min);
+        let next_change = match (next_event, next_typing_end) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(at), None) | (None, Some(at)) => at,
+            (None, None) => return None,
+        };
+        Some(Duration::from_secs_f64(((next_change - self.position) / self.speed).max(0.0)))
+    }
+
+    /// A line like "Replaying at 4x: 12 of 40 events, 0:31"
+    pub fn get_status(&self) -> String {
+        let total_seconds = self.position as u64;
+        let progress = format!("{} of {} events, {}:{:02}",
+            self.next_event, self.events.len(), total_seconds / 60, total_seconds % 60);
+        if self.next_event == self.events.len() && self.typing_until.is_empty() {
+            format!("Replay finished: {}", progress)
+        } else if self.paused {
+            format!("Replay paused: {}", progress)
+        } else {
+            format!("Replaying at {}x: {}", self.speed, progress)
+        }
+    }
+
+    fn apply(&mut self, state: &mut AppState, event: &TranscriptEvent) {
+        let timestamp = self.start_epoch_seconds + event.at.round() as i64;
+        match &event.action {
+            TranscriptAction::Message { text, id, reply_to } => {
+                let mut message = Message::new(emoji::expand_shortcodes(text), timestamp, MessageKind::Text);
+                message.reply_to = reply_to.as_ref()
+                    .and_then(|target| self.message_ids.get(target))
+                    .and_then(|target_id| timeline::find_message(state, *target_id))
+                    .map(|(user_id, target)| ReplyPreview { message_id: target.id, user_id, text: target.message.clone() });
+                self.send(state, event.user, message, id);
+            },
+            TranscriptAction::Action { text, id } => {
+                let message = Message::new(emoji::expand_shortcodes(text), timestamp, MessageKind::Action);
+                self.send(state, event.user, message, id);
+            },
+            TranscriptAction::Edit { target, text } => {
+                if let Some((_, message)) = self.message_ids.get(target).and_then(|id| timeline::find_message_mut(state, *id)) {
+                    message.message = emoji::expand_shortcodes(text);
+                    message.edited = true;
+                }
+            },
+            TranscriptAction::Delete { target } => {
+                if let Some(id) = self.message_ids.get(target) {
+                    message_actions::remove_message(state, *id);
+                }
+            },
+            TranscriptAction::React { target, emoji } => {
+                if let Some((_, message)) = self.message_ids.get(target).and_then(|id| timeline::find_message_mut(state, *id)) {
+                    message_actions::toggle_reaction(message, emoji, event.user);
+                }
+            },
+            TranscriptAction::Typing { seconds } => {
+                self.typing_until.retain(|(user_id, _)| *user_id != event.user);
+                self.typing_until.push((event.user, event.at + seconds));
+            },
+        }
+    }
+
+    fn send(&mut self, state: &mut AppState, user_id: u32, message: Message, id: &Option<String>) {
+        if let Some(id) = id {
+            self.message_ids.insert(id.clone(), state.next_message_id);
+        }
+        timeline::push_message(state, user_id, message);
+        // Sending ends the typing
+        self.typing_until.retain(|(typing_user_id, _)| *typing_user_id != user_id);
+    }
+
+    fn update_typing(&mut self, state: &mut AppState) {
+        let position = self.position;
+        self.typing_until.retain(|(_, until)| *until > position);
+        let mut typing_users: Vec<u32> = self.typing_until.iter().map(|(user_id, _)| *user_id).collect();
+        typing_users.sort_unstable();
+        state.typing_users = im::Vector::from(typing_users);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use druid::ImageBuf;
+    use crate::LayoutSettings;
+    use crate::helper::theme;
+
+    const TRANSCRIPT: &str = r#"
+        [[event]]
+        at = 0
+        user = 1
+        type = "message"
+        text = "Hi"
+        id = "hi"
+
+        [[event]]
+        at = 2
+        user = 2
+        type = "typing"
+        seconds = 10
+
+        [[event]]
+        at = 5
+        user = 2
+        type = "message"
+        text = "Hello :wave:"
+        reply_to = "hi"
+
+        [[event]]
+        at = 6
+        user = 1
+        type = "edit"
+        target = "hi"
+        text = "Hi all"
+
+        [[event]]
+        at = 7
+        user = 2
+        type = "react"
+        target = "hi"
+        emoji = "👍"
+
+        [[event]]
+        at = 100
+        user = 1
+        type = "delete"
+        target = "hi"
+    "#;
+
+    fn new_state() -> AppState {
+        let mut state = AppState::new(LayoutSettings::default(), theme::default_theme());
+        state.profile_pics.push_back(ImageBuf::empty());
+        state
+    }
+
+    fn texts(state: &AppState) -> Vec<Vec<String>> {
+        state.timeline_data.iter()
+            .map(|group| group.messages.iter().map(|message| message.message.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn events_are_played_when_they_are_due() {
+        let mut state = new_state();
+        let mut replay = Replay::new(parse_transcript(TRANSCRIPT).unwrap(), 2.0, false, 1_600_000_000);
+        replay.advance(&mut state, Duration::from_secs(1));
+        assert_eq!(texts(&state), vec![vec!["Hi".to_string()]]);
+        assert_eq!(state.typing_users, im::vector![2]);
+        assert_eq!(replay.get_time_until_next_change(), Some(Duration::from_millis(1500)));
+
+        replay.advance(&mut state, Duration::from_secs(3));
+        assert!(state.typing_users.is_empty());
+        let hi = &state.timeline_data[0].messages[0];
+        assert_eq!((hi.message.as_str(), hi.edited, hi.reactions.len()), ("Hi all", true, 1));
+        let reply = &state.timeline_data[1].messages[0];
+        assert_eq!(reply.reply_to.as_ref().map(|reply| reply.message_id), Some(hi.id));
+        assert_eq!(reply.timestamp_epoch_seconds, 1_600_000_005);
+
+        replay.advance(&mut state, Duration::from_secs(100));
+        assert_eq!(texts(&state).len(), 1);
+        assert_eq!(replay.get_time_until_next_change(), None);
+        assert!(replay.get_status().starts_with("Replay finished"));
+    }
+
+    #[test]
+    fn stepping_plays_one_event_and_pauses() {
+        let mut state = new_state();
+        let mut replay = Replay::new(parse_transcript(TRANSCRIPT).unwrap(), 1.0, true, 0);
+        replay.advance(&mut state, Duration::from_secs(60));
+        assert!(state.timeline_data.is_empty());
+        replay.control(&mut state, ReplayControl::Step);
+        replay.control(&mut state, ReplayControl::Step);
+        assert_eq!(texts(&state).len(), 1);
+        assert_eq!(state.typing_users, im::vector![2]);
+        assert_eq!(replay.get_time_until_next_change(), None);
+        replay.control(&mut state, ReplayControl::Resume);
+        assert_eq!(replay.get_time_until_next_change(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn events_are_sorted_by_time() {
+        let events = parse_transcript(r#"
+            [[event]]
+            at = 3
+            user = 0
+            type = "message"
+            text = "second"
+
+            [[event]]
+            at = 1.5
+            user = 0
+            type = "action"
+            text = "first"
+        "#).unwrap();
+        assert_eq!(events.iter().map(|event| event.at).collect::<Vec<f64>>(), vec![1.5, 3.0]);
+    }
+
+    #[test]
+    fn invalid_transcripts_are_errors() {
+        let event = |fields: &str| format!("[[event]]\nuser = 0\n{}", fields);
+        assert!(parse_transcript(&event("at = 0\ntype = \"shout\"\ntext = \"Hi\"")).is_err());
+        assert!(parse_transcript(&event("at = -1\ntype = \"message\"\ntext = \"Hi\"")).is_err());
+        assert!(parse_transcript(&event("at = 0\ntype = \"delete\"\ntarget = \"missing\"")).is_err());
+        assert!(parse_transcript(&event("at = 0\ntype = \"typing\"\nseconds = -2")).is_err());
+        let duplicate = format!("{}\n{}",
+            event("at = 0\ntype = \"message\"\ntext = \"a\"\nid = \"x\""),
+            event("at = 1\ntype = \"message\"\ntext = \"b\"\nid = \"x\""));
+        assert!(parse_transcript(&duplicate).is_err());
+        // The target has to be sent first, even when it's earlier in the file
+        let out_of_order = format!("{}\n{}",
+            event("at = 5\ntype = \"message\"\ntext = \"a\"\nid = \"x\""),
+            event("at = 1\ntype = \"edit\"\ntarget = \"x\"\ntext = \"b\""));
+        assert!(parse_transcript(&out_of_order).is_err());
+    }
+
+    #[test]
+    fn the_example_transcript_loads() {
+        parse_transcript(include_str!("../../transcripts/grouping.toml")).unwrap();
+    }
+}
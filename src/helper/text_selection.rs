@@ -0,0 +1,228 @@
+use druid::{Command, Env, Handled, Selector};
+use crate::AppState;
+use crate::helper::helper_functions;
+use crate::helper::helper_functions::TimestampFormat;
+use crate::widgets::single_message_widget;
+
+/// Starts a new selection, replacing the current one. The payload is the selection and the click count.
+pub const SET_TEXT_SELECTION: Selector<(TextSelection, u8)> = Selector::new("polysoft.druid-demo.set_text_selection");
+/// Moves the end of the current selection, like when shift clicking
+pub const EXTEND_TEXT_SELECTION: Selector<SelectionPoint> = Selector::new("polysoft.druid-demo.extend_text_selection");
+/// Moves the end of the current selection while dragging, unless it was started with a double or triple click
+pub const DRAG_TEXT_SELECTION: Selector<SelectionPoint> = Selector::new("polysoft.druid-demo.drag_text_selection");
+pub const COPY_TEXT_SELECTION: Selector = Selector::new("polysoft.druid-demo.copy_text_selection");
+pub const SELECT_ALL_TEXT: Selector = Selector::new("polysoft.druid-demo.select_all_text");
+
+/// A position in the text of a message. The offset is in bytes of the displayed text.
+#[derive(Clone, Copy, PartialEq, Debug, druid::Data)]
+pub struct SelectionPoint {
+    pub message_id: u64,
+    pub offset: usize,
+}
+
+/// A selection that can span several messages.
+///
+/// The anchor is where the selection was started, and the focus is where it ends.
+/// The focus can be before the anchor when selecting backwards.
+#[derive(Clone, Copy, PartialEq, Debug, druid::Data)]
+pub struct TextSelection {
+    pub anchor: SelectionPoint,
+    pub focus: SelectionPoint,
+}
+
+impl TextSelection {
+    pub fn new(anchor: SelectionPoint, focus: SelectionPoint) -> TextSelection {
+        TextSelection { anchor, focus }
+    }
+
+    /// Message IDs increase in timeline order, so points can be ordered by ID and then by offset.
+    pub fn start_and_end(&self) -> (SelectionPoint, SelectionPoint) {
+        if (self.anchor.message_id, self.anchor.offset) <= (self.focus.message_id, self.focus.offset) {
+            (self.anchor, self.focus)
+        } else {
+            (self.focus, self.anchor)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    /// Gets the selected part of one message, or none if the message is not selected.
+    pub fn range_for_message(&self, message_id: u64, text: &str) -> Option<std::ops::Range<usize>> {
+        let (start, end) = self.start_and_end();
+        if message_id < start.message_id || message_id > end.message_id {
+            return None;
+        }
+        let range_start = if message_id == start.message_id { start.offset } else { 0 };
+        let range_end = if message_id == end.message_id { end.offset } else { text.len() };
+        let range_start = floor_char_boundary(text, range_start);
+        let range_end = floor_char_boundary(text, range_end);
+        if range_start < range_end {
+            Some(range_start..range_end)
+        } else {
+            None
+        }
+    }
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Gets the range of the word at the offset, for selecting with a double click.
+/// When the offset is not on a word, only the character there is selected.
+pub fn word_range_at(text: &str, offset: usize) -> std::ops::Range<usize> {
+    let offset = floor_char_boundary(text, offset);
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let start = text[..offset].char_indices().rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(offset, |(index, _)| index);
+    let end = text[offset..].char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(text.len(), |(index, _)| offset + index);
+    if start == end {
+        // Not on a word, so select the character under the pointer
+        let next_char_len = text[offset..].chars().next().map_or(0, char::len_utf8);
+        return offset..offset + next_char_len;
+    }
+    start..end
+}
+
+/// Builds the plain text that is put on the clipboard.
+///
+/// When the selection is inside one message, only the selected text is copied.
+/// Otherwise each group starts with a line with the sender and time, followed by the selected text of each message.
+pub fn get_selected_text(state: &AppState, selection: &TextSelection, self_id: u32) -> String {
+    let (start, end) = selection.start_and_end();
+    let mut sections: Vec<String> = Vec::new();
+    for group in state.timeline_data.iter() {
+        let sender_name = helper_functions::format_display_name(group.user_id, self_id, &state.self_display_name);
+        let mut lines: Vec<String> = Vec::new();
+        let mut first_time = None;
+        for message in group.messages.iter() {
+            let text = single_message_widget::format_display_text(message, &sender_name);
+            if let Some(range) = selection.range_for_message(message.id, &text) {
+                first_time.get_or_insert(message.timestamp_epoch_seconds);
+                lines.push(text[range].to_string());
+            }
+        }
+        if let Some(time) = first_time {
+            if start.message_id == end.message_id {
+                return lines.join("\n");
+            }
+            sections.push(format!(
+                "{} ({}):\n{}",
+                sender_name,
+                helper_functions::timestamp_to_display_msg(time, TimestampFormat::Full24),
+                lines.join("\n")
+            ));
+        }
+    }
+    sections.join("\n\n")
+}
+
+/// Selects the text of every message in the timeline.
+fn select_all(state: &AppState, env: &Env) -> Option<TextSelection> {
+    let first = state.timeline_data.front()?.messages.front()?;
+    let last_group = state.timeline_data.back()?;
+    let last = last_group.messages.back()?;
+    let self_id = env.get(crate::SELF_USER_ID_KEY) as u32;
+    let sender_name = helper_functions::format_display_name(last_group.user_id, self_id, &state.self_display_name);
+    let last_len = single_message_widget::format_display_text(last, &sender_name).len();
+    Some(TextSelection::new(
+        SelectionPoint { message_id: first.id, offset: 0 },
+        SelectionPoint { message_id: last.id, offset: last_len },
+    ))
+}
+
+/// Handles the text selection commands. Called by the app delegate.
+///
+/// The messages draw the selection from the app state, so nothing else has to be told when it changes.
+pub fn handle_command(cmd: &Command, data: &mut AppState, env: &Env) -> Handled {
+    let extend = |data: &AppState, focus: SelectionPoint| match data.text_selection {
+        Some(selection) => TextSelection::new(selection.anchor, focus),
+        None => TextSelection::new(focus, focus),
+    };
+    if let Some((selection, click_count)) = cmd.get(SET_TEXT_SELECTION) {
+        data.text_selection = Some(*selection);
+        data.text_selection_click_count = *click_count;
+    } else if let Some(focus) = cmd.get(EXTEND_TEXT_SELECTION) {
+        data.text_selection = Some(extend(data, *focus));
+    } else if let Some(focus) = cmd.get(DRAG_TEXT_SELECTION) {
+        if data.text_selection_click_count <= 1 {
+            data.text_selection = Some(extend(data, *focus));
+        }
+    } else if cmd.is(SELECT_ALL_TEXT) {
+        data.text_selection = select_all(data, env);
+    } else if cmd.is(COPY_TEXT_SELECTION) {
+        if let Some(selection) = data.text_selection {
+            if !selection.is_empty() {
+                let text = get_selected_text(data, &selection, env.get(crate::SELF_USER_ID_KEY) as u32);
+                druid::Application::global().clipboard().put_string(text);
+            }
+        }
+    } else {
+        return Handled::No;
+    }
+    Handled::Yes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use druid::ImageBuf;
+    use crate::{LayoutSettings, Message, MessageKind};
+    use crate::helper::{theme, timeline};
+
+    fn point(message_id: u64, offset: usize) -> SelectionPoint {
+        SelectionPoint { message_id, offset }
+    }
+
+    #[test]
+    fn double_clicks_select_words() {
+        let text = "hello wörld, it's_me!";
+        assert_eq!(word_range_at(text, 0), 0..5);
+        assert_eq!(word_range_at(text, 3), 0..5);
+        assert_eq!(&text[word_range_at(text, 8)], "wörld");
+        // In the middle of the two byte ö
+        assert_eq!(&text[word_range_at(text, 8)], &text[word_range_at(text, 9)]);
+        assert_eq!(&text[word_range_at(text, 14)], "it's_me");
+        // Right after a word is still on it
+        assert_eq!(&text[word_range_at(text, 5)], "hello");
+        assert_eq!(&text[word_range_at(text, 13)], " ");
+        assert_eq!(word_range_at(text, text.len()), text.len()..text.len());
+        assert_eq!(word_range_at("", 0), 0..0);
+        assert_eq!(word_range_at("a b", 100), 2..3);
+    }
+
+    #[test]
+    fn selections_are_copied_as_text() {
+        let mut state = AppState::new(LayoutSettings::default(), theme::default_theme());
+        state.profile_pics.push_back(ImageBuf::empty());
+        state.self_display_name = "Me".into();
+        timeline::push_message(&mut state, 1, Message::new("first message".to_string(), 0, MessageKind::Text));
+        timeline::push_message(&mut state, 1, Message::new("second message".to_string(), 10, MessageKind::Text));
+        timeline::push_message(&mut state, 0, Message::new("waves".to_string(), 20, MessageKind::Action));
+
+        let inside_one = TextSelection::new(point(1, 6), point(1, 0));
+        assert_eq!(get_selected_text(&state, &inside_one, 0), "second");
+
+        let across_groups = TextSelection::new(point(0, 6), point(2, 4));
+        let text = get_selected_text(&state, &across_groups, 0);
+        let sections: Vec<&str> = text.split("\n\n").collect();
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].starts_with("User1 ("));
+        assert!(sections[0].ends_with("):\nmessage\nsecond message"));
+        assert!(sections[1].starts_with("Me ("));
+        assert!(sections[1].ends_with("):\n* Me"));
+
+        let nothing = TextSelection::new(point(5, 0), point(6, 0));
+        assert_eq!(get_selected_text(&state, &nothing, 0), "");
+    }
+}
\ No newline at end of file
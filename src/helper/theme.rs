@@ -0,0 +1,355 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use druid::{Color, Command, FileInfo, Handled, Selector};
+use druid::env::Key;
+use crate::AppState;
+use crate::helper::color::SimpleColor;
+use crate::helper::config;
+
+/// The themes that come with the app. The first one is the default,
+/// and has every color, so other themes can leave colors out.
+const BUILT_IN_THEME_FILES: [(&str, &str); 3] = [
+    ("dark.toml", include_str!("../assets/themes/dark.toml")),
+    ("light.toml", include_str!("../assets/themes/light.toml")),
+    ("high-contrast.toml", include_str!("../assets/themes/high-contrast.toml")),
+];
+
+/// Theme files added by the user go in this directory, inside the config directory
+const THEMES_DIR_NAME: &str = "themes";
+const THEME_EXTENSION: &str = "toml";
+
+/// The payload is the name of the theme
+pub const APPLY_THEME: Selector<String> = Selector::new("polysoft.druid-demo.apply_theme");
+/// Sent by the save dialog when exporting the current theme
+pub const EXPORT_THEME_TO: Selector<FileInfo> = Selector::new("polysoft.druid-demo.export_theme_to");
+
+// Env keys for the colors of the app's own widgets.
+// The rest of the colors use the keys from druid::theme.
+pub const OTHER_BUBBLE_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.other_bubble_color");
+pub const SELF_BUBBLE_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.self_bubble_color");
+pub const SENDER_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.sender_color");
+pub const DATETIME_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.datetime_color");
+pub const SELF_SENDER_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.self_sender_color");
+pub const SELF_DATETIME_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.self_datetime_color");
+pub const LEFT_LINE_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.left_line_color");
+pub const MESSAGE_HOVER_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.message_hover_color");
+pub const SELECTED_MESSAGE_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.selected_message_color");
+pub const NOTICE_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.notice_color");
+pub const ACTION_BAR_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.action_bar_color");
+pub const ACTION_BAR_BORDER_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.action_bar_border_color");
+pub const HOT_BUTTON_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.hot_button_color");
+pub const WARNING_COLOR_KEY: Key<Color> = Key::new("polysoft.druid-demo.warning_color");
+
+/// Every color used in the windows
+#[derive(Clone, PartialEq, druid::Data, druid::Lens, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeColors {
+    pub window_background: SimpleColor,
+    /// The header, the composer, and the emoji picker
+    pub panel_background: SimpleColor,
+    pub input_background: SimpleColor,
+    pub text: SimpleColor,
+    pub disabled_text: SimpleColor,
+    pub placeholder_text: SimpleColor,
+    /// Focus rings and other highlights
+    pub accent_light: SimpleColor,
+    pub accent_dark: SimpleColor,
+    /// Slider knobs and other controls
+    pub foreground_light: SimpleColor,
+    pub foreground_dark: SimpleColor,
+    pub button_light: SimpleColor,
+    pub button_dark: SimpleColor,
+    pub border_light: SimpleColor,
+    pub border_dark: SimpleColor,
+    pub selected_text_background: SimpleColor,
+    pub cursor: SimpleColor,
+    pub other_bubble: SimpleColor,
+    pub self_bubble: SimpleColor,
+    pub sender: SimpleColor,
+    pub datetime: SimpleColor,
+    /// For when the self bubble's color messes with the visibility of the text
+    pub self_sender: SimpleColor,
+    pub self_datetime: SimpleColor,
+    /// The line beside every message, when it is shown
+    pub left_line: SimpleColor,
+    pub message_hover: SimpleColor,
+    pub selected_message: SimpleColor,
+    /// Local notices, like the output of slash commands
+    pub notice: SimpleColor,
+    pub action_bar: SimpleColor,
+    pub action_bar_border: SimpleColor,
+    /// The highlight of the button or emoji under the pointer
+    pub hot_button: SimpleColor,
+    pub warning: SimpleColor,
+}
+
+#[derive(Clone, PartialEq, druid::Data, druid::Lens, serde::Serialize)]
+pub struct Theme {
+    pub name: String,
+    pub colors: ThemeColors,
+}
+
+/// The format of a theme file.
+/// Colors that are left out come from the base theme, or the default theme if there is no base.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    base: Option<String>,
+    #[serde(default)]
+    colors: toml::value::Table,
+}
+
+impl ThemeColors {
+    pub fn set_env(&self, env: &mut druid::Env) {
+        env.set(druid::theme::WINDOW_BACKGROUND_COLOR, self.window_background.to_druid_color());
+        env.set(druid::theme::BACKGROUND_LIGHT, self.panel_background.to_druid_color());
+        env.set(druid::theme::BACKGROUND_DARK, self.input_background.to_druid_color());
+        env.set(druid::theme::TEXT_COLOR, self.text.to_druid_color());
+        env.set(druid::theme::DISABLED_TEXT_COLOR, self.disabled_text.to_druid_color());
+        env.set(druid::theme::PLACEHOLDER_COLOR, self.placeholder_text.to_druid_color());
+        env.set(druid::theme::PRIMARY_LIGHT, self.accent_light.to_druid_color());
+        env.set(druid::theme::PRIMARY_DARK, self.accent_dark.to_druid_color());
+        env.set(druid::theme::FOREGROUND_LIGHT, self.foreground_light.to_druid_color());
+        env.set(druid::theme::FOREGROUND_DARK, self.foreground_dark.to_druid_color());
+        env.set(druid::theme::BUTTON_LIGHT, self.button_light.to_druid_color());
+        env.set(druid::theme::BUTTON_DARK, self.button_dark.to_druid_color());
+        env.set(druid::theme::BORDER_LIGHT, self.border_light.to_druid_color());
+        env.set(druid::theme::BORDER_DARK, self.border_dark.to_druid_color());
+        env.set(druid::theme::SELECTED_TEXT_BACKGROUND_COLOR, self.selected_text_background.to_druid_color());
+        env.set(druid::theme::CURSOR_COLOR, self.cursor.to_druid_color());
+        env.set(OTHER_BUBBLE_COLOR_KEY, self.other_bubble.to_druid_color());
+        env.set(SELF_BUBBLE_COLOR_KEY, self.self_bubble.to_druid_color());
+        env.set(SENDER_COLOR_KEY, self.sender.to_druid_color());
+        env.set(DATETIME_COLOR_KEY, self.datetime.to_druid_color());
+        env.set(SELF_SENDER_COLOR_KEY, self.self_sender.to_druid_color());
+        env.set(SELF_DATETIME_COLOR_KEY, self.self_datetime.to_druid_color());
+        env.set(LEFT_LINE_COLOR_KEY, self.left_line.to_druid_color());
+        env.set(MESSAGE_HOVER_COLOR_KEY, self.message_hover.to_druid_color());
+        env.set(SELECTED_MESSAGE_COLOR_KEY, self.selected_message.to_druid_color());
+        env.set(NOTICE_COLOR_KEY, self.notice.to_druid_color());
+        env.set(ACTION_BAR_COLOR_KEY, self.action_bar.to_druid_color());
+        env.set(ACTION_BAR_BORDER_COLOR_KEY, self.action_bar_border.to_druid_color());
+        env.set(HOT_BUTTON_COLOR_KEY, self.hot_button.to_druid_color());
+        env.set(WARNING_COLOR_KEY, self.warning.to_druid_color());
+    }
+
+    pub fn get_bubble_color(&self, is_self_user: bool) -> Color {
+        if is_self_user {
+            self.self_bubble.to_druid_color()
+        } else {
+            self.other_bubble.to_druid_color()
+        }
+    }
+
+    // Note: In the future, this could be determined by the server's preferences
+    pub fn get_sender_color(&self, is_self_user: bool) -> Color {
+        if is_self_user {
+            self.self_sender.to_druid_color()
+        } else {
+            self.sender.to_druid_color()
+        }
+    }
+
+    pub fn get_datetime_color(&self, is_self_user: bool) -> Color {
+        if is_self_user {
+            self.self_datetime.to_druid_color()
+        } else {
+            self.datetime.to_druid_color()
+        }
+    }
+}
+
+pub fn bubble_color_key(is_self_user: bool) -> Key<Color> {
+    if is_self_user { SELF_BUBBLE_COLOR_KEY } else { OTHER_BUBBLE_COLOR_KEY }
+}
+
+pub fn sender_color_key(is_self_user: bool) -> Key<Color> {
+    if is_self_user { SELF_SENDER_COLOR_KEY } else { SENDER_COLOR_KEY }
+}
+
+pub fn datetime_color_key(is_self_user: bool) -> Key<Color> {
+    if is_self_user { SELF_DATETIME_COLOR_KEY } else { DATETIME_COLOR_KEY }
+}
+
+/// Reads a theme file, or the theme saved in the config file.
+///
+/// The base has to be one of the given themes. Unknown or invalid colors are errors,
+/// so mistakes in a hand written theme are found right away.
+pub fn parse_theme(value: toml::Value, themes: &[Theme]) -> Result<Theme, String> {
+    let file: ThemeFile = value.try_into().map_err(|e: toml::de::Error| e.to_string())?;
+    let base = match &file.base {
+        Some(base_name) => find_theme(themes, base_name)
+            .ok_or_else(|| format!("There is no theme named \"{}\" to use as the base", base_name))?
+            .colors.clone(),
+        None => default_theme().colors,
+    };
+    let mut merged = match toml::Value::try_from(&base) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("Theme colors are always serialized to a table"),
+    };
+    for (key, value) in file.colors {
+        if !merged.contains_key(&key) {
+            return Err(format!("Unknown theme color \"{}\"", key));
+        }
+        merged.insert(key, value);
+    }
+    let colors = toml::Value::Table(merged).try_into().map_err(|e: toml::de::Error| e.to_string())?;
+    Ok(Theme { name: file.name, colors })
+}
+
+fn parse_theme_text(text: &str, themes: &[Theme]) -> Result<Theme, String> {
+    let value = text.parse::<toml::Value>().map_err(|e| e.to_string())?;
+    parse_theme(value, themes)
+}
+
+fn find_theme<'a>(themes: &'a [Theme], name: &str) -> Option<&'a Theme> {
+    themes.iter().find(|theme| theme.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// The dark theme, which is used when nothing else is chosen
+pub fn default_theme() -> Theme {
+    let value = BUILT_IN_THEME_FILES[0].1.parse::<toml::Value>().expect("The default theme is TOML");
+    let file: ThemeFile = value.try_into().expect("The default theme has a name and colors");
+    let colors = toml::Value::Table(file.colors).try_into().expect("The default theme has every color");
+    Theme { name: file.name, colors }
+}
+
+pub fn get_themes_dir() -> Option<PathBuf> {
+    config::get_config_dir().map(|dir| dir.join(THEMES_DIR_NAME))
+}
+
+/// Loads the built in themes, followed by the user's themes sorted by name.
+/// Themes that can't be read, or that have the same name as an earlier theme, are skipped with a warning.
+pub fn load_themes() -> druid::im::Vector<Theme> {
+    let mut themes: Vec<Theme> = Vec::new();
+    for (file_name, text) in BUILT_IN_THEME_FILES {
+        match parse_theme_text(text, &themes) {
+            Ok(theme) => themes.push(theme),
+            Err(e) => tracing::error!("Could not load the theme {}: {}", file_name, e),
+        }
+    }
+    let mut user_theme_paths: Vec<PathBuf> = match get_themes_dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_theme_file(path))
+            .collect(),
+        // No themes have been added
+        _ => Vec::new(),
+    };
+    user_theme_paths.sort();
+    let mut user_themes = Vec::new();
+    for path in user_theme_paths {
+        match read_theme_file(&path, &themes) {
+            Ok(theme) if find_theme(&themes, &theme.name).is_some() || find_theme(&user_themes, &theme.name).is_some() => {
+                tracing::warn!("Skipping theme {}: there is already a theme named \"{}\"", path.display(), theme.name);
+            },
+            Ok(theme) => user_themes.push(theme),
+            Err(e) => tracing::warn!("Skipping theme {}: {}", path.display(), e),
+        }
+    }
+    user_themes.sort_by_key(|theme| theme.name.to_lowercase());
+    themes.into_iter().chain(user_themes).collect()
+}
+
+pub fn is_theme_file(path: &Path) -> bool {
+    path.extension() == Some(std::ffi::OsStr::new(THEME_EXTENSION))
+}
+
+/// Reads a user's theme file. User themes can be based on any built in theme.
+fn read_theme_file(path: &Path, built_in_themes: &[Theme]) -> Result<Theme, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_theme_text(&text, built_in_themes)
+}
+
+/// Reloads the themes after a theme file changed.
+/// When the changed file has the current theme, its colors are applied right away.
+pub fn reload_theme_file(path: &Path, data: &mut AppState) -> Result<(), String> {
+    data.themes = load_themes();
+    if !path.exists() {
+        // A removed theme stays applied until another one is chosen
+        return Ok(());
+    }
+    let built_in_themes: Vec<Theme> = data.themes.iter().take(BUILT_IN_THEME_FILES.len()).cloned().collect();
+    let theme = read_theme_file(path, &built_in_themes)?;
+    if theme.name.eq_ignore_ascii_case(&data.theme.name) {
+        data.theme = theme;
+    }
+    Ok(())
+}
+
+/// Writes the theme in the theme file format, with every color.
+fn write_theme(theme: &Theme, path: &Path) -> Result<(), String> {
+    let text = toml::Value::try_from(theme)
+        .and_then(|value| toml::to_string_pretty(&value))
+        .map_err(|e| e.to_string())?;
+    config::write_file(path, &text).map_err(|e| e.to_string())
+}
+
+/// Handles the theme commands. Called by the app delegate.
+pub fn handle_command(cmd: &Command, data: &mut AppState) -> Handled {
+    if let Some(name) = cmd.get(APPLY_THEME) {
+        let themes: Vec<Theme> = data.themes.iter().cloned().collect();
+        data.theme_status = match find_theme(&themes, name) {
+            Some(theme) => {
+                data.theme = theme.clone();
+                String::new()
+            },
+            None => format!("Error: \"{}\" no longer exists", name),
+        };
+        Handled::Yes
+    } else if let Some(file_info) = cmd.get(EXPORT_THEME_TO) {
+        data.theme_status = match write_theme(&data.theme, file_info.path()) {
+            Ok(()) => format!("Exported \"{}\" to {}", data.theme.name, file_info.path().display()),
+            Err(e) => format!("Error: {}", e),
+        };
+        Handled::Yes
+    } else {
+        Handled::No
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::color;
+
+    #[test]
+    fn built_in_themes_load() {
+        let mut themes = Vec::new();
+        for (file_name, text) in BUILT_IN_THEME_FILES {
+            match parse_theme_text(text, &themes) {
+                Ok(theme) => themes.push(theme),
+                Err(e) => panic!("{}: {}", file_name, e),
+            }
+        }
+    }
+
+    #[test]
+    fn light_and_high_contrast_themes_have_readable_text() {
+        // The dark theme keeps the original blue bubble, which is a little under AA with white text
+        for (_, text) in &BUILT_IN_THEME_FILES[1..] {
+            let theme = parse_theme_text(text, &[]).unwrap();
+            let background = theme.colors.window_background.to_druid_color();
+            for is_self_user in [false, true] {
+                let bubble = color::blend(&theme.colors.get_bubble_color(is_self_user), &background);
+                let contrast = color::contrast_ratio(&theme.colors.text.to_druid_color(), &bubble);
+                assert!(contrast >= color::WCAG_AA_CONTRAST, "{}: {:.1}", theme.name, contrast);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_colors_come_from_the_base() {
+        let dark = default_theme();
+        let theme = parse_theme_text("name = \"Test\"\n[colors]\ntext = \"#123456\"\n", std::slice::from_ref(&dark)).unwrap();
+        assert_eq!(theme.colors.text.to_hex(), "#123456");
+        assert!(theme.colors.window_background == dark.colors.window_background);
+    }
+
+    #[test]
+    fn unknown_colors_are_errors() {
+        assert!(parse_theme_text("name = \"Test\"\n[colors]\ntxet = \"#123456\"\n", &[]).is_err());
+        assert!(parse_theme_text("name = \"Test\"\nbase = \"Missing\"\n", &[]).is_err());
+    }
+}
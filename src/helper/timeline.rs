@@ -0,0 +1,88 @@
+use druid::im;
+use crate::{AppState, Message, MessageGroup, MessageKind};
+
+/// Messages from the same user sent within this many seconds of each other are grouped.
+pub const GROUPING_INTERVAL_SECONDS: i64 = 60;
+
+/// Appends a message to the end of the timeline.
+///
+/// The message is added to the last group when that group is from the same user
+/// and its last message was sent less than a minute before. Otherwise a new group is started.
+/// Only regular text messages are grouped, so actions and notices always stand out.
+/// The message is given the next message ID.
+pub fn push_message(state: &mut AppState, user_id: u32, mut message: Message) {
+    message.id = state.next_message_id;
+    state.next_message_id += 1;
+    if let Some(last_group) = state.timeline_data.back_mut() {
+        let can_group = match last_group.messages.back() {
+            Some(last_message) => {
+                last_group.user_id == user_id
+                    && last_message.kind == MessageKind::Text
+                    && message.kind == MessageKind::Text
+                    && message.timestamp_epoch_seconds - last_message.timestamp_epoch_seconds < GROUPING_INTERVAL_SECONDS
+            },
+            None => false,
+        };
+        if can_group {
+            message.position_in_group = last_group.messages.len() as u32;
+            last_group.messages.push_back(message);
+            return;
+        }
+    }
+    message.position_in_group = 0;
+    let profile_pic = state.profile_pics[user_id as usize % state.profile_pics.len()].clone();
+    state.timeline_data.push_back(MessageGroup {
+        user_id,
+        profile_pic,
+        messages: im::vector![message],
+    });
+}
+
+/// Shows a message in the timeline that is only visible locally, like command errors.
+pub fn push_local_notice(state: &mut AppState, self_id: u32, text: String) {
+    push_message(state, self_id, Message::new(
+        text,
+        chrono::offset::Local::now().timestamp(),
+        MessageKind::Notice,
+    ));
+}
+
+/// Finds a message by ID. Also returns the ID of the user that sent it.
+pub fn find_message(state: &AppState, message_id: u64) -> Option<(u32, &Message)> {
+    state.timeline_data.iter().find_map(|group| {
+        group.messages.iter()
+            .find(|message| message.id == message_id)
+            .map(|message| (group.user_id, message))
+    })
+}
+
+/// Finds a message by ID so it can be changed. Also returns the ID of the user that sent it.
+pub fn find_message_mut(state: &mut AppState, message_id: u64) -> Option<(u32, &mut Message)> {
+    state.timeline_data.iter_mut().find_map(|group| {
+        let user_id = group.user_id;
+        group.messages.iter_mut()
+            .find(|message| message.id == message_id)
+            .map(|message| (user_id, message))
+    })
+}
+
+/// Removes a message. Its group is removed too if it was the last message in it.
+/// Returns false if there is no message with the ID.
+pub fn delete_message(state: &mut AppState, message_id: u64) -> bool {
+    let group_index = state.timeline_data.iter()
+        .position(|group| group.messages.iter().any(|message| message.id == message_id));
+    let group_index = match group_index {
+        Some(index) => index,
+        None => return false,
+    };
+    let group = &mut state.timeline_data[group_index];
+    group.messages.retain(|message| message.id != message_id);
+    if group.messages.is_empty() {
+        state.timeline_data.remove(group_index);
+    } else {
+        for (position, message) in group.messages.iter_mut().enumerate() {
+            message.position_in_group = position as u32;
+        }
+    }
+    true
+}
@@ -1,54 +1,39 @@
 use druid::{AppLauncher, WindowDesc, PlatformError, ImageBuf, AppDelegate};
 use druid::im;
 use druid;
-use rand::rngs::ThreadRng;
 use std::sync;
-use rand::Rng;
 use std::path::Path;
-use std::env;
+use clap::Parser;
 
 use helper::layout_settings::LayoutSettings;
+use helper::preset_registry::PresetRegistry;
+use helper::{audit, benchmark, cli, config, config_watcher, demo_data, flood, message_actions, presets, replay, text_selection, theme};
+use slash_commands::CommandRegistry;
+use widgets::chat_window_widget;
 
 mod widgets;
 mod helper;
 mod settings_ui;
 mod chat_ui;
+mod slash_commands;
 
-// Env keys to define layout in the environment
-pub const ITEM_LAYOUT_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.item_layout");
-pub const METADATA_LAYOUT_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.metadata_layout");
-pub const PICTURE_SHAPE_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.picture_shape");
-pub const PICTURE_SIZE_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.picture_size");
-pub const CHAT_BUBBLE_TAIL_SHAPE_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.tail_shape");
-pub const CHAT_BUBBLE_TAIL_SIZE_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.tail_size");
-pub const CHAT_BUBBLE_RADIUS_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.bubble_radius");
-pub const CHAT_BUBBLE_IMG_SPACING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.bubble_img_spacing");
-pub const SELF_USER_ID_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.self_user");
-pub const SHOW_SELF_PROFILE_PIC_KEY: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.show_self_pic");
-pub const BUBBLE_PADDING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.bubble_padding");
-pub const METADATA_CONTENT_SPACING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.metadata_content_padding");
-pub const ALIGN_TO_PICTURE: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.align-to-picture");
+// The layout settings, shared by every timeline item. Replaced when the settings change, so they are only compared by pointer.
+pub const LAYOUT_SETTINGS_KEY: druid::env::Key<sync::Arc<LayoutSettings>> = druid::env::Key::new("polysoft.druid-demo.layout_settings");
+// Copies of the settings that druid's own widgets read, since they take a key instead of the settings
 pub const GROUP_SPACING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.group_spacing");
 pub const SINGLE_MESSAGE_SPACING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.single_message_spacing");
-pub const SHOW_LEFT_LINE_KEY: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.show_left_line");
-pub const LEFT_SPACING_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.left_spacing");
-pub const LEFT_BUBBLE_FLIPPED_KEY: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.left_bubble_flipped");
-pub const RIGHT_BUBBLE_FLIPPED_KEY: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.right_bubble_flipped");
 pub const SENDER_FONT_SIZE_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.sender_font_size");
 pub const CONTENT_FONT_SIZE_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.content_font_size");
 pub const DATETIME_FONT_SIZE_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.datetime_font_size");
-pub const HEADER_FONT_BOLDED_KEY: druid::env::Key<bool> = druid::env::Key::new("polysoft.druid-demo.metadata_font_bolded");
-pub const DATETIME_FORMAT_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.datetime_format");
-pub const SIDE_TIME_FORMAT_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.side_time_format");
-pub const SENDER_COLOR_KEY: druid::env::Key<druid::Color> = druid::env::Key::new("polysoft.druid-demo.sender_color");
-pub const DATETIME_COLOR_KEY: druid::env::Key<druid::Color> = druid::env::Key::new("polysoft.druid-demo.datetime_color");
-pub const SELF_DATETIME_COLOR_KEY: druid::env::Key<druid::Color> = druid::env::Key::new("polysoft.druid-demo.self_datetime_color");
-pub const SELF_SENDER_COLOR_KEY: druid::env::Key<druid::Color> = druid::env::Key::new("polysoft.druid-demo.self_sender_color");
-pub const LEFT_META_OFFSET_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.left_meta_offset");
-pub const IRC_STACK_WIDTH_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.irc_stack_width");
-pub const IRC_HEADER_WIDTH_KEY: druid::env::Key<f64> = druid::env::Key::new("polysoft.druid-demo.irc_header_width");
-// Commands to communicate things that need to happen
-const REFRESH_UI_SELECTOR: druid::Selector = druid::Selector::new("polysoft.druid-demo.refresh_ui");
+pub const SELF_USER_ID_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.self_user");
+pub const SELF_DISPLAY_NAME_KEY: druid::env::Key<druid::ArcStr> = druid::env::Key::new("polysoft.druid-demo.self_display_name");
+// Set by each timeline item for its messages, so they know who sent them
+pub const GROUP_USER_ID_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.group_user");
+// The ID of the message selected with the context menu, or NO_SELECTED_MESSAGE
+pub const SELECTED_MESSAGE_KEY: druid::env::Key<u64> = druid::env::Key::new("polysoft.druid-demo.selected_message");
+pub const NO_SELECTED_MESSAGE: u64 = u64::MAX;
+// The text selected in the timeline, from the app state, so every message can draw its part of it
+pub const TEXT_SELECTION_KEY: druid::env::Key<sync::Arc<Option<text_selection::TextSelection>>> = druid::env::Key::new("polysoft.druid-demo.text_selection");
 
 
 #[derive(Clone, druid::Data, druid::Lens)]
@@ -56,8 +41,95 @@ struct AppState {
     text_edit: sync::Arc<String>,
     timeline_data: im::Vector<MessageGroup>,
     profile_pics: im::Vector<ImageBuf>,
-    layout_settings: LayoutSettings,
+    /// Shared with the timeline through the env, so it is replaced rather than changed in place
+    layout_settings: sync::Arc<LayoutSettings>,
+    /// The settings of the last applied preset, or the defaults. The reset buttons go back to these.
+    preset_baseline: sync::Arc<LayoutSettings>,
+    /// The colors of every window
+    theme: theme::Theme,
+    /// The built in themes, followed by the user's themes
+    themes: im::Vector<theme::Theme>,
+    /// The result of the last theme action, shown in the Colors tab
+    theme_status: String,
     settings_open: bool,
+    /// Set with /nick. Empty when the default name should be used.
+    self_display_name: druid::ArcStr,
+    command_registry: sync::Arc<CommandRegistry>,
+    preset_registry: sync::Arc<PresetRegistry>,
+    emoji_picker_open: bool,
+    emoji_search: sync::Arc<String>,
+    /// Most recently used first
+    recent_emoji: im::Vector<String>,
+    /// The ID that the next message gets
+    next_message_id: u64,
+    /// Keyboard shortcuts act on the selected message
+    selected_message: Option<u64>,
+    /// Shown above the composer, and attached to the next sent message
+    replying_to: Option<ReplyPreview>,
+    /// When set, sending replaces the text of this message instead
+    editing_message: Option<u64>,
+    /// Text selected in the timeline, which can span several messages
+    text_selection: Option<text_selection::TextSelection>,
+    /// How many clicks started the text selection. Only a selection started with a single click follows dragging,
+    /// so a word selected with a double click stays selected.
+    text_selection_click_count: u8,
+    /// Presets saved by the user, loaded from the presets directory
+    user_presets: im::Vector<presets::UserPreset>,
+    /// The name used when saving or renaming a preset
+    preset_name_edit: sync::Arc<String>,
+    /// The result of the last preset action, shown in the Layouts tab
+    preset_status: String,
+    /// The preset waiting for the export dialog to be closed
+    exporting_preset: Option<String>,
+    /// Shown at the top of the chat window when a changed config file can't be fully read. Empty when hidden.
+    config_banner: String,
+    /// The width of the timeline preview in the settings window
+    preview_width: f64,
+    /// Filters the controls in the settings window
+    settings_search: sync::Arc<String>,
+    /// The users shown as typing under the timeline
+    typing_users: im::Vector<u32>,
+    /// Shown under the title while a transcript is replayed. Empty otherwise.
+    replay_status: String,
+}
+
+impl AppState {
+    /// Creates the state with an empty timeline and no profile pictures.
+    /// Nothing is read from disk, so the user's themes and presets are left empty.
+    fn new(layout_settings: LayoutSettings, theme: theme::Theme) -> AppState {
+        AppState {
+            text_edit: "".to_string().into(),
+            timeline_data: im::vector![],
+            profile_pics: im::vector![],
+            settings_open: false,
+            layout_settings: sync::Arc::new(layout_settings),
+            preset_baseline: sync::Arc::new(LayoutSettings::default()),
+            theme,
+            themes: im::Vector::new(),
+            theme_status: String::new(),
+            self_display_name: "".into(),
+            command_registry: sync::Arc::new(CommandRegistry::with_builtin_commands()),
+            preset_registry: sync::Arc::new(PresetRegistry::load()),
+            emoji_picker_open: false,
+            emoji_search: String::new().into(),
+            recent_emoji: im::Vector::new(),
+            next_message_id: 0,
+            selected_message: None,
+            replying_to: None,
+            editing_message: None,
+            text_selection: None,
+            text_selection_click_count: 0,
+            user_presets: im::Vector::new(),
+            preset_name_edit: String::new().into(),
+            preset_status: String::new(),
+            exporting_preset: None,
+            config_banner: String::new(),
+            preview_width: 400.0,
+            settings_search: String::new().into(),
+            typing_users: im::Vector::new(),
+            replay_status: String::new(),
+        }
+    }
 }
 
 #[derive(Clone, druid::Data, druid::Lens)]
@@ -69,9 +141,56 @@ struct MessageGroup {
 
 #[derive(Clone, druid::Data)]
 struct Message {
+    /// Unique, and increasing in timeline order
+    id: u64,
     message: String,
     position_in_group: u32,
     timestamp_epoch_seconds: i64,
+    kind: MessageKind,
+    edited: bool,
+    reply_to: Option<ReplyPreview>,
+    reactions: im::Vector<Reaction>,
+}
+
+impl Message {
+    /// Creates a message without an ID, reply, or reactions.
+    /// The ID and position are set when it is added to the timeline.
+    fn new(message: String, timestamp_epoch_seconds: i64, kind: MessageKind) -> Message {
+        Message {
+            id: 0,
+            message,
+            position_in_group: 0,
+            timestamp_epoch_seconds,
+            kind,
+            edited: false,
+            reply_to: None,
+            reactions: im::Vector::new(),
+        }
+    }
+}
+
+/// A copy of the message being replied to, so it can be shown with the reply.
+#[derive(Clone, druid::Data)]
+struct ReplyPreview {
+    message_id: u64,
+    user_id: u32,
+    text: String,
+}
+
+#[derive(Clone, druid::Data)]
+struct Reaction {
+    emoji: String,
+    user_ids: im::Vector<u32>,
+}
+
+#[derive(Clone, Copy, PartialEq, druid::Data)]
+enum MessageKind {
+    /// A regular message
+    Text,
+    /// Sent with /me, and shown as "* User waves"
+    Action,
+    /// Only shown locally, like errors from commands
+    Notice,
 }
 
 struct Delegate {
@@ -79,26 +198,30 @@ struct Delegate {
 }
 
 impl AppDelegate<AppState> for Delegate {
-    fn event(
-        &mut self,
-        _ctx: &mut druid::DelegateCtx,
-        _window_id: druid::WindowId,
-        event: druid::Event,
-        _data: &mut AppState,
-        _env: &druid::Env,
-    ) -> Option<druid::Event> {
-        Some(event)
-    }
-
     fn command(
         &mut self,
         _ctx: &mut druid::DelegateCtx,
         _target: druid::Target,
-        _cmd: &druid::Command,
-        _data: &mut AppState,
-        _env: &druid::Env,
+        cmd: &druid::Command,
+        data: &mut AppState,
+        env: &druid::Env,
     ) -> druid::Handled {
-        druid::Handled::No
+        if message_actions::handle_command(cmd, data, env).is_handled() {
+            return druid::Handled::Yes;
+        }
+        if presets::handle_command(cmd, data).is_handled() {
+            return druid::Handled::Yes;
+        }
+        if theme::handle_command(cmd, data).is_handled() {
+            return druid::Handled::Yes;
+        }
+        if config_watcher::handle_command(cmd, data).is_handled() {
+            return druid::Handled::Yes;
+        }
+        if flood::handle_command(cmd, data).is_handled() {
+            return druid::Handled::Yes;
+        }
+        text_selection::handle_command(cmd, data, env)
     }
 
     fn window_added(
@@ -117,138 +240,55 @@ impl AppDelegate<AppState> for Delegate {
         data.settings_open = false;
         if self.window_count <= 0 {
             println!("All windows closed. Quitting...");
+            // In case the last change is still waiting to be saved
+            config::save_config(&data.layout_settings, &data.theme);
             druid::Application::global().quit();
         }
     }
 }
 
-fn get_chat_window_desc() -> WindowDesc<AppState> {
+fn get_chat_window_desc(size: (f64, f64), replay: Option<replay::Replay>, show_performance_overlay: bool) -> WindowDesc<AppState> {
     let main_window = WindowDesc::new(
-        chat_ui::build_chat_ui()
-    ).window_size((300.0, 450.0));
+        chat_ui::build_chat_ui(replay, show_performance_overlay)
+    ).window_size(size);
     return main_window;
 }
 
-fn get_self_user_from_args() -> u64 {
-    let args: Vec<String> = env::args().collect();
-    if args.len() >= 2 {
-        let parsed_input = args[1].parse::<u64>();
-        match parsed_input {
-            Ok(user_id) => {
-                return user_id;
-            },
-            Err(_e) => {
-                eprintln!("Could not parse first arg. Expected int as user ID.");
-                return 0;
-            }
-        }
-    } else {
-        println!("Using default self user 0");
-        return 0;
-    }
-}
-
-const NOUNS: &'static [&str] = &[
-    "time", "person", "year", "way", "day", "thing", "man", "world", "life",
-    "hand", "part", "child", "eye", "woman", "place", "work", "week", "case",
-    "point",  "government", "company", "number", "group", "problem", "fact"
-];
-const VERBS: &'static [&str] = &[
-    "be", "have", "do", "say", "get", "make", "go", "know", "take", "see",
-    "come", "think", "look", "want", "give", "use", "find", "tell", "ask",
-    "work", "seem", "feel", "try", "leave", "call"
-];
-const ADJECTIVES: &'static [&str] = &[
-    "good", "new", "first", "long", "great", "little", "own", "other", "old",
-    "right", "big", "high", "different", "small", "large", "next", "early",
-    "important", "few", "public", "bad", "same", "able",
-];
-const PREPOSITIONS: &'static [&str] = &[
-    "to", "of", "in", "for", "on", "with", "at", "by", "from", "up", "about",
-    "info", "over", "after",
-];
-const OTHERS: &'static [&str] = &[
-    "the", "and", "a", "that", "I", "it", "not", "he", "as", "you", "this",
-    "but", "his", "they", "her", "she", "or", "an", "will", "my", "one",
-    "all", "would", "there", "their",
-];
-const ALL_WORD_LISTS: &'static [&'static [&str]] = &[
-    NOUNS, VERBS, ADJECTIVES, PREPOSITIONS, OTHERS,
-];
-const SINGLE_WORDS: &'static [&str] = &[
-    "Nice", "Thanks", "Okay", "ok", "Hey", "lol",
-];
-
-fn uppercase_first_letter(s: String) -> String {
-    let mut c = s.chars();
-    match c.next() {
-        None => String::new(),
-        Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-    }
+/// Sets the keys that every window reads, before anything in the state has set them
+fn configure_env(env: &mut druid::Env, self_id: u64) {
+    // Makes it so the entire UI knows which ID the user is.
+    env.set(SELF_USER_ID_KEY, self_id);
+    env.set(SELF_DISPLAY_NAME_KEY, "");
+    env.set(GROUP_USER_ID_KEY, self_id);
+    env.set(SELECTED_MESSAGE_KEY, NO_SELECTED_MESSAGE);
+    env.set(TEXT_SELECTION_KEY, sync::Arc::new(None));
 }
 
-fn generate_random_message(rng: &mut ThreadRng, capital_probability: f64) -> String {
-    let mut msg_len = rng.gen_range(1..13);
-    if rng.gen_bool(0.1) {
-        msg_len *= 2;
-    } else if rng.gen_bool(0.1) {
-        msg_len *= 4;
+fn main() -> Result<(), PlatformError> {
+    let mut args = cli::Args::parse();
+    if let Err(e) = args.validate() {
+        cli::exit_with_error(&e);
     }
-
-    let mut message = String::new();
-
-    if msg_len <= 3 {
-        if rng.gen_bool(0.5) {
-            if rng.gen_bool(0.5) {
-                message.push_str("Hi ");
-            } else {
-                message.push_str("Hello ");
-            }
-            if msg_len == 3 {
-                message.push_str(ADJECTIVES[rng.gen_range(0..ADJECTIVES.len())]);
-                message.push(' ');
-            }
-            if msg_len > 1 {
-                message.push_str(NOUNS[rng.gen_range(0..NOUNS.len())]);
-                message.push(' ');
-            }
-            return message;
-        } else if msg_len == 1 {
-            message.push_str(SINGLE_WORDS[rng.gen_range(0..SINGLE_WORDS.len())]);
-            return message;
+    if let Some(sizes) = &args.benchmark {
+        if let Err(e) = benchmark::run(sizes, args.benchmark_window) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return Ok(());
     }
-
-    for _i in 0..msg_len {
-        let word_list = ALL_WORD_LISTS[rng.gen_range(0..ALL_WORD_LISTS.len())];
-        message += word_list[rng.gen_range(0..word_list.len())];
-        message.push(' ');
-    }
-
-    if rng.gen_bool(0.4) {
-        let str = message[0..message.len() - 1].to_string();
-        message = str;
-        message.push('.');
-    }
-
-    if rng.gen_bool(capital_probability) {
-        uppercase_first_letter(message)
-    } else {
-        message
-    }
-}
-
-fn main() -> Result<(), PlatformError> {
+    config::set_paths(args.store.clone(), args.config.clone());
+    audit::init_from_env();
     // create the initial app state
-    let mut initial_state = AppState {
-        text_edit: "".to_string().into(),
-        timeline_data: im::vector![],
-        profile_pics: im::vector![],
-        settings_open: false,
-        layout_settings: LayoutSettings::default(),
-    };
-
-    let mut rng = rand::thread_rng();
+    let (layout_settings, current_theme) = config::load_config();
+    let mut initial_state = AppState::new(layout_settings, current_theme);
+    initial_state.themes = theme::load_themes();
+    initial_state.user_presets = presets::load_user_presets();
+    if let Some(name) = &args.layout {
+        match cli::find_layout(name, &initial_state.preset_registry, &initial_state.user_presets) {
+            Ok(settings) => presets::apply_preset(&mut initial_state, &settings),
+            Err(e) => cli::exit_with_error(&e),
+        }
+    }
 
     // Find required image resolution to not cause blurry profile pics
 
@@ -261,75 +301,45 @@ fn main() -> Result<(), PlatformError> {
         initial_state.profile_pics.push_back(img_data.unwrap());
     }
     // Set self user
-    let mut self_id = get_self_user_from_args();
-    if self_id > 4 {
-        self_id = 0;
-    }
+    let self_id = args.user as u64;
 
-    let mut time = chrono::offset::Local::now().timestamp();
-    time -= 10; // 10 seconds ago
-    let mut offset_amount = 60;
-
-    for _ in 1..20 {
-        let user_id = rng.gen_range(0..5);
-        let group_size = rng.gen_range(1..5);
-        let mut messages = im::vector![];
-        for i in 0..group_size {
-            messages.push_back(Message {
-                message: generate_random_message(&mut rng, if i == 0 {0.95} else {0.7} ),
-                position_in_group: i,
-                timestamp_epoch_seconds: time + i as i64,
-            })
-        }
-        let msg_group = MessageGroup {
-            messages: messages,
-            user_id: user_id,
-            profile_pic: initial_state.profile_pics[user_id as usize].clone(),
-        };
-        initial_state.timeline_data.push_front(msg_group);
-        time -= offset_amount;
-        offset_amount *= 2;
+    let mut replay = None;
+    match (args.backend, &args.transcript) {
+        (cli::Backend::Replay, Some(path)) => {
+            let events = replay::load_transcript(path).unwrap_or_else(|e| cli::exit_with_error(&e));
+            let mut registry = CommandRegistry::with_builtin_commands();
+            slash_commands::replay::register_replay_commands(&mut registry);
+            initial_state.command_registry = sync::Arc::new(registry);
+            replay = Some(replay::Replay::new(events, args.speed, args.paused, chrono::offset::Local::now().timestamp()));
+        },
+        _ => {
+            let demo_options = args.demo_options();
+            println!("Demo data seed: {} (run with --seed {} to see the same messages again)", demo_options.seed, demo_options.seed);
+            demo_data::fill_timeline(&mut initial_state, &demo_options, chrono::offset::Local::now().timestamp());
+        },
     }
-    let user_id = rng.gen_range(0..5);
-    let msg = MessageGroup {
-        messages: im::vector![
-            Message {
-                timestamp_epoch_seconds: time,
-                position_in_group: 0,
-                message: "This\nis\na\nnarrow\nbut\nlong\nmessage.\nHopefully\nthe\nbubble\nstays\nnarrow.".to_string(),
-            },
-        ],
-        user_id: user_id,
-        profile_pic: initial_state.profile_pics[user_id as usize].clone(),
-    };
-    initial_state.timeline_data.push_front(msg);
-    let user_id = rng.gen_range(0..5);
-    let msg = MessageGroup {
-        messages: im::vector![
-                Message {
-                    timestamp_epoch_seconds: time,
-                    position_in_group: 0,
-                    message: "Hi".to_string(),
-                },
-        ],
-        user_id: user_id,
-        profile_pic: initial_state.profile_pics[user_id as usize].clone(),
-    };
-    initial_state.timeline_data.push_front(msg);
 
-    AppLauncher::with_window(
-        get_chat_window_desc()
+    let launcher = AppLauncher::with_window(
+        get_chat_window_desc(args.window_size, replay, args.flood.is_some())
     ).delegate(
         Delegate {
             window_count: 0,
         }
-    )
-    .configure_env(move |env, _| {
-        // Makes it so the entire UI knows which ID the user is.
-        env.set(SELF_USER_ID_KEY, self_id);
-    })
+    );
+    // Kept until the app quits, since dropping it stops the watching
+    let _config_watcher = config_watcher::start_watching(launcher.get_external_handle());
+    if let Some(messages_per_second) = args.flood {
+        flood::start_flood(launcher.get_external_handle(), messages_per_second, args.users);
+    }
+    if args.settings {
+        // Handled by the chat window once it's shown
+        let _ = launcher.get_external_handle().submit_command(chat_window_widget::OPEN_SETTINGS, (), druid::Target::Global);
+    }
+
+    launcher.configure_env(move |env, _| configure_env(env, self_id))
     .launch(
         initial_state
     )?;
+    audit::print_summary();
     Ok(())
 }
\ No newline at end of file
@@ -1,8 +1,20 @@
 use crate::helper::helper_functions::TimestampFormat;
 use crate::widgets::timeline_item_widget::{PictureShape, TailShape, ItemLayoutOption, MetadataLayout};
-use druid::{EventCtx, Widget, WidgetExt};
+use std::sync;
+use druid::{im, Data, EventCtx, ImageBuf, Lens, LensExt, RenderContext, Widget, WidgetExt};
 use druid::widget;
-use crate::helper::layout_settings::{LayoutSettings, PredefinedLayout};
+use crate::helper::color::{self, SimpleColor};
+use crate::helper::layout_settings::{self, LayoutSettings};
+use crate::helper::theme::{self, Theme, ThemeColors};
+use crate::helper::preset_registry::PresetRegistry;
+use crate::helper::presets::{self, UserPreset};
+use crate::widgets::audit_widget::AuditWidget;
+use crate::widgets::color_picker::ColorPicker;
+use crate::widgets::layout_thumbnail::LayoutThumbnail;
+use crate::widgets::responsive_columns::ResponsiveColumns;
+use crate::widgets::search_filter::{SearchFilter, SEARCH_KEY};
+use crate::widgets::settings_history_controller::SettingsHistoryController;
+use crate::widgets::timeline_preview::TimelinePreview;
 use crate::AppState;
 
 
@@ -45,6 +57,11 @@ const DATETIME_OPTIONS: [(&str, TimestampFormat); 4] =
     ("Compact 24hr", TimestampFormat::Compact24),
 ];
 
+const PRESET_BUTTONS_PER_ROW: usize = 3;
+
+const PRESET_FILE_TYPE: druid::FileSpec = druid::FileSpec::new("Layout preset", &["toml"]);
+const THEME_FILE_TYPE: druid::FileSpec = druid::FileSpec::new("Theme", &["toml"]);
+
 const TIME_OPTIONS: [(&str, TimestampFormat); 3] =
 [
     ("12hr", TimestampFormat::TimeOnly12),
@@ -52,206 +69,309 @@ const TIME_OPTIONS: [(&str, TimestampFormat); 3] =
     ("12hr am/pm", TimestampFormat::TimeOnlyAmPm),
 ];
 
-pub(crate) fn build_settings_ui() -> impl Widget<AppState> {
-    widget::Tabs::new()
-        .with_tab("Layouts", build_predefined_styles_settings().lens(AppState::layout_settings))
-        .with_tab("Advanced", build_advanced_settings().lens(AppState::layout_settings))
+/// The settings that the reset buttons go back to
+const PRESET_BASELINE_KEY: druid::env::Key<sync::Arc<LayoutSettings>> = druid::env::Key::new("polysoft.druid-demo.preset_baseline");
+/// The narrowest a column of settings gets before the settings are put in fewer columns
+const SETTINGS_COLUMN_WIDTH: f64 = 360.0;
+const COLOR_COLUMN_WIDTH: f64 = 190.0;
+/// The width of the strip that marks a setting that differs from the preset
+const CHANGED_MARK_WIDTH: f64 = 3.0;
+
+pub(crate) fn build_settings_ui(registry: &PresetRegistry, profile_pics: &im::Vector<ImageBuf>) -> impl Widget<AppState> {
+    // Each tab scrolls, so the window can be made smaller than the settings
+    let scrolled = |tab| widget::Scroll::new(tab).vertical();
+    let tabs = widget::Tabs::new()
+        .with_tab("Layouts", scrolled(build_layouts_tab(registry, profile_pics).boxed()))
+        .with_tab("Advanced", scrolled(build_advanced_settings().lens(edited_layout_settings()).boxed()))
+        .with_tab("Colors", scrolled(build_color_settings().boxed()));
+    let settings = widget::Flex::column()
+        .with_child(build_search_box())
+        .with_flex_child(tabs, 1.0);
+    let settings_ui = widget::EnvScope::new(
+        |env: &mut druid::env::Env, data: &AppState| {
+            data.theme.colors.set_env(env);
+            env.set(PRESET_BASELINE_KEY, data.preset_baseline.clone());
+            env.set(SEARCH_KEY, data.settings_search.as_str());
+        },
+        widget::Split::columns(settings, build_preview(profile_pics))
+            .split_point(0.75)
+            .draggable(true)
+    )
+    .controller(SettingsHistoryController::new());
+    // Around the history controller too, so its grouping timer is counted
+    AuditWidget::new(settings_ui)
+}
+
+/// Filters the settings in every tab by their labels and keywords
+fn build_search_box() -> impl Widget<AppState> {
+    widget::Flex::row()
+        .with_flex_child(
+            widget::TextBox::new()
+                .with_placeholder("Search settings, like \"tail\", \"irc\" or \"font\"")
+                .lens(AppState::settings_search)
+                .expand_width()
+        , 1.0)
+        .with_default_spacer()
+        .with_child(
+            widget::Button::new("Clear")
+                .on_click( |_ctx: &mut EventCtx, data: &mut AppState, _ | {
+                    data.settings_search = String::new().into();
+                })
+                .disabled_if(|data: &AppState, _| data.settings_search.is_empty())
+        )
+        .padding(8.0)
+}
+
+/// Shows the changes on sample messages, so the chat window doesn't have to be put next to the settings
+fn build_preview(profile_pics: &im::Vector<ImageBuf>) -> impl Widget<AppState> {
+    widget::Flex::column()
+        .with_child(
+            widget::Label::new("Preview")
+                .with_text_size(20.0).padding(8.0).align_left()
+        )
+        .with_child(
+            widget::Flex::row()
+                .with_child(widget::Label::new("Width"))
+                .with_default_spacer()
+                .with_flex_child(
+                    widget::Slider::new().with_range(150.0, 1000.0).with_step(5.0)
+                    .lens(AppState::preview_width)
+                , 0.9)
+                .with_flex_child(widget::Label::new(
+                    |data: &AppState, _: &_| {format!("{:.0}", data.preview_width)}),
+                    0.4)
+                .padding(8.0)
+        )
+        .with_flex_child(
+            // Scrolls sideways when the preview is wider than the pane
+            widget::Scroll::new(TimelinePreview::new(profile_pics))
+                .horizontal()
+                .padding(8.0),
+            1.0
+        )
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+}
+
+fn build_layouts_tab(registry: &PresetRegistry, profile_pics: &im::Vector<ImageBuf>) -> impl Widget<AppState> {
+    // The thumbnails show the first user talking to the second
+    let picture = |index: usize| profile_pics.get(index).cloned().unwrap_or_else(ImageBuf::empty);
+    let pictures = [picture(1), picture(0)];
+    widget::Flex::column()
+        .with_child(build_predefined_styles_settings(registry, &pictures))
+        .with_child(SearchFilter::new(build_user_presets_settings(), "your presets user save import export rename delete"))
 }
 
-fn build_predefined_styles_settings() -> impl Widget<LayoutSettings> {
+fn build_predefined_styles_settings(registry: &PresetRegistry, pictures: &[ImageBuf; 2]) -> impl Widget<AppState> {
     widget::Flex::column()
         .with_child(
             widget::Label::new("Predefined Layouts")
                 .with_text_size(20.0).padding(8.0).align_left()
         )
         .with_default_spacer()
+        .with_child(
+            ResponsiveColumns::new(SETTINGS_COLUMN_WIDTH)
+                .with_child(build_preset_section("Bubble Layouts", registry, pictures, true))
+                .with_child(build_preset_section("Other Layouts", registry, pictures, false))
+                .with_child(build_font_settings().lens(edited_layout_settings()))
+                .padding(8.0)
+        )
+        .with_child(widget::Label::new("The standard IRC layout changes when width > 400").padding(8.0))
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+}
+
+/// The buttons for one kind of predefined layout. They can be searched for by name.
+fn build_preset_section(title: &'static str, registry: &PresetRegistry, pictures: &[ImageBuf; 2], bubble: bool) -> impl Widget<AppState> {
+    let names: Vec<&str> = registry.iter()
+        .filter(|preset| preset.settings.is_bubble() == bubble)
+        .map(|preset| preset.name.as_str())
+        .collect();
+    SettingsSection::new(title)
+        .with_row(&format!("preset {}", names.join(" ")), build_preset_buttons(registry, pictures, bubble))
+        .build()
+}
+
+fn build_font_settings() -> impl Widget<LayoutSettings> {
+    SettingsSection::new("Fonts")
+        .with_row("Content Font Size text", with_reset(widget::Flex::row()
+            .with_flex_child(widget::Label::new("Content Font Size:").align_right()
+            , 0.7)
+            .with_default_spacer()
+            .with_flex_child(
+                size_stepper("content_font_size")
+                .lens(LayoutSettings::content_font_size)
+            , 0.9)
+            .with_flex_child(
+                widget::Label::new(
+                    |data: &LayoutSettings, _: &_| {format!("{:.1}", data.content_font_size)})
+            , 0.4)
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::content_font_size
+        ))
+        .with_row("Sender Font Size", with_reset(widget::Flex::row()
+            .with_flex_child(widget::Label::new("Sender Font Size:").align_right()
+            , 0.7)
+            .with_default_spacer()
+            .with_flex_child(
+                size_stepper("sender_font_size")
+                .lens(LayoutSettings::sender_font_size)
+            , 0.9)
+            .with_flex_child(
+                widget::Label::new(
+                    |data: &LayoutSettings, _: &_| {format!("{:.1}", data.sender_font_size)})
+            , 0.4)
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::sender_font_size
+        ))
+        .with_row("Datetime Font Size", with_reset(widget::Flex::row()
+            .with_flex_child(widget::Label::new("Datetime Font Size:").align_right()
+            , 0.7)
+            .with_default_spacer()
+            .with_flex_child(
+                size_stepper("datetime_font_size")
+                .lens(LayoutSettings::datetime_font_size)
+            , 0.9)
+            .with_flex_child(
+                widget::Label::new(
+                    |data: &LayoutSettings, _: &_| {format!("{:.1}", data.datetime_font_size)})
+            , 0.4)
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::datetime_font_size
+        ))
+        .build()
+}
+
+/// A grid with a button for each predefined layout, either the bubble layouts or the others.
+/// Each button has a thumbnail of the layout above its name.
+fn build_preset_buttons(registry: &PresetRegistry, pictures: &[ImageBuf; 2], bubble: bool) -> impl Widget<AppState> {
+    let mut column = widget::Flex::column()
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start);
+    let presets: Vec<_> = registry.iter().filter(|preset| preset.settings.is_bubble() == bubble).collect();
+    for row_presets in presets.chunks(PRESET_BUTTONS_PER_ROW) {
+        let mut row = widget::Flex::row()
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start);
+        for preset in row_presets {
+            let settings = preset.settings.clone();
+            row.add_child(
+                widget::Flex::column()
+                    .with_child(LayoutThumbnail::new(preset.settings.clone(), pictures.clone()))
+                    .with_spacer(4.0)
+                    .with_child(widget::Label::new(preset.name.clone()))
+                    .padding(4.0)
+                    .background(widget::Painter::new(|ctx, _: &AppState, env| {
+                        if ctx.is_hot() {
+                            let rect = ctx.size().to_rounded_rect(4.0);
+                            ctx.fill(rect, &env.get(druid::theme::BUTTON_LIGHT));
+                        }
+                    }))
+                    .on_click(move |_, data: &mut AppState, _ | {
+                        presets::apply_preset(data, &settings);
+                    })
+            );
+        }
+        column.add_child(row);
+    }
+    column
+}
+
+fn build_user_presets_settings() -> impl Widget<AppState> {
+    widget::Flex::column()
+        .with_child(
+            widget::Label::new("Your Presets")
+                .with_text_size(20.0).padding(8.0).align_left()
+        )
         .with_child(
             widget::Flex::row()
                 .with_flex_child(
-                    widget::Label::new("Layout").align_right()
-                , 0.7)
+                    widget::TextBox::new()
+                        .with_placeholder("Preset name")
+                        .lens(AppState::preset_name_edit)
+                        .expand_width()
+                , 1.0)
                 .with_default_spacer()
-                .with_flex_child(
-                    widget::Flex::column()
-                        .with_child(
-                            widget::Button::new("Modern Hangouts")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::ModernHangouts, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Modern Bubble")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::ModernBubble, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Large Bubble")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::LargeBubble, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Old Fashioned Hangouts")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::OldHangouts, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Telegram")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Telegram, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("iMessage")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::IMessage, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Old Kik")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::OldKik, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Tear Drop")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::TearDrop, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("No Tail")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Tailless, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Relaxed")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Relaxed, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Other Bubble")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::OtherBubble, data);
-                                })
-                        )
-                        .cross_axis_alignment(widget::CrossAxisAlignment::Fill)
-                    , 1.3)
-                .with_flex_child(
-                    widget::Flex::column()
-                        .with_child(
-                            widget::Button::new("Discord")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Discord, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Compact Discord")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::CompactDiscord, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Slack")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Slack, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Compact")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::Compact, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Modern IRC")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::IRC, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Large IRC")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::LargeIRC, data);
-                                })
-                        )
-                        .with_child(
-                            widget::Button::new("Spaced Modern IRC")
-                                .on_click( |ctx: &mut EventCtx, data: &mut LayoutSettings, _ | {
-                                    predefined_layout_selected(ctx, PredefinedLayout::SpacedIRC, data);
-                                })
-                        )
-                        .cross_axis_alignment(widget::CrossAxisAlignment::Fill)
-                    , 1.3)
-                .with_flex_child(
-                    widget::Flex::column()
-                        .with_child(widget::Flex::row()
-                        .with_flex_child(widget::Label::new("Content Font Size:").align_right()
-                        , 0.7)
-                        .with_default_spacer()
-                        .with_flex_child(
-                            widget::Stepper::new()
-                            .on_click( |ctx: &mut EventCtx, _, _ | {
-                                ui_changed_callback(ctx);
-                            })
-                            .lens(LayoutSettings::content_font_size)
-                        , 0.9)
-                        .with_flex_child(
-                            widget::Label::new(
-                                |data: &LayoutSettings, _: &_| {format!("{:.1}", data.content_font_size)})
-                        , 0.4)
-                        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-                        )
-                        .with_spacer(10.0)
-                        .with_child(widget::Flex::row()
-                            .with_flex_child(widget::Label::new("Sender Font Size:").align_right()
-                            , 0.7)
-                            .with_default_spacer()
-                            .with_flex_child(
-                                widget::Stepper::new()
-                                .on_click( |ctx: &mut EventCtx, _, _ | {
-                                    ui_changed_callback(ctx);
-                                })
-                                .lens(LayoutSettings::sender_font_size)
-                            , 0.9)
-                            .with_flex_child(
-                                widget::Label::new(
-                                    |data: &LayoutSettings, _: &_| {format!("{:.1}", data.sender_font_size)})
-                            , 0.4)
-                            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-                        )
-                        .with_spacer(10.0)
-                        .with_child(widget::Flex::row()
-                            .with_flex_child(widget::Label::new("Datetime Font Size:").align_right()
-                            , 0.7)
-                            .with_default_spacer()
-                            .with_flex_child(
-                                widget::Stepper::new()
-                                .on_click( |ctx: &mut EventCtx, _, _ | {
-                                    ui_changed_callback(ctx);
-                                })
-                                .lens(LayoutSettings::datetime_font_size)
-                            , 0.9)
-                            .with_flex_child(
-                                widget::Label::new(
-                                    |data: &LayoutSettings, _: &_| {format!("{:.1}", data.datetime_font_size)})
-                            , 0.4)
-                            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-                        )
-                    , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+                .with_child(
+                    widget::Button::new("Save Current Layout")
+                        .on_click( |ctx: &mut EventCtx, _, _ | {
+                            ctx.submit_command(presets::SAVE_USER_PRESET);
+                        })
+                )
+                .with_default_spacer()
+                .with_child(
+                    widget::Button::new("Import...")
+                        .on_click( |ctx: &mut EventCtx, _, _ | {
+                            let options = druid::FileDialogOptions::new()
+                                .allowed_types(vec![PRESET_FILE_TYPE])
+                                .accept_command(presets::IMPORT_PRESET_FROM);
+                            ctx.submit_command(druid::commands::SHOW_OPEN_PANEL.with(options));
+                        })
+                )
+                .padding((8.0, 0.0))
+        )
+        .with_default_spacer()
+        .with_child(
+            widget::Label::new(|data: &AppState, _: &_| data.preset_status.clone())
+                .padding((8.0, 0.0)).align_left()
+        )
+        .with_default_spacer()
+        .with_child(
+            widget::Either::new(
+                |data: &AppState, _| data.user_presets.is_empty(),
+                widget::Label::new(|_: &AppState, _: &_| {
+                    match presets::get_presets_dir() {
+                        Some(dir) => format!("No presets yet. Save the current layout, or add preset files to {}", dir.display()),
+                        None => "No presets yet.".to_string(),
+                    }
+                }).with_line_break_mode(widget::LineBreaking::WordWrap),
+                widget::Scroll::new(
+                    widget::List::new(build_user_preset_row).lens(AppState::user_presets)
+                ).vertical().fix_height(150.0)
+            ).padding(8.0)
         )
-        .with_flex_child(widget::Label::new("The standard IRC layout changes when width > 400"), 1.0)
+        .cross_axis_alignment(widget::CrossAxisAlignment::Fill)
+}
 
+/// A row with the name of a preset and the buttons for it.
+/// Rename uses the name typed in the preset name box.
+fn build_user_preset_row() -> impl Widget<UserPreset> {
+    widget::Flex::row()
+        .with_flex_child(
+            widget::Label::new(|preset: &UserPreset, _: &_| preset.name.clone()).align_left()
+        , 1.0)
+        .with_child(
+            widget::Button::new("Apply")
+                .on_click( |ctx: &mut EventCtx, preset: &mut UserPreset, _ | {
+                    ctx.submit_command(presets::APPLY_USER_PRESET.with(preset.name.clone()));
+                })
+        )
+        .with_child(
+            widget::Button::new("Rename")
+                .on_click( |ctx: &mut EventCtx, preset: &mut UserPreset, _ | {
+                    ctx.submit_command(presets::RENAME_USER_PRESET.with(preset.name.clone()));
+                })
+        )
+        .with_child(
+            widget::Button::new("Export...")
+                .on_click( |ctx: &mut EventCtx, preset: &mut UserPreset, _ | {
+                    ctx.submit_command(presets::EXPORT_USER_PRESET.with(preset.name.clone()));
+                    let options = druid::FileDialogOptions::new()
+                        .allowed_types(vec![PRESET_FILE_TYPE])
+                        .default_name(format!("{}.toml", preset.name))
+                        .accept_command(presets::EXPORT_PRESET_TO)
+                        .cancel_command(presets::EXPORT_PRESET_CANCELLED);
+                    ctx.submit_command(druid::commands::SHOW_SAVE_PANEL.with(options));
+                })
+        )
+        .with_child(
+            widget::Button::new("Delete")
+                .on_click( |ctx: &mut EventCtx, preset: &mut UserPreset, _ | {
+                    ctx.submit_command(presets::DELETE_USER_PRESET.with(preset.name.clone()));
+                })
+        )
+        .padding((0.0, 2.0))
 }
 
 fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
-    widget::Flex::column()
-        .with_child(
+    SettingsSection::new("Layout")
+        .with_row("Item Layout", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Item Layout:").align_right()
@@ -259,15 +379,12 @@ fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(LAYOUT_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::item_layout)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::item_layout
+        ))
+        .with_row("Metadata Layout", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Metadata Layout:").align_right()
@@ -275,15 +392,12 @@ fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(METADATA_LAYOUT_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::metadata_layout)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::metadata_layout
+        ))
+        .with_row("Profile Pic Shape picture circle hexagon", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Profile Pic Shape:").align_right()
@@ -291,33 +405,27 @@ fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(IMG_SHAPE_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::picture_shape)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::picture_shape
+        ))
+        .with_row("Profile Pic Size picture", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Profile Pic Size:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(10.0, 100.0).with_step(1.0)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("picture_size", 1.0)
                     .lens(LayoutSettings::picture_size)
                 , 1.0)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:1}", data.picture_size)}),
                     0.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::picture_size
+        ))
+        .with_row("Time Format datetime timestamp date", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Time Format:").align_right()
@@ -325,15 +433,12 @@ fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(DATETIME_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::datetime_format)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::datetime_format
+        ))
+        .with_row("Side Time Format timestamp", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Side Time Format:").align_right()
@@ -341,18 +446,17 @@ fn build_advanced_layout_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(TIME_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::side_time_format)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::side_time_format
+        ))
+        .build()
 }
 
 fn build_advanced_bubble_settings() -> impl Widget<LayoutSettings> {
-    widget::Flex::column()
-        .with_child(
+    SettingsSection::new("Bubble-specific")
+        .with_row("Bubble Tail Shape", with_reset(
             widget::Flex::row()
                 .with_flex_child(
                     widget::Label::new("Bubble Tail Shape:").align_right()
@@ -360,97 +464,79 @@ fn build_advanced_bubble_settings() -> impl Widget<LayoutSettings> {
                 .with_default_spacer()
                 .with_flex_child(
                     widget::RadioGroup::column(TAIL_SHAPE_OPTIONS)
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
-                        })
                         .lens(LayoutSettings::chat_bubble_tail_shape)
                 , 1.3)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::chat_bubble_tail_shape
+        ))
+        .with_row("Bubble Radius corner rounded", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Bubble Radius:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 12.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("chat_bubble_radius", 0.5)
                     .lens(LayoutSettings::chat_bubble_radius)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.chat_bubble_radius)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::chat_bubble_radius
+        ))
+        .with_row("Tail Size", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Tail Size:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(2.0, 12.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("chat_bubble_tail_size", 0.5)
                     .lens(LayoutSettings::chat_bubble_tail_size)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.chat_bubble_tail_size)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::chat_bubble_tail_size
+        ))
+        .with_row("Flip left bubble flipped", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Flip left bubble:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
                 widget::Switch::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
                 .lens(LayoutSettings::left_bubble_flipped)
             , 1.3)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::left_bubble_flipped
+        ))
+        .with_row("Flip right bubble flipped", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Flip right bubble:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
                 widget::Switch::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
                 .lens(LayoutSettings::right_bubble_flipped)
             , 1.3)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::right_bubble_flipped
+        ))
+        .with_row("Bubble Padding", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Bubble Padding:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 15.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("bubble_padding", 0.5)
                     .lens(LayoutSettings::bubble_padding)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.bubble_padding)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(15.0)
-        .with_child(widget::Flex::row()
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::bubble_padding
+        ))
+        .with_row("Show Self Profile Pic picture", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Show Self Profile Pic:")
                 .with_line_break_mode(widget::LineBreaking::WordWrap)
                 .align_right()
@@ -458,13 +544,12 @@ fn build_advanced_bubble_settings() -> impl Widget<LayoutSettings> {
             .with_default_spacer()
             .with_flex_child(
                 widget::Switch::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
                 .lens(LayoutSettings::show_self_pic)
             , 1.3)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::show_self_pic
+        ))
+        .build()
         .disabled_if(|data, _| data.item_layout != ItemLayoutOption::BubbleExternBottomMeta
             && data.item_layout != ItemLayoutOption::BubbleInternalBottomMeta
             && data.item_layout != ItemLayoutOption::BubbleInternalTopMeta
@@ -473,285 +558,435 @@ fn build_advanced_bubble_settings() -> impl Widget<LayoutSettings> {
 }
 
 fn build_advanced_irc_settings() -> impl Widget<LayoutSettings> {
-    widget::Flex::column()
-        .with_child(
+    SettingsSection::new("IRC-specific")
+        .with_row("Stack width", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Stack width:").align_right()
                     , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(100.0, 1000.0).with_step(5.0)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("irc_stack_width", 5.0)
                     .lens(LayoutSettings::irc_stack_width)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.irc_stack_width)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::irc_stack_width
+        ))
+        .with_row("Header Width", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Header Width:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(20.0, 250.0).with_step(1.0)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("irc_header_width", 1.0)
                     .lens(LayoutSettings::irc_header_width)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.irc_header_width)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::irc_header_width
+        ))
+        .build()
         .disabled_if(|data, _| data.item_layout != ItemLayoutOption::IRCStyle)
 }
 
 fn build_advanced_sizing_settings() -> impl Widget<LayoutSettings> {
-    widget::Flex::column()
-        .with_child(
+    SettingsSection::new("Sizing")
+        .with_row("Profile Pic Spacing picture", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Profile Pic Spacing:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(-15.0, 20.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("chat_picture_spacing", 0.5)
                     .lens(LayoutSettings::chat_picture_spacing)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.chat_picture_spacing)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::chat_picture_spacing
+        ))
+        .with_row("Content Spacing metadata", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Content Spacing:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 10.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("metadata_content_spacing", 0.5)
                     .lens(LayoutSettings::metadata_content_spacing)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.metadata_content_spacing)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::metadata_content_spacing
+        ))
+        .with_row("Msg Spacing message", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Msg Spacing:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 28.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("single_message_spacing", 0.5)
                     .lens(LayoutSettings::single_message_spacing)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.single_message_spacing)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::single_message_spacing
+        ))
+        .with_row("Group Spacing", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Group Spacing:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 28.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("group_spacing", 0.5)
                     .lens(LayoutSettings::group_spacing)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.group_spacing)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::group_spacing
+        ))
+        .with_row("Left Spacing", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Left Spacing:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 10.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("left_spacing", 0.5)
                     .lens(LayoutSettings::left_spacing)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.left_spacing)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::left_spacing
+        ))
+        .with_row("Left Meta Offset metadata", with_reset(
             widget::Flex::row()
                 .with_flex_child(widget::Label::new("Left Meta Offset:").align_right()
                 , 0.7)
                 .with_default_spacer()
                 .with_flex_child(
-                    widget::Slider::new().with_range(0.0, 30.0).with_step(0.5)
-                    .on_click( |ctx: &mut EventCtx, _, _ | {
-                        ui_changed_callback(ctx);
-                    })
+                    size_slider("left_meta_offset", 0.5)
                     .lens(LayoutSettings::left_meta_offset)
                 , 0.9)
                 .with_flex_child(widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.left_meta_offset)}),
                     0.4)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::left_meta_offset
+        ))
+        .with_row("Show Left Line", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Show Left Line:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
                 widget::Switch::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
                 .lens(LayoutSettings::show_left_line)
             , 1.3)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::show_left_line
+        ))
+        .with_row("Align to Pic picture", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Align to Pic:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
                 widget::Switch::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
                 .lens(LayoutSettings::align_to_picture)
             , 1.3)
             .cross_axis_alignment(widget::CrossAxisAlignment::Start)
             .disabled_if(|data, _| data.item_layout == ItemLayoutOption::BubbleExternBottomMeta
                 || data.item_layout == ItemLayoutOption::BubbleInternalBottomMeta
                 || data.item_layout == ItemLayoutOption::BubbleInternalTopMeta
-            )
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+            ),
+            LayoutSettings::align_to_picture
+        ))
+        .with_row("Content Font Size text", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Content Font Size:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
-                widget::Stepper::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
+                size_stepper("content_font_size")
                 .lens(LayoutSettings::content_font_size)
             , 0.9)
             .with_flex_child(
                 widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.content_font_size)})
             , 0.4)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::content_font_size
+        ))
+        .with_row("Sender Font Size", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Sender Font Size:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
-                widget::Stepper::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
+                size_stepper("sender_font_size")
                 .lens(LayoutSettings::sender_font_size)
             , 0.9)
             .with_flex_child(
                 widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.sender_font_size)})
             , 0.4)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-        )
-        .with_spacer(10.0)
-        .with_child(widget::Flex::row()
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::sender_font_size
+        ))
+        .with_row("Datetime Font Size", with_reset(widget::Flex::row()
             .with_flex_child(widget::Label::new("Datetime Font Size:").align_right()
             , 0.7)
             .with_default_spacer()
             .with_flex_child(
-                widget::Stepper::new()
-                .on_click( |ctx: &mut EventCtx, _, _ | {
-                    ui_changed_callback(ctx);
-                })
+                size_stepper("datetime_font_size")
                 .lens(LayoutSettings::datetime_font_size)
             , 0.9)
             .with_flex_child(
                 widget::Label::new(
                     |data: &LayoutSettings, _: &_| {format!("{:.1}", data.datetime_font_size)})
             , 0.4)
-            .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+            .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            LayoutSettings::datetime_font_size
+        ))
+        .build()
+}
+
+fn build_advanced_settings() -> impl Widget<LayoutSettings> {
+    widget::Flex::column()
+        .with_child(
+            widget::Label::new("Layout Settings")
+                .with_text_size(20.0).padding(8.0).align_left()
+        )
+        .with_default_spacer()
+        .with_child(
+            ResponsiveColumns::new(SETTINGS_COLUMN_WIDTH)
+                .with_child(build_advanced_layout_settings())
+                .with_child(build_advanced_bubble_settings())
+                .with_child(build_advanced_irc_settings())
+                .with_child(build_advanced_sizing_settings())
+                .padding(8.0)
         )
 }
 
+fn build_color_settings() -> impl Widget<AppState> {
+    let colors = || AppState::theme.then(Theme::colors);
+    widget::Flex::column()
+        .with_child(
+            widget::Label::new("Colors")
+                .with_text_size(20.0).padding(8.0).align_left()
+        )
+        .with_child(SearchFilter::new(build_theme_settings(), "theme dark light high contrast import export"))
+        .with_default_spacer()
+        .with_child(
+            ResponsiveColumns::new(COLOR_COLUMN_WIDTH)
+                .with_child(SearchFilter::new(build_color_setting("Text", ThemeColors::text).lens(colors()), "Text color"))
+                .with_child(SearchFilter::new(build_color_setting("Background", ThemeColors::window_background).lens(colors()), "Background color"))
+                .with_child(SearchFilter::new(build_color_setting("Sender", ThemeColors::sender).lens(colors()), "Sender color"))
+                .with_child(SearchFilter::new(build_color_setting("Time", ThemeColors::datetime).lens(colors()), "Time datetime color"))
+                .with_child(SearchFilter::new(build_color_setting("Your Sender", ThemeColors::self_sender).lens(colors()), "Your Sender self color"))
+                .with_child(SearchFilter::new(build_color_setting("Your Time", ThemeColors::self_datetime).lens(colors()), "Your Time self datetime color"))
+                .with_child(SearchFilter::new(
+                    widget::Flex::column()
+                        .with_child(build_color_setting("Other Bubbles", ThemeColors::other_bubble).lens(colors()))
+                        .with_default_spacer()
+                        .with_child(build_contrast_warning(false))
+                        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+                , "Other Bubbles bubble contrast color"))
+                .with_child(SearchFilter::new(
+                    widget::Flex::column()
+                        .with_child(build_color_setting("Your Bubbles", ThemeColors::self_bubble).lens(colors()))
+                        .with_default_spacer()
+                        .with_child(build_contrast_warning(true))
+                        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+                , "Your Bubbles self bubble contrast color"))
+        )
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+        .padding(8.0)
+}
 
-fn build_advanced_settings() -> impl Widget<LayoutSettings> {
+/// A button for each theme, and a way to export the current colors as a theme file
+fn build_theme_settings() -> impl Widget<AppState> {
     widget::Flex::column()
         .with_child(
             widget::Flex::row()
+                .with_child(widget::Label::new("Theme:"))
+                .with_default_spacer()
                 .with_child(
-                    widget::Label::new("Layout Settings")
-                        .with_text_size(20.0).padding(8.0).align_left()
+                    widget::List::new(|| {
+                        widget::Button::new(|theme: &Theme, _: &_| theme.name.clone())
+                            .on_click( |ctx: &mut EventCtx, theme: &mut Theme, _ | {
+                                ctx.submit_command(theme::APPLY_THEME.with(theme.name.clone()));
+                            })
+                    })
+                    .horizontal()
+                    .with_spacing(4.0)
+                    .lens(AppState::themes)
                 )
+                .with_default_spacer()
                 .with_child(
-                    widget::Button::new("Refresh Window")
-                        .on_click( |ctx: &mut EventCtx, _, _ | {
-                            ui_changed_callback(ctx);
+                    widget::Button::new("Export Theme...")
+                        .on_click( |ctx: &mut EventCtx, data: &mut AppState, _ | {
+                            let options = druid::FileDialogOptions::new()
+                                .allowed_types(vec![THEME_FILE_TYPE])
+                                .default_name(format!("{}.toml", data.theme.name))
+                                .accept_command(theme::EXPORT_THEME_TO);
+                            ctx.submit_command(druid::commands::SHOW_SAVE_PANEL.with(options));
                         })
                 )
         )
         .with_default_spacer()
         .with_child(
-            widget::Flex::row()
-                .with_flex_child(build_advanced_layout_settings(), 1.5)
-                .with_flex_child(
-                    widget::Flex::column()
-                        .with_child(widget::Label::new("Bubble-specific"))
-                        .with_spacer(20.0)
-                        .with_child(build_advanced_bubble_settings())
-                        .with_spacer(30.0)
-                        .with_child(widget::Label::new("IRC-specific"))
-                        .with_spacer(20.0)
-                        .with_child(build_advanced_irc_settings())
-                        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
-                , 1.0)
-                .with_flex_child(build_advanced_sizing_settings(), 1.0)
-                .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+            widget::Label::new(|data: &AppState, _: &_| {
+                if !data.theme_status.is_empty() {
+                    data.theme_status.clone()
+                } else {
+                    match theme::get_themes_dir() {
+                        Some(dir) => format!("Using \"{}\". More themes can be added to {}", data.theme.name, dir.display()),
+                        None => format!("Using \"{}\"", data.theme.name),
+                    }
+                }
+            })
+        )
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+}
+
+fn build_color_setting(
+    name: &'static str, lens: impl druid::Lens<ThemeColors, SimpleColor> + 'static
+) -> impl Widget<ThemeColors> {
+    widget::Flex::column()
+        .with_child(widget::Label::new(name))
+        .with_default_spacer()
+        .with_child(ColorPicker::new().lens(lens))
+        .cross_axis_alignment(widget::CrossAxisAlignment::Start)
+}
+
+/// Shown under a bubble color when text on the bubble would be hard to read
+fn build_contrast_warning(is_self_user: bool) -> impl Widget<AppState> {
+    widget::Label::new(move |data: &AppState, _env: &druid::Env| {
+        let contrast = get_lowest_bubble_contrast(&data.layout_settings, &data.theme.colors, is_self_user);
+        if contrast < color::WCAG_AA_CONTRAST {
+            format!("Text contrast is only {:.1}:1\nWCAG AA needs {:.1}:1", contrast, color::WCAG_AA_CONTRAST)
+        } else {
+            String::new()
+        }
+    })
+    .with_text_color(theme::WARNING_COLOR_KEY)
+}
+
+/// Gets the contrast of the least readable text drawn on the bubble.
+/// See-through colors are blended with what is behind them first.
+fn get_lowest_bubble_contrast(settings: &LayoutSettings, colors: &ThemeColors, is_self_user: bool) -> f64 {
+    let background = colors.window_background.to_druid_color();
+    let bubble = color::blend(&colors.get_bubble_color(is_self_user), &background);
+    let mut text_colors = vec![colors.text.to_druid_color()];
+    // The sender and time are only on the bubble in these layouts
+    if matches!(settings.item_layout, ItemLayoutOption::BubbleInternalBottomMeta | ItemLayoutOption::BubbleInternalTopMeta) {
+        text_colors.push(colors.get_sender_color(is_self_user));
+        text_colors.push(colors.get_datetime_color(is_self_user));
+    }
+    text_colors.iter()
+        .map(|text_color| color::contrast_ratio(&color::blend(text_color, &bubble), &bubble))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// A titled group of settings rows, which is one of the blocks laid out in columns.
+/// A row is only shown when it matches the search, and the section is hidden when none of its rows do.
+struct SettingsSection<T> {
+    title: &'static str,
+    column: widget::Flex<T>,
+    /// The keywords of all the rows, so the section is found by any of them
+    keywords: String,
+}
+
+impl<T: Data> SettingsSection<T> {
+    fn new(title: &'static str) -> Self {
+        SettingsSection {
+            title,
+            column: widget::Flex::column()
+                .with_child(widget::Label::new(title).with_text_size(16.0))
+                .with_spacer(12.0)
+                .cross_axis_alignment(widget::CrossAxisAlignment::Start),
+            keywords: title.to_string(),
+        }
+    }
+
+    /// The row is found by its keywords or by the title of the section
+    fn with_row(mut self, keywords: &str, row: impl Widget<T> + 'static) -> Self {
+        self.keywords.push(' ');
+        self.keywords.push_str(keywords);
+        self.column.add_child(SearchFilter::new(
+            row.padding(druid::Insets::new(0.0, 0.0, 0.0, 10.0)),
+            &format!("{} {}", self.title, keywords)
+        ));
+        self
+    }
+
+    fn build(self) -> SearchFilter<T> {
+        SearchFilter::new(self.column, &self.keywords)
+    }
+}
+
+/// Adds a button to a settings row that puts the field back to the value of the last applied preset.
+/// While the field differs from it, the row is marked at its left side.
+fn with_reset<V: Data>(
+    row: impl Widget<LayoutSettings> + 'static,
+    field: impl Lens<LayoutSettings, V> + Clone + 'static,
+) -> impl Widget<LayoutSettings> {
+    let differs = {
+        let field = field.clone();
+        move |data: &LayoutSettings, env: &druid::Env| {
+            let baseline = env.get(PRESET_BASELINE_KEY);
+            !field.with(data, |value| field.with(&*baseline, |default| value.same(default)))
+        }
+    };
+    let mark_differs = differs.clone();
+    widget::Flex::row()
+        .with_flex_child(row, 1.0)
+        .with_default_spacer()
+        .with_child(
+            widget::Button::new("Reset")
+                .on_click(move |_: &mut EventCtx, data: &mut LayoutSettings, env| {
+                    let baseline = env.get(PRESET_BASELINE_KEY);
+                    let value = field.with(&*baseline, |default| default.clone());
+                    field.with_mut(data, |current| *current = value);
+                })
+                .disabled_if(move |data, env| !differs(data, env))
         )
-        
+        .padding(druid::Insets::new(CHANGED_MARK_WIDTH + 4.0, 0.0, 0.0, 0.0))
+        .background(widget::Painter::new(move |ctx, data: &LayoutSettings, env| {
+            if mark_differs(data, env) {
+                let mark = druid::Rect::new(0.0, 0.0, CHANGED_MARK_WIDTH, ctx.size().height);
+                ctx.fill(mark, &env.get(druid::theme::PRIMARY_LIGHT));
+            }
+        }))
+}
+
+/// A slider over the range the setting is allowed to have when it is loaded
+fn size_slider(name: &str, step: f64) -> widget::Slider {
+    let (min, max) = get_size_range(name);
+    widget::Slider::new().with_range(min, max).with_step(step)
+}
+
+/// A stepper that stops at the ends of the range the setting is allowed to have when it is loaded
+fn size_stepper(name: &str) -> widget::Stepper {
+    let (min, max) = get_size_range(name);
+    widget::Stepper::new().with_range(min, max)
 }
 
-fn ui_changed_callback(ctx: &mut EventCtx) {
-    // Signal to all timeline widgets to refresh
-    ctx.submit_command(crate::REFRESH_UI_SELECTOR.to(druid::Target::Global));
+/// Panics for a name that isn't in the ranges, since the controls are only built for sizes
+fn get_size_range(name: &str) -> (f64, f64) {
+    layout_settings::get_range(name).unwrap_or_else(|| panic!("{} is not a size setting", name))
 }
 
-fn predefined_layout_selected(ctx: &mut EventCtx, layout: PredefinedLayout, settings: &mut LayoutSettings) {
-    settings.set_from_predefined_layout(layout);
-    ui_changed_callback(ctx);
-}
\ No newline at end of file
+/// Edits a copy of the shared layout settings, which replaces them when something was changed
+fn edited_layout_settings() -> impl Lens<AppState, LayoutSettings> {
+    AppState::layout_settings.then(LensExt::<LayoutSettings, LayoutSettings>::in_arc(druid::lens::Identity))
+}
@@ -0,0 +1,124 @@
+use druid::{Env, EventCtx};
+use crate::{AppState, Message, MessageKind};
+use crate::helper::{presets, timeline};
+use super::{CommandRegistry, SlashCommand};
+
+pub fn register_builtin_commands(registry: &mut CommandRegistry) {
+    registry.register(Box::new(MeCommand));
+    registry.register(Box::new(NickCommand));
+    registry.register(Box::new(LayoutCommand));
+    registry.register(Box::new(ClearCommand));
+    registry.register(Box::new(HelpCommand));
+}
+
+fn self_id(env: &Env) -> u32 {
+    env.get(crate::SELF_USER_ID_KEY) as u32
+}
+
+/// `/me <action>` sends an action message, like "* User0 waves"
+struct MeCommand;
+
+impl SlashCommand for MeCommand {
+    fn name(&self) -> &str { "me" }
+    fn usage(&self) -> &str { "<action>" }
+    fn description(&self) -> &str { "Send an action message" }
+
+    fn execute(&self, args: &str, _ctx: &mut EventCtx, state: &mut AppState, env: &Env) -> Result<(), String> {
+        if args.is_empty() {
+            return Err("Usage: /me <action>".to_string());
+        }
+        timeline::push_message(state, self_id(env), Message::new(
+            args.to_string(),
+            chrono::offset::Local::now().timestamp(),
+            MessageKind::Action,
+        ));
+        Ok(())
+    }
+}
+
+/// `/nick <name>` changes the display name of the self user
+struct NickCommand;
+
+impl SlashCommand for NickCommand {
+    fn name(&self) -> &str { "nick" }
+    fn usage(&self) -> &str { "<name>" }
+    fn description(&self) -> &str { "Change your display name" }
+
+    fn execute(&self, args: &str, _ctx: &mut EventCtx, state: &mut AppState, env: &Env) -> Result<(), String> {
+        if args.is_empty() {
+            return Err("Usage: /nick <name>".to_string());
+        }
+        state.self_display_name = args.into();
+        timeline::push_local_notice(state, self_id(env), format!("You are now known as {}", args));
+        Ok(())
+    }
+}
+
+/// `/layout <preset>` switches to one of the predefined layouts
+struct LayoutCommand;
+
+impl SlashCommand for LayoutCommand {
+    fn name(&self) -> &str { "layout" }
+    fn usage(&self) -> &str { "<layout>" }
+    fn description(&self) -> &str { "Switch to a predefined layout" }
+
+    fn complete_argument(&self, partial: &str, state: &AppState) -> Vec<String> {
+        let partial = partial.to_lowercase();
+        state.preset_registry.iter()
+            .map(|preset| preset.id.as_str())
+            .filter(|id| id.to_lowercase().starts_with(&partial))
+            .map(|id| id.to_string())
+            .collect()
+    }
+
+    fn execute(&self, args: &str, _ctx: &mut EventCtx, state: &mut AppState, _env: &Env) -> Result<(), String> {
+        let registry = state.preset_registry.clone();
+        match registry.get(args) {
+            Some(preset) => {
+                presets::apply_preset(state, &preset.settings);
+                Ok(())
+            },
+            None => {
+                let ids: Vec<&str> = state.preset_registry.iter().map(|preset| preset.id.as_str()).collect();
+                Err(format!("Unknown layout \"{}\". Available layouts: {}", args, ids.join(", ")))
+            }
+        }
+    }
+}
+
+/// `/clear` removes everything from the timeline
+struct ClearCommand;
+
+impl SlashCommand for ClearCommand {
+    fn name(&self) -> &str { "clear" }
+    fn description(&self) -> &str { "Clear the timeline" }
+
+    fn execute(&self, _args: &str, _ctx: &mut EventCtx, state: &mut AppState, _env: &Env) -> Result<(), String> {
+        state.timeline_data.clear();
+        Ok(())
+    }
+}
+
+/// `/help` lists all registered commands as a local notice
+struct HelpCommand;
+
+impl SlashCommand for HelpCommand {
+    fn name(&self) -> &str { "help" }
+    fn description(&self) -> &str { "List the available commands" }
+
+    fn execute(&self, _args: &str, _ctx: &mut EventCtx, state: &mut AppState, env: &Env) -> Result<(), String> {
+        let mut text = "Available commands:".to_string();
+        for command in state.command_registry.commands() {
+            text.push_str("\n/");
+            text.push_str(command.name());
+            if !command.usage().is_empty() {
+                text.push(' ');
+                text.push_str(command.usage());
+            }
+            text.push_str(" - ");
+            text.push_str(command.description());
+        }
+        timeline::push_local_notice(state, self_id(env), text);
+        Ok(())
+    }
+}
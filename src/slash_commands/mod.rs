@@ -0,0 +1,224 @@
+use druid::{Env, EventCtx};
+use crate::AppState;
+
+pub mod builtin;
+pub mod replay;
+
+/// The prefix that marks composer input as a command instead of a message.
+pub const COMMAND_PREFIX: char = '/';
+/// The maximum amount of completions shown above the composer.
+pub const MAX_COMPLETIONS: usize = 6;
+
+/// A command that can be typed into the composer, like `/me` or `/nick`.
+///
+/// Backends register their own commands through [CommandRegistry::register].
+/// The registry is shared through an `Arc` in the app state, so commands have to be `Send` and `Sync`.
+pub(crate) trait SlashCommand: Send + Sync {
+    /// The name of the command, without the leading slash.
+    fn name(&self) -> &str;
+
+    /// A short description of the arguments, like `<name>`. Empty when it takes no arguments.
+    fn usage(&self) -> &str {
+        ""
+    }
+
+    /// A one line description shown in `/help` and in the completions.
+    fn description(&self) -> &str;
+
+    /// Possible values for the argument that is currently being typed.
+    fn complete_argument(&self, _partial: &str, _state: &AppState) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Runs the command. The returned error is shown as a local notice in the timeline.
+    fn execute(&self, args: &str, ctx: &mut EventCtx, state: &mut AppState, env: &Env) -> Result<(), String>;
+}
+
+/// A suggestion shown above the composer while typing a command.
+#[derive(Clone, PartialEq)]
+pub struct Completion {
+    /// The full composer text after accepting the completion
+    pub replacement: String,
+    /// What is shown to the user
+    pub display: String,
+}
+
+/// Holds every command that the composer knows about.
+pub(crate) struct CommandRegistry {
+    commands: Vec<Box<dyn SlashCommand>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> CommandRegistry {
+        CommandRegistry {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Creates a registry with the commands that are always available.
+    pub fn with_builtin_commands() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+        builtin::register_builtin_commands(&mut registry);
+        registry
+    }
+
+    /// Adds a command. A command registered later replaces an earlier one with the same name.
+    pub fn register(&mut self, command: Box<dyn SlashCommand>) {
+        self.commands.retain(|existing| existing.name() != command.name());
+        self.commands.push(command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn SlashCommand> {
+        self.commands.iter()
+            .find(|command| command.name().eq_ignore_ascii_case(name))
+            .map(|command| command.as_ref())
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &dyn SlashCommand> {
+        self.commands.iter().map(|command| command.as_ref())
+    }
+
+    /// Parses and runs the command in the input.
+    ///
+    /// Unknown commands and failed commands return an error message meant for the user.
+    pub fn execute(&self, input: &str, ctx: &mut EventCtx, state: &mut AppState, env: &Env) -> Result<(), String> {
+        let (name, args) = match parse_command(input) {
+            Some(parsed) => parsed,
+            None => return Err("Not a command.".to_string()),
+        };
+        match self.get(name) {
+            Some(command) => command.execute(args, ctx, state, env),
+            None => Err(format!("Unknown command: /{}. Type /help for a list of commands.", name)),
+        }
+    }
+
+    /// Gets the completions for what has been typed into the composer so far.
+    ///
+    /// While the name is being typed, command names are suggested. After the first space,
+    /// the command is asked for argument values.
+    pub fn completions(&self, input: &str, state: &AppState) -> Vec<Completion> {
+        if !is_command(input) || input.contains('\n') {
+            return Vec::new();
+        }
+        let body = &input[1..];
+        let mut completions = Vec::new();
+        match body.find(' ') {
+            None => {
+                for command in self.commands() {
+                    if command.name().starts_with(&body.to_lowercase()) {
+                        let mut display = format!("/{}", command.name());
+                        if !command.usage().is_empty() {
+                            display.push(' ');
+                            display.push_str(command.usage());
+                        }
+                        display.push_str(" - ");
+                        display.push_str(command.description());
+                        completions.push(Completion {
+                            replacement: format!("/{} ", command.name()),
+                            display,
+                        });
+                    }
+                }
+            },
+            Some(space_index) => {
+                let name = &body[..space_index];
+                let partial = body[space_index + 1..].trim_start();
+                if let Some(command) = self.get(name) {
+                    for value in command.complete_argument(partial, state) {
+                        completions.push(Completion {
+                            replacement: format!("/{} {}", command.name(), value),
+                            display: value,
+                        });
+                    }
+                }
+            }
+        }
+        // Nothing left to complete when the only suggestion is what was already typed
+        if completions.len() == 1 && completions[0].replacement.trim_end() == input.trim_end() {
+            completions.clear();
+        }
+        completions.truncate(MAX_COMPLETIONS);
+        completions
+    }
+}
+
+/// Returns true when the input should be run as a command instead of sent.
+///
+/// Input starting with two slashes is an escaped message, so `//shrug` sends `/shrug`.
+pub fn is_command(input: &str) -> bool {
+    input.starts_with(COMMAND_PREFIX) && !input.starts_with("//")
+}
+
+/// Removes the escaping slash from messages that start with `//`.
+pub fn unescape_message(input: &str) -> &str {
+    if input.starts_with("//") {
+        &input[1..]
+    } else {
+        input
+    }
+}
+
+/// Splits command input into the name and the (trimmed) arguments.
+pub fn parse_command(input: &str) -> Option<(&str, &str)> {
+    if !is_command(input) {
+        return None;
+    }
+    let body = input[1..].trim_end();
+    match body.find(char::is_whitespace) {
+        Some(index) => Some((&body[..index], body[index..].trim_start())),
+        None => Some((body, "")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LayoutSettings;
+    use crate::helper::theme;
+
+    fn replacements(completions: &[Completion]) -> Vec<&str> {
+        completions.iter().map(|completion| completion.replacement.as_str()).collect()
+    }
+
+    #[test]
+    fn commands_are_recognized() {
+        assert!(is_command("/me waves"));
+        assert!(is_command("/"));
+        assert!(!is_command("//shrug"));
+        assert!(!is_command("hello /me"));
+        assert!(!is_command(""));
+        assert_eq!(unescape_message("//shrug"), "/shrug");
+        assert_eq!(unescape_message("hello"), "hello");
+    }
+
+    #[test]
+    fn commands_are_split_into_name_and_arguments() {
+        assert_eq!(parse_command("/me waves"), Some(("me", "waves")));
+        assert_eq!(parse_command("/nick   Alice  "), Some(("nick", "Alice")));
+        assert_eq!(parse_command("/help"), Some(("help", "")));
+        assert_eq!(parse_command("/me\tline one\nline two"), Some(("me", "line one\nline two")));
+        assert_eq!(parse_command("/"), Some(("", "")));
+        assert_eq!(parse_command("//shrug"), None);
+        assert_eq!(parse_command("hello"), None);
+    }
+
+    #[test]
+    fn completions_follow_the_input() {
+        let registry = CommandRegistry::with_builtin_commands();
+        let state = AppState::new(LayoutSettings::default(), theme::default_theme());
+        let all = registry.completions("/", &state);
+        assert_eq!(all.len(), registry.commands().count().min(MAX_COMPLETIONS));
+        assert!(replacements(&registry.completions("/m", &state)).iter().all(|replacement| replacement.starts_with("/m")));
+        assert!(registry.completions("/ME", &state).iter().any(|completion| completion.replacement == "/me "));
+        assert!(registry.completions("/no-such-command", &state).is_empty());
+        assert!(registry.completions("/unknown argument", &state).is_empty());
+        assert!(registry.completions("hello", &state).is_empty());
+        assert!(registry.completions("//me", &state).is_empty());
+        assert!(registry.completions("/me one\ntwo", &state).is_empty());
+        let first_layout = &state.preset_registry.iter().next().unwrap().id;
+        let layouts = registry.completions(&format!("/layout {}", &first_layout[..1]), &state);
+        assert!(replacements(&layouts).contains(&format!("/layout {}", first_layout).as_str()));
+        // Nothing is suggested once the input is already complete
+        assert!(registry.completions("/help ", &state).is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+use druid::{Env, EventCtx};
+use crate::AppState;
+use crate::helper::replay::{ReplayControl, CONTROL_REPLAY};
+use super::{CommandRegistry, SlashCommand};
+
+const ACTIONS: [&str; 4] = ["pause", "resume", "step", "speed"];
+
+/// Registers the commands that are only available while a transcript is replayed
+pub fn register_replay_commands(registry: &mut CommandRegistry) {
+    registry.register(Box::new(ReplayCommand));
+}
+
+/// `/replay pause`, `/replay resume`, `/replay step` and `/replay speed <factor>` control the replay
+struct ReplayCommand;
+
+impl SlashCommand for ReplayCommand {
+    fn name(&self) -> &str { "replay" }
+    fn usage(&self) -> &str { "pause|resume|step|speed <factor>" }
+    fn description(&self) -> &str { "Control the transcript replay" }
+
+    fn complete_argument(&self, partial: &str, _state: &AppState) -> Vec<String> {
+        let partial = partial.to_lowercase();
+        ACTIONS.iter()
+            .filter(|action| action.starts_with(&partial))
+            .map(|action| action.to_string())
+            .collect()
+    }
+
+    fn execute(&self, args: &str, ctx: &mut EventCtx, _state: &mut AppState, _env: &Env) -> Result<(), String> {
+        let mut words = args.split_whitespace();
+        let control = match (words.next(), words.next()) {
+            (Some("pause"), None) => ReplayControl::Pause,
+            (Some("resume"), None) => ReplayControl::Resume,
+            (Some("step"), None) => ReplayControl::Step,
+            (Some("speed"), Some(factor)) => match factor.parse::<f64>() {
+                Ok(speed) if speed > 0.0 && speed.is_finite() => ReplayControl::Speed(speed),
+                _ => return Err(format!("The speed has to be a number above 0, but it is \"{}\"", factor)),
+            },
+            _ => return Err(format!("Usage: /replay {}", self.usage())),
+        };
+        ctx.submit_command(CONTROL_REPLAY.with(control).to(druid::Target::Global));
+        Ok(())
+    }
+}
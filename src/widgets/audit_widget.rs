@@ -0,0 +1,41 @@
+use druid::widget::prelude::*;
+use crate::helper::audit::{self, Counter};
+
+/// Wraps the root of a window to count its timers, layouts and paints for the audit mode.
+/// When the audit is off, it only passes everything on.
+pub struct AuditWidget<W> {
+    inner: W,
+}
+
+impl<W> AuditWidget<W> {
+    pub fn new(inner: W) -> Self {
+        AuditWidget { inner }
+    }
+}
+
+impl<T: Data, W: Widget<T>> Widget<T> for AuditWidget<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Timer(_) = event {
+            audit::record(Counter::TimerWakeup);
+        }
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.inner.update(ctx, old_data, data, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        audit::record(Counter::Layout);
+        self.inner.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        audit::record(Counter::Paint);
+        self.inner.paint(ctx, data, env)
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use druid::widget::prelude::*;
+use druid::{Modifiers, MouseButton, MouseButtons, MouseEvent, Point, Vec2};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::{AppState, LayoutSettings};
+use crate::helper::{benchmark, presets, timeline};
+
+/// The furthest a jump goes, in window heights. The timeline stops the scrolling at its ends.
+const MAX_JUMP_PAGES: f64 = 50.0;
+
+/// One thing the benchmark does to the chat window, for a timeline of a given size
+#[derive(Clone, Copy)]
+enum Step {
+    /// Replaces the timeline with a new one, adding the messages one by one
+    Grouping,
+    /// Scrolls the timeline with the mouse wheel. Holds the number of the jump.
+    ScrollJump(usize),
+    /// Switches to another layout, which lays out every item again
+    SettingsChange,
+}
+
+/// A step that was started, and is measured once the window has drawn it
+struct RunningStep {
+    size: usize,
+    step: Step,
+    start: Instant,
+}
+
+/// Wraps the root of the chat window to run the benchmark through it, then quit.
+///
+/// Each step is started on an animation frame, and the window lays out and paints its result before the next one,
+/// so the time of a step is the time until the window has drawn it.
+pub struct BenchmarkWidget<W> {
+    inner: W,
+    steps: VecDeque<(usize, Step)>,
+    running: Option<RunningStep>,
+    changed_settings: LayoutSettings,
+    rng: StdRng,
+    /// How long the inner widgets took to lay out and paint since the running step started
+    layout_duration: Duration,
+    paint_duration: Duration,
+    /// When the window last finished doing something for the running step
+    last_work_end: Instant,
+    scroll_duration: Duration,
+}
+
+impl<W> BenchmarkWidget<W> {
+    /// The sizes are the numbers of messages to measure with, and the settings are the layout switched to.
+    pub fn new(inner: W, sizes: Vec<usize>, changed_settings: LayoutSettings) -> Self {
+        let steps = sizes.into_iter().flat_map(|size| {
+            std::iter::once(Step::Grouping)
+                .chain((0..benchmark::SCROLL_JUMPS).map(Step::ScrollJump))
+                .chain(std::iter::once(Step::SettingsChange))
+                .map(move |step| (size, step))
+        }).collect();
+        BenchmarkWidget {
+            inner,
+            steps,
+            running: None,
+            changed_settings,
+            rng: StdRng::seed_from_u64(benchmark::SEED),
+            layout_duration: Duration::ZERO,
+            paint_duration: Duration::ZERO,
+            last_work_end: Instant::now(),
+            scroll_duration: Duration::ZERO,
+        }
+    }
+
+    /// Reports the step that the window has finished drawing
+    fn finish_step(&mut self, running: RunningStep) {
+        let RunningStep { size, step, start } = running;
+        match step {
+            Step::Grouping => {
+                benchmark::report(size, "layout", 1, self.layout_duration);
+                benchmark::report(size, "paint", 1, self.paint_duration);
+            },
+            Step::ScrollJump(jump) => {
+                self.scroll_duration += self.last_work_end - start;
+                if jump + 1 == benchmark::SCROLL_JUMPS {
+                    benchmark::report(size, "scroll_jump", benchmark::SCROLL_JUMPS, self.scroll_duration);
+                    self.scroll_duration = Duration::ZERO;
+                }
+            },
+            Step::SettingsChange => benchmark::report(size, "settings_change", 1, self.last_work_end - start),
+        }
+    }
+}
+
+impl<W: Widget<AppState>> BenchmarkWidget<W> {
+    fn start_step(&mut self, ctx: &mut EventCtx, size: usize, step: Step, data: &mut AppState, env: &Env) {
+        self.layout_duration = Duration::ZERO;
+        self.paint_duration = Duration::ZERO;
+        let start = match step {
+            Step::Grouping => {
+                let messages = benchmark::generate_messages(size);
+                *data = benchmark::new_state();
+                // Grouping is done before the window sees the new timeline, so it is reported right away
+                let start = Instant::now();
+                for (user_id, message) in messages {
+                    timeline::push_message(data, user_id, message);
+                }
+                benchmark::report(size, "grouping", 1, start.elapsed());
+                start
+            },
+            Step::ScrollJump(_) => {
+                let start = Instant::now();
+                let pages = self.rng.gen_range(-MAX_JUMP_PAGES..MAX_JUMP_PAGES);
+                let wheel = get_wheel_event(ctx.size(), pages * ctx.size().height);
+                self.inner.event(ctx, &Event::Wheel(wheel), data, env);
+                start
+            },
+            Step::SettingsChange => {
+                let start = Instant::now();
+                presets::apply_preset(data, &self.changed_settings);
+                start
+            },
+        };
+        self.last_work_end = Instant::now();
+        self.running = Some(RunningStep { size, step, start });
+    }
+}
+
+impl<W: Widget<AppState>> Widget<AppState> for BenchmarkWidget<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::WindowConnected => ctx.request_anim_frame(),
+            // A frame is only asked for after the last step was started, so the window has drawn it by now
+            Event::AnimFrame(_) if self.running.is_some() || !self.steps.is_empty() => {
+                if let Some(running) = self.running.take() {
+                    self.finish_step(running);
+                }
+                match self.steps.pop_front() {
+                    Some((size, step)) => {
+                        self.start_step(ctx, size, step, data, env);
+                        ctx.request_anim_frame();
+                    },
+                    None => ctx.submit_command(druid::commands::QUIT_APP),
+                }
+            },
+            _ => (),
+        }
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        self.inner.update(ctx, old_data, data, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, env: &Env) -> Size {
+        let start = Instant::now();
+        let size = self.inner.layout(ctx, bc, data, env);
+        self.layout_duration += start.elapsed();
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        let start = Instant::now();
+        self.inner.paint(ctx, data, env);
+        self.last_work_end = Instant::now();
+        self.paint_duration += self.last_work_end - start;
+    }
+}
+
+/// A turn of the mouse wheel over the middle of the window, where the timeline is
+fn get_wheel_event(window_size: Size, distance: f64) -> MouseEvent {
+    let pos = Point::new(window_size.width / 2.0, window_size.height / 2.0);
+    MouseEvent {
+        pos,
+        window_pos: pos,
+        buttons: MouseButtons::new(),
+        mods: Modifiers::empty(),
+        count: 0,
+        focus: false,
+        button: MouseButton::None,
+        wheel_delta: Vec2::new(0.0, distance),
+    }
+}
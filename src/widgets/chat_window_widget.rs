@@ -1,21 +1,34 @@
 use std::time::Duration;
 
-use druid::{WindowDesc, Widget, WidgetPod, WidgetExt, EventCtx, im, Event, TimerToken, Screen, Monitor, Size};
+use druid::{WindowDesc, Widget, WidgetPod, WidgetExt, EventCtx, Event, TimerToken, Screen, Monitor, Size, WindowHandle, Selector};
 use druid::widget;
-use crate::{AppState, Message, MessageGroup};
+use crate::{AppState, Message, MessageKind};
 use super::timeline_item_widget;
+use super::composer_controller::{self, ComposerController};
+use super::message_shortcut_controller::MessageShortcutController;
+use super::emoji_picker_widget;
 use tracing::error;
 use crate::settings_ui::build_settings_ui;
+use crate::slash_commands;
+use crate::helper::{timeline, emoji, message_actions, helper_functions, presets, theme};
 
 pub struct ChatWindowWidget {
     header: WidgetPod<AppState, widget::Container<AppState>>,
     timeline: WidgetPod<AppState, Box<dyn druid::Widget<AppState>>>,
     footer: WidgetPod<AppState, widget::Flex<AppState>>,
     location_timer_token: TimerToken,
+    /// Where the window was when docking was last checked, to only check again after it moved
+    last_checked_position: Option<druid::Point>,
 }
 
-const LOCATION_CHECK_TIMER_INTERVAL: Duration = Duration::from_millis(200);
+/// Slides the window into the bottom right corner of the screen when it's dropped near it
+const DOCK_TO_CORNER: bool = false;
 const LOCATION_MOVE_INTERVAL: Duration = Duration::from_millis(16);
+/// The size the settings window opens at, unless the screen is smaller
+const SETTINGS_WINDOW_SIZE: Size = Size::new(1800.0, 750.0);
+const SETTINGS_WINDOW_MIN_SIZE: Size = Size::new(480.0, 400.0);
+/// Opens the settings window, the same as clicking the settings icon. Sent at launch with `--settings`.
+pub const OPEN_SETTINGS: Selector = Selector::new("polysoft.druid-demo.open_settings");
 
 impl ChatWindowWidget {
     pub fn new() -> ChatWindowWidget {
@@ -23,7 +36,8 @@ impl ChatWindowWidget {
             header: Self::build_title(),
             timeline: Self::build_timeline(),
             footer: Self::build_footer(),
-            location_timer_token: TimerToken::INVALID
+            location_timer_token: TimerToken::INVALID,
+            last_checked_position: None,
         }
     }
 
@@ -37,20 +51,61 @@ impl ChatWindowWidget {
             }
         };
 
-        WidgetPod::new(widget::Flex::row()
+        WidgetPod::new(widget::Flex::column()
+            .with_child(widget::Flex::row()
+                .with_flex_child(
+                    widget::Label::new("Chat Title")
+                    .with_line_break_mode(widget::LineBreaking::WordWrap)
+                    .padding(7.0)
+                    .expand_width(),
+                1.0)
+                .with_child(
+                    widget::ControllerHost::new(
+                        widget::Svg::new(settings_svg).fix_height(15.0).padding(7.0),
+                        widget::Click::new(on_settings_icon_click)
+                    )
+                )
+            )
+            .with_child(Self::build_config_banner())
+            .with_child(Self::build_replay_status())
+            .background(druid::theme::BACKGROUND_LIGHT)
+        )
+    }
+
+    /// Shown under the title when a changed config file couldn't be fully read.
+    /// It doesn't block anything, and goes away once the file is fixed or it is dismissed.
+    fn build_config_banner() -> impl Widget<AppState> {
+        let banner = widget::Flex::row()
             .with_flex_child(
-                widget::Label::new("Chat Title")
-                .with_line_break_mode(widget::LineBreaking::WordWrap)
-                .padding(7.0)
-                .expand_width(),
+                widget::Label::new(|data: &AppState, _env: &_| data.config_banner.clone())
+                    .with_line_break_mode(widget::LineBreaking::WordWrap)
+                    .with_text_color(theme::WARNING_COLOR_KEY)
+                    .expand_width(),
             1.0)
+            .with_default_spacer()
             .with_child(
-                widget::ControllerHost::new(
-                    widget::Svg::new(settings_svg).fix_height(15.0).padding(7.0),
-                    widget::Click::new(on_settings_icon_click)
-                )
+                widget::Button::new("Dismiss")
+                    .on_click(|_ctx, data: &mut AppState, _env| data.config_banner = String::new())
             )
-            .background(druid::theme::BACKGROUND_LIGHT)
+            .padding(7.0)
+            .border(theme::WARNING_COLOR_KEY, 1.0);
+        widget::Either::new(
+            |data: &AppState, _env| !data.config_banner.is_empty(),
+            banner,
+            widget::SizedBox::empty()
+        )
+    }
+
+    /// Shown under the title while a transcript is replayed, with how far it has played
+    fn build_replay_status() -> impl Widget<AppState> {
+        widget::Either::new(
+            |data: &AppState, _env| !data.replay_status.is_empty(),
+            widget::Label::new(|data: &AppState, _env: &_| data.replay_status.clone())
+                .with_line_break_mode(widget::LineBreaking::Clip)
+                .with_text_size(12.0)
+                .padding(druid::Insets::new(7.0, 0.0, 7.0, 5.0))
+                .expand_width(),
+            widget::SizedBox::empty()
         )
     }
 
@@ -66,6 +121,7 @@ impl ChatWindowWidget {
             .vertical()
             .expand()
             .lens(AppState::timeline_data)
+            .controller(MessageShortcutController)
             .boxed()
         )
     }
@@ -80,20 +136,81 @@ impl ChatWindowWidget {
             }
         };
 
-        WidgetPod::new(widget::Flex::row()
-            .with_flex_child(
-                widget::TextBox::multiline()
-                    .with_placeholder("Message...")
-                    .lens(AppState::text_edit)
-                    .padding(1.0)
-                    .expand_width(),
-            1.0)
-            .with_child(
-                widget::ControllerHost::new(
-                    widget::Svg::new(send_svg).fix_height(25.0).padding(5.0),
-                    widget::Click::new(on_send_icon_click)
+        let completion_hint = widget::Either::new(
+            |data: &AppState, _env: &_| !composer_controller::get_composer_hint(data).is_empty(),
+            widget::Label::new(|data: &AppState, _env: &_| composer_controller::get_composer_hint(data))
+                .with_line_break_mode(widget::LineBreaking::WordWrap)
+                .with_text_size(12.0)
+                .padding(5.0)
+                .expand_width()
+                .background(druid::theme::BACKGROUND_LIGHT),
+            widget::SizedBox::empty()
+        );
+
+        let emoji_picker = widget::Either::new(
+            |data: &AppState, _env: &_| data.emoji_picker_open,
+            emoji_picker_widget::build_emoji_picker(),
+            widget::SizedBox::empty()
+        );
+
+        // Shows what the next send will do when replying or editing
+        let composer_context = widget::Either::new(
+            |data: &AppState, _env: &_| data.replying_to.is_some() || data.editing_message.is_some(),
+            widget::Flex::row()
+                .with_flex_child(
+                    widget::Label::new(|data: &AppState, env: &druid::Env| get_composer_context_text(data, env))
+                        .with_line_break_mode(widget::LineBreaking::Clip)
+                        .with_text_size(12.0)
+                        .expand_width(),
+                1.0)
+                .with_child(
+                    widget::Label::new("✕")
+                        .with_text_size(12.0)
+                        .on_click(|ctx: &mut EventCtx, _data: &mut AppState, _env: &_| {
+                            ctx.submit_command(message_actions::CANCEL_COMPOSER_ACTION)
+                        })
+                )
+                .padding(5.0)
+                .background(druid::theme::BACKGROUND_LIGHT),
+            widget::SizedBox::empty()
+        );
+
+        let typing_indicator = widget::Either::new(
+            |data: &AppState, _env: &_| !data.typing_users.is_empty(),
+            widget::Label::new(|data: &AppState, env: &druid::Env| get_typing_text(data, env))
+                .with_line_break_mode(widget::LineBreaking::Clip)
+                .with_text_size(12.0)
+                .padding((5.0, 2.0))
+                .expand_width(),
+            widget::SizedBox::empty()
+        );
+
+        WidgetPod::new(widget::Flex::column()
+            .with_child(typing_indicator)
+            .with_child(emoji_picker)
+            .with_child(composer_context)
+            .with_child(completion_hint)
+            .with_child(widget::Flex::row()
+                .with_flex_child(
+                    widget::TextBox::multiline()
+                        .with_placeholder("Message...")
+                        .lens(AppState::text_edit)
+                        .controller(ComposerController)
+                        .padding(1.0)
+                        .expand_width(),
+                1.0)
+                .with_child(
+                    widget::Label::new("🙂")
+                        .with_text_size(18.0)
+                        .padding(5.0)
+                        .on_click(on_emoji_icon_click)
+                )
+                .with_child(
+                    widget::ControllerHost::new(
+                        widget::Svg::new(send_svg).fix_height(25.0).padding(5.0),
+                        widget::Click::new(on_send_icon_click)
+                    )
                 )
-                
             )
         )
     }
@@ -104,13 +221,20 @@ impl ChatWindowWidget {
 impl Widget<AppState> for ChatWindowWidget {
     fn event(&mut self, ctx: &mut druid::EventCtx, event: &druid::Event, data: &mut AppState, env: &druid::Env) {
         match event {
-            Event::WindowConnected => {
-                // Start the timer when the application launches
-                //self.location_timer_token = ctx.request_timer(LOCATION_CHECK_TIMER_INTERVAL);
+            // There's no event for the window moving, so docking is checked when it's shown or resized,
+            // and when the mouse comes back to it after it was dragged somewhere else.
+            // Nothing polls the position while the app is idle.
+            // Asking the window for its position goes to the OS, so it's only done when docking is on
+            Event::WindowConnected | Event::WindowSize(_) | Event::MouseMove(_)
+                if DOCK_TO_CORNER
+                    && self.location_timer_token == TimerToken::INVALID
+                    && self.last_checked_position != Some(ctx.window().get_position()) =>
+            {
+                self.location_timer_token = ctx.request_timer(LOCATION_MOVE_INTERVAL);
             }
             Event::Timer(id) => {
                 if *id == self.location_timer_token {
-                    let monitor = get_current_monitor(ctx);
+                    let monitor = get_current_monitor(ctx.window());
                     let window_size = ctx.window().get_size();
                     let dock_origin = get_dock_origin(&monitor, &window_size);
                     let window = ctx.window();
@@ -134,11 +258,18 @@ impl Widget<AppState> for ChatWindowWidget {
                         }
                         self.location_timer_token = ctx.request_timer(LOCATION_MOVE_INTERVAL);
                     } else {
-                        self.location_timer_token = ctx.request_timer(LOCATION_CHECK_TIMER_INTERVAL);
+                        // Docked, or left somewhere else on purpose. Stop until it moves again.
+                        self.location_timer_token = TimerToken::INVALID;
+                        self.last_checked_position = Some(window_position);
                     }
                     return; // Handled. No need to run the event to every other widget.
                 }
             }
+            Event::Command(cmd) if cmd.is(OPEN_SETTINGS) => {
+                on_settings_icon_click(ctx, data, env);
+                ctx.set_handled();
+                return;
+            }
             _ => (),
         }
         self.header.event(ctx, event, data, env);
@@ -187,29 +318,72 @@ impl Widget<AppState> for ChatWindowWidget {
 }
 
 
-fn on_send_icon_click(_ctx: &mut EventCtx, state: &mut AppState, env: &druid::Env) {
+fn on_send_icon_click(ctx: &mut EventCtx, state: &mut AppState, env: &druid::Env) {
     println!("Send click");
 
     // Find which user is self
-    let self_id = env.get(crate::SELF_USER_ID_KEY);
-
-    // TODO: Check to see if last thing in the timeline is a message from
-    // self user to append to existing group.
-    state.timeline_data.push_back(
-        MessageGroup {
-            messages: im::vector![
-                Message {
-                    message: state.text_edit.to_string(),
-                    position_in_group: 0,
-                    timestamp_epoch_seconds: chrono::offset::Local::now().timestamp()
+    let self_id = env.get(crate::SELF_USER_ID_KEY) as u32;
+    let input = state.text_edit.to_string();
+    if input.trim().is_empty() {
+        return;
+    }
+
+    if slash_commands::is_command(&input) {
+        // Commands are run locally instead of being sent
+        let registry = state.command_registry.clone();
+        if let Err(error_msg) = registry.execute(&input, ctx, state, env) {
+            timeline::push_local_notice(state, self_id, error_msg);
+        }
+    } else {
+        let message = emoji::expand_shortcodes(slash_commands::unescape_message(&input));
+        // The last one used ends up first
+        for used_emoji in emoji::find_used_emoji(&message) {
+            emoji::push_recent(&mut state.recent_emoji, used_emoji.emoji);
+        }
+        match state.editing_message.take() {
+            Some(message_id) => {
+                if let Some((_, edited_message)) = timeline::find_message_mut(state, message_id) {
+                    edited_message.message = message;
+                    edited_message.edited = true;
                 }
-            ],
-            user_id: self_id as u32,
-            profile_pic: state.profile_pics[self_id as usize].clone(),
+            },
+            None => {
+                let mut new_message = Message::new(message, chrono::offset::Local::now().timestamp(), MessageKind::Text);
+                new_message.reply_to = state.replying_to.take();
+                timeline::push_message(state, self_id, new_message);
+            }
         }
-    );
+    }
+
+    state.text_edit = String::new().into();
+    state.emoji_picker_open = false;
+}
+
+fn get_composer_context_text(data: &AppState, env: &druid::Env) -> String {
+    if data.editing_message.is_some() {
+        "Editing message".to_string()
+    } else if let Some(reply) = &data.replying_to {
+        format!("Replying to {}: {}", helper_functions::get_display_name(reply.user_id, env), reply.text)
+    } else {
+        String::new()
+    }
+}
+
+fn get_typing_text(data: &AppState, env: &druid::Env) -> String {
+    let names: Vec<String> = data.typing_users.iter()
+        .map(|user_id| helper_functions::get_display_name(*user_id, env))
+        .collect();
+    match names.as_slice() {
+        [] => String::new(),
+        [name] => format!("{} is typing…", name),
+        [first, second] => format!("{} and {} are typing…", first, second),
+        _ => "Several people are typing…".to_string(),
+    }
+}
 
-    //state.text_edit
+fn on_emoji_icon_click(_ctx: &mut EventCtx, state: &mut AppState, _env: &druid::Env) {
+    state.emoji_picker_open = !state.emoji_picker_open;
+    state.emoji_search = String::new().into();
 }
 
 fn on_settings_icon_click(ctx: &mut EventCtx, state: &mut AppState, _env: &druid::Env) {
@@ -219,18 +393,34 @@ fn on_settings_icon_click(ctx: &mut EventCtx, state: &mut AppState, _env: &druid
         println!("Settings already open. Ignoring.");
     } else {
         state.settings_open = true; // Prevent it from being opened a second time
-        let settings_size = druid::Size::new(1400.0, 750.0);
-        let mut new_win = WindowDesc::new(build_settings_ui()).resizable(false);
-        new_win = new_win.window_size(settings_size);
+        // Picks up preset files added to the presets directory since the last time
+        state.user_presets = presets::load_user_presets();
+        state.preset_status = String::new();
+        // The same for theme files
+        state.themes = theme::load_themes();
+        state.theme_status = String::new();
+        state.settings_search = String::new().into();
+        // Small screens get a window that fits, and the settings reflow into fewer columns
+        let settings_size = match get_current_monitor(ctx.window()) {
+            Some(monitor) => {
+                let work_area = monitor.virtual_work_rect().size();
+                Size::new(SETTINGS_WINDOW_SIZE.width.min(work_area.width), SETTINGS_WINDOW_SIZE.height.min(work_area.height))
+            },
+            None => SETTINGS_WINDOW_SIZE,
+        };
+        let new_win = WindowDesc::new(build_settings_ui(&state.preset_registry, &state.profile_pics))
+            .resizable(true)
+            .with_min_size(SETTINGS_WINDOW_MIN_SIZE)
+            .window_size(settings_size);
         ctx.new_window(new_win);
     }
 }
 
-fn get_current_monitor(ctx: &mut EventCtx) -> Option<Monitor>{
+fn get_current_monitor(window: &WindowHandle) -> Option<Monitor>{
     // Determine which monitor it's on
     let mut monitor_found: Option<Monitor> = None;
     for monitor in Screen::get_monitors() {
-        if monitor.virtual_rect().contains(ctx.window().get_position()) {
+        if monitor.virtual_rect().contains(window.get_position()) {
             monitor_found = Some(monitor);
         }
     }
@@ -0,0 +1,255 @@
+use druid::widget::prelude::*;
+use druid::widget::TextBox;
+use druid::{Color, LinearGradient, Point, Rect, UnitPoint, WidgetPod};
+use druid::kurbo::Circle;
+use crate::helper::color::{self, SimpleColor};
+
+/// The width of the picker, and the height of the saturation and value square
+const PICKER_SIZE: f64 = 150.0;
+const BAR_HEIGHT: f64 = 12.0;
+const SPACING: f64 = 6.0;
+const SWATCH_SIZE: f64 = 28.0;
+const CHECKER_SIZE: f64 = 6.0;
+const MARKER_RADIUS: f64 = 5.0;
+const BORDER_COLOR: Color = Color::rgb8(120, 120, 120);
+const CHECKER_LIGHT_COLOR: Color = Color::rgb8(200, 200, 200);
+const CHECKER_DARK_COLOR: Color = Color::rgb8(150, 150, 150);
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragTarget {
+    SaturationValue,
+    Hue,
+    Alpha,
+}
+
+/// Edits a color with a saturation and value square, a hue slider, an alpha slider, and a hex field.
+///
+/// The hue is kept separately from the color, since grays and black don't have one,
+/// and it would otherwise jump back to red while dragging through them.
+/// Every change also refreshes the timeline, so it shows the new color right away.
+pub struct ColorPicker {
+    hue: f64,
+    saturation: f64,
+    value: f64,
+    /// The color the hue, saturation, and value were last set from, or set to
+    current: Option<SimpleColor>,
+    dragging: Option<DragTarget>,
+    hex_text: String,
+    hex_box: WidgetPod<String, TextBox<String>>,
+}
+
+impl ColorPicker {
+    pub fn new() -> Self {
+        ColorPicker {
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.0,
+            current: None,
+            dragging: None,
+            hex_text: String::new(),
+            hex_box: WidgetPod::new(TextBox::new()),
+        }
+    }
+
+    fn square_rect() -> Rect {
+        Rect::from_origin_size(Point::ORIGIN, (PICKER_SIZE, PICKER_SIZE))
+    }
+
+    fn hue_rect() -> Rect {
+        Rect::from_origin_size((0.0, PICKER_SIZE + SPACING), (PICKER_SIZE, BAR_HEIGHT))
+    }
+
+    fn alpha_rect() -> Rect {
+        Rect::from_origin_size((0.0, PICKER_SIZE + BAR_HEIGHT + SPACING * 2.0), (PICKER_SIZE, BAR_HEIGHT))
+    }
+
+    fn swatch_rect() -> Rect {
+        Rect::from_origin_size((0.0, PICKER_SIZE + BAR_HEIGHT * 2.0 + SPACING * 3.0), (SWATCH_SIZE, SWATCH_SIZE))
+    }
+
+    fn find_drag_target(pos: Point) -> Option<DragTarget> {
+        if ColorPicker::square_rect().contains(pos) {
+            Some(DragTarget::SaturationValue)
+        } else if ColorPicker::hue_rect().contains(pos) {
+            Some(DragTarget::Hue)
+        } else if ColorPicker::alpha_rect().contains(pos) {
+            Some(DragTarget::Alpha)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the hue, saturation, and value from the color, unless it is the color they made.
+    fn sync_from_color(&mut self, data: &SimpleColor) {
+        if self.current.as_ref() == Some(data) {
+            return;
+        }
+        let (hue, saturation, value) = color::rgb_to_hsv(data.r, data.g, data.b);
+        // Keep the old hue and saturation when the new color doesn't have them
+        if saturation > 0.0 && value > 0.0 {
+            self.hue = hue;
+        }
+        if value > 0.0 {
+            self.saturation = saturation;
+        }
+        self.value = value;
+        self.current = Some(data.clone());
+    }
+
+    /// Moves the dragged part to the position, and updates the color to match.
+    fn drag_to(&mut self, target: DragTarget, pos: Point, data: &mut SimpleColor) {
+        let x = ((pos.x) / PICKER_SIZE).clamp(0.0, 1.0);
+        match target {
+            DragTarget::SaturationValue => {
+                self.saturation = x;
+                self.value = 1.0 - (pos.y / PICKER_SIZE).clamp(0.0, 1.0);
+            },
+            DragTarget::Hue => self.hue = x * 360.0,
+            DragTarget::Alpha => data.a = (x * 255.0).round() as u8,
+        }
+        let (r, g, b) = color::hsv_to_rgb(self.hue, self.saturation, self.value);
+        data.r = r;
+        data.g = g;
+        data.b = b;
+        self.current = Some(data.clone());
+    }
+
+    fn paint_checkerboard(ctx: &mut PaintCtx, rect: Rect) {
+        ctx.fill(rect, &CHECKER_LIGHT_COLOR);
+        ctx.with_save(|ctx| {
+            ctx.clip(rect);
+            let columns = (rect.width() / CHECKER_SIZE).ceil() as usize;
+            let rows = (rect.height() / CHECKER_SIZE).ceil() as usize;
+            for row in 0..rows {
+                for column in (row % 2..columns).step_by(2) {
+                    let square = Rect::from_origin_size(
+                        (rect.x0 + column as f64 * CHECKER_SIZE, rect.y0 + row as f64 * CHECKER_SIZE),
+                        (CHECKER_SIZE, CHECKER_SIZE)
+                    );
+                    ctx.fill(square, &CHECKER_DARK_COLOR);
+                }
+            }
+        });
+    }
+
+    fn paint_bar_marker(ctx: &mut PaintCtx, rect: Rect, fraction: f64) {
+        let x = rect.x0 + fraction * rect.width();
+        let marker = Rect::new(x - 2.0, rect.y0 - 1.0, x + 2.0, rect.y1 + 1.0);
+        ctx.fill(marker, &Color::WHITE);
+        ctx.stroke(marker, &Color::BLACK, 1.0);
+    }
+}
+
+impl Widget<SimpleColor> for ColorPicker {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut SimpleColor, env: &Env) {
+        match event {
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                if let Some(target) = ColorPicker::find_drag_target(mouse_event.pos) {
+                    self.dragging = Some(target);
+                    self.drag_to(target, mouse_event.pos, data);
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    return;
+                }
+            },
+            Event::MouseMove(mouse_event) if ctx.is_active() => {
+                if let Some(target) = self.dragging {
+                    self.drag_to(target, mouse_event.pos, data);
+                    ctx.set_handled();
+                    return;
+                }
+            },
+            Event::MouseUp(_) if ctx.is_active() => {
+                self.dragging = None;
+                ctx.set_active(false);
+                ctx.set_handled();
+                return;
+            },
+            _ => {}
+        }
+        self.hex_box.event(ctx, event, &mut self.hex_text, env);
+        // Only a complete hex color changes the color, so it can be typed one character at a time
+        if let Some(typed) = SimpleColor::from_hex(&self.hex_text) {
+            if typed != *data {
+                *data = typed;
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &SimpleColor, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.sync_from_color(data);
+            self.hex_text = data.to_hex();
+        }
+        self.hex_box.lifecycle(ctx, event, &self.hex_text, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &SimpleColor, data: &SimpleColor, env: &Env) {
+        if !old_data.same(data) {
+            self.sync_from_color(data);
+            // Leave the text alone while it is being typed, unless it no longer matches
+            if SimpleColor::from_hex(&self.hex_text).as_ref() != Some(data) {
+                self.hex_text = data.to_hex();
+            }
+            ctx.request_paint();
+        }
+        self.hex_box.update(ctx, &self.hex_text, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &SimpleColor, env: &Env) -> Size {
+        let swatch_rect = ColorPicker::swatch_rect();
+        let hex_width = PICKER_SIZE - SWATCH_SIZE - SPACING;
+        let hex_size = self.hex_box.layout(ctx, &BoxConstraints::tight(Size::new(hex_width, SWATCH_SIZE)), &self.hex_text, env);
+        self.hex_box.set_origin(ctx, Point::new(swatch_rect.x1 + SPACING, swatch_rect.y0));
+        bc.constrain(Size::new(PICKER_SIZE, swatch_rect.y0 + hex_size.height.max(SWATCH_SIZE)))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &SimpleColor, env: &Env) {
+        // The square fades from white to the hue left to right, then to black top to bottom
+        let square_rect = ColorPicker::square_rect();
+        let (hue_r, hue_g, hue_b) = color::hsv_to_rgb(self.hue, 1.0, 1.0);
+        let saturation_gradient = LinearGradient::new(
+            UnitPoint::LEFT, UnitPoint::RIGHT,
+            (Color::WHITE, Color::rgb8(hue_r, hue_g, hue_b))
+        );
+        let value_gradient = LinearGradient::new(
+            UnitPoint::TOP, UnitPoint::BOTTOM,
+            (Color::rgba8(0, 0, 0, 0), Color::BLACK)
+        );
+        ctx.fill(square_rect, &saturation_gradient);
+        ctx.fill(square_rect, &value_gradient);
+        ctx.stroke(square_rect, &BORDER_COLOR, 1.0);
+        let marker_center = Point::new(self.saturation * PICKER_SIZE, (1.0 - self.value) * PICKER_SIZE);
+        ctx.stroke(Circle::new(marker_center, MARKER_RADIUS), &Color::BLACK, 2.0);
+        ctx.stroke(Circle::new(marker_center, MARKER_RADIUS), &Color::WHITE, 1.0);
+
+        let hue_rect = ColorPicker::hue_rect();
+        let hue_stops: Vec<Color> = (0..=6)
+            .map(|step| {
+                let (r, g, b) = color::hsv_to_rgb(step as f64 * 60.0, 1.0, 1.0);
+                Color::rgb8(r, g, b)
+            })
+            .collect();
+        ctx.fill(hue_rect, &LinearGradient::new(UnitPoint::LEFT, UnitPoint::RIGHT, hue_stops.as_slice()));
+        ctx.stroke(hue_rect, &BORDER_COLOR, 1.0);
+        ColorPicker::paint_bar_marker(ctx, hue_rect, self.hue / 360.0);
+
+        let alpha_rect = ColorPicker::alpha_rect();
+        ColorPicker::paint_checkerboard(ctx, alpha_rect);
+        let alpha_gradient = LinearGradient::new(
+            UnitPoint::LEFT, UnitPoint::RIGHT,
+            (Color::rgba8(data.r, data.g, data.b, 0), Color::rgb8(data.r, data.g, data.b))
+        );
+        ctx.fill(alpha_rect, &alpha_gradient);
+        ctx.stroke(alpha_rect, &BORDER_COLOR, 1.0);
+        ColorPicker::paint_bar_marker(ctx, alpha_rect, data.a as f64 / 255.0);
+
+        // The checkerboard shows through when the color is see-through
+        let swatch_rect = ColorPicker::swatch_rect();
+        ColorPicker::paint_checkerboard(ctx, swatch_rect);
+        ctx.fill(swatch_rect, &data.to_druid_color());
+        ctx.stroke(swatch_rect, &BORDER_COLOR, 1.0);
+
+        self.hex_box.paint(ctx, &self.hex_text, env);
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync;
+use druid::widget::{Controller, prelude::*};
+use druid::KbKey;
+use crate::AppState;
+use crate::slash_commands::{self, Completion};
+use crate::helper::{emoji, message_actions};
+
+/// Handles the keyboard shortcuts of the composer.
+///
+/// Pressing tab accepts the first completion shown above the composer,
+/// and escape stops replying or editing.
+pub struct ComposerController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ComposerController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::KeyDown(key_event) = event {
+            if key_event.key == KbKey::Tab && !key_event.mods.shift() {
+                if let Some(completion) = get_composer_completions(data).first() {
+                    data.text_edit = sync::Arc::new(completion.replacement.clone());
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            if key_event.key == KbKey::Escape && (data.replying_to.is_some() || data.editing_message.is_some()) {
+                ctx.submit_command(message_actions::CANCEL_COMPOSER_ACTION);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+/// Gets the completions for the current composer text.
+/// These are commands while typing a command, or emoji while typing a `:shortcode`.
+pub fn get_composer_completions(data: &AppState) -> Vec<Completion> {
+    if slash_commands::is_command(&data.text_edit) {
+        data.command_registry.completions(&data.text_edit, data)
+    } else {
+        get_shortcode_completions(&data.text_edit)
+    }
+}
+
+fn get_shortcode_completions(text: &str) -> Vec<Completion> {
+    let partial = match emoji::get_partial_shortcode(text) {
+        Some(partial) => partial,
+        None => return Vec::new(),
+    };
+    // Everything before the colon stays as it is
+    let prefix = &text[..text.len() - partial.len() - 1];
+    emoji::search(partial).iter()
+        .take(slash_commands::MAX_COMPLETIONS)
+        .map(|emoji| Completion {
+            replacement: format!("{}{}", prefix, emoji.emoji),
+            display: format!("{} :{}:", emoji.emoji, emoji.shortcode),
+        })
+        .collect()
+}
+
+/// The text shown above the composer, listing the completions. Empty if there are none.
+pub fn get_composer_hint(data: &AppState) -> String {
+    let completions = get_composer_completions(data);
+    if completions.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<&str> = completions.iter().map(|completion| completion.display.as_str()).collect();
+    format!("{}\nTab to complete", lines.join("\n"))
+}
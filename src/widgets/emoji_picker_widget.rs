@@ -0,0 +1,184 @@
+use std::sync;
+use druid::widget::prelude::*;
+use druid::{widget, WidgetExt, Point, TextLayout, ArcStr};
+use crate::AppState;
+use crate::helper::emoji::{self, Emoji, EmojiCategory};
+
+const CELL_SIZE: f64 = 32.0;
+const EMOJI_FONT_SIZE: f64 = 20.0;
+const TITLE_FONT_SIZE: f64 = 12.0;
+const TITLE_SPACING: f64 = 4.0;
+const PICKER_HEIGHT: f64 = 200.0;
+
+/// Builds the panel shown above the composer when the emoji button is clicked.
+/// It has a search box followed by the grid of emoji.
+pub fn build_emoji_picker() -> impl Widget<AppState> {
+    widget::Flex::column()
+        .with_child(
+            widget::TextBox::new()
+                .with_placeholder("Search emoji...")
+                .lens(AppState::emoji_search)
+                .expand_width()
+                .padding(3.0)
+        )
+        .with_child(
+            widget::Scroll::new(EmojiGridWidget::new())
+                .vertical()
+                .fix_height(PICKER_HEIGHT)
+        )
+        .background(druid::theme::BACKGROUND_LIGHT)
+}
+
+/// A titled group of emoji in the grid
+struct EmojiSection {
+    title: TextLayout<ArcStr>,
+    title_origin: Point,
+    cells: Vec<EmojiCell>,
+}
+
+struct EmojiCell {
+    emoji: &'static str,
+    layout: TextLayout<ArcStr>,
+    rect: druid::Rect,
+}
+
+/// Shows the emoji grouped by category, with the recently used ones first.
+///
+/// When something is typed in the search box, only the matches are shown.
+/// Clicking an emoji inserts it into the composer.
+pub struct EmojiGridWidget {
+    sections: Vec<EmojiSection>,
+    hot_cell: Option<druid::Rect>,
+}
+
+impl EmojiGridWidget {
+    pub fn new() -> Self {
+        EmojiGridWidget {
+            sections: Vec::new(),
+            hot_cell: None,
+        }
+    }
+
+    /// Gets the titles and emoji to show for the current search and recently used emoji.
+    fn get_section_contents(data: &AppState) -> Vec<(String, Vec<&'static str>)> {
+        if !data.emoji_search.trim().is_empty() {
+            let matches = emoji::search(&data.emoji_search);
+            let title = if matches.is_empty() { "No emoji found" } else { "Search results" };
+            return vec![(title.to_string(), matches.iter().map(|emoji| emoji.emoji).collect())];
+        }
+        let mut contents = Vec::new();
+        if !data.recent_emoji.is_empty() {
+            let recent: Vec<&'static str> = data.recent_emoji.iter()
+                .filter_map(|recent| emoji::EMOJI.iter().find(|emoji| emoji.emoji == recent.as_str()))
+                .map(|emoji| emoji.emoji)
+                .collect();
+            contents.push(("Recently used".to_string(), recent));
+        }
+        for category in EmojiCategory::ALL {
+            let emoji: Vec<&'static str> = emoji::get_emoji_in_category(category)
+                .map(|emoji: &Emoji| emoji.emoji)
+                .collect();
+            contents.push((category.name().to_string(), emoji));
+        }
+        contents
+    }
+
+    fn find_cell(&self, pos: Point) -> Option<&EmojiCell> {
+        self.sections.iter()
+            .flat_map(|section| section.cells.iter())
+            .find(|cell| cell.rect.contains(pos))
+    }
+}
+
+impl Widget<AppState> for EmojiGridWidget {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, _env: &Env) {
+        match event {
+            Event::MouseMove(mouse_event) => {
+                let hot_cell = self.find_cell(mouse_event.pos).map(|cell| cell.rect);
+                if hot_cell != self.hot_cell {
+                    self.hot_cell = hot_cell;
+                    ctx.request_paint();
+                }
+            },
+            Event::MouseDown(mouse_event) => {
+                if let Some(cell) = self.find_cell(mouse_event.pos) {
+                    let mut text = data.text_edit.to_string();
+                    text.push_str(cell.emoji);
+                    data.text_edit = sync::Arc::new(text);
+                    emoji::push_recent(&mut data.recent_emoji, cell.emoji);
+                    ctx.set_handled();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &AppState, _env: &Env) {
+        if let LifeCycle::HotChanged(false) = event {
+            self.hot_cell = None;
+            ctx.request_paint();
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, _env: &Env) {
+        if !old_data.emoji_search.same(&data.emoji_search) || !old_data.recent_emoji.same(&data.recent_emoji) {
+            self.hot_cell = None;
+            ctx.request_layout();
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, env: &Env) -> Size {
+        let width = bc.max().width;
+        let columns = ((width / CELL_SIZE).floor() as usize).max(1);
+        let mut y = 0.0;
+        self.sections.clear();
+        for (title, emoji_list) in Self::get_section_contents(data) {
+            let mut title_layout = TextLayout::<ArcStr>::from_text(title);
+            title_layout.set_text_size(TITLE_FONT_SIZE);
+            title_layout.set_text_color(druid::theme::TEXT_COLOR);
+            title_layout.rebuild_if_needed(ctx.text(), env);
+            let title_origin = Point::new(TITLE_SPACING, y + TITLE_SPACING);
+            y += title_layout.size().height + TITLE_SPACING * 2.0;
+
+            let mut cells = Vec::new();
+            for (index, emoji) in emoji_list.iter().enumerate() {
+                let mut layout = TextLayout::<ArcStr>::from_text(*emoji);
+                layout.set_text_size(EMOJI_FONT_SIZE);
+                layout.rebuild_if_needed(ctx.text(), env);
+                let column = index % columns;
+                let row = index / columns;
+                let origin = Point::new(column as f64 * CELL_SIZE, y + row as f64 * CELL_SIZE);
+                cells.push(EmojiCell {
+                    emoji,
+                    layout,
+                    rect: druid::Rect::from_origin_size(origin, (CELL_SIZE, CELL_SIZE)),
+                });
+            }
+            let rows = (emoji_list.len() as f64 / columns as f64).ceil();
+            y += rows * CELL_SIZE;
+            self.sections.push(EmojiSection {
+                title: title_layout,
+                title_origin,
+                cells,
+            });
+        }
+        bc.constrain(Size::new(width, y))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &AppState, env: &Env) {
+        if let Some(hot_cell) = self.hot_cell {
+            ctx.fill(hot_cell.to_rounded_rect(4.0), &env.get(crate::helper::theme::HOT_BUTTON_COLOR_KEY));
+        }
+        for section in &self.sections {
+            section.title.draw(ctx, section.title_origin);
+            for cell in &section.cells {
+                let emoji_size = cell.layout.size();
+                let emoji_origin = Point::new(
+                    cell.rect.x0 + (CELL_SIZE - emoji_size.width) / 2.0,
+                    cell.rect.y0 + (CELL_SIZE - emoji_size.height) / 2.0,
+                );
+                cell.layout.draw(ctx, emoji_origin);
+            }
+        }
+    }
+}
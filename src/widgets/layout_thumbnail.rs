@@ -0,0 +1,120 @@
+use std::sync;
+use druid::widget::prelude::*;
+use druid::{im, widget, Affine, ImageBuf, Point, Rect, WidgetExt, WidgetPod};
+use crate::{Message, MessageGroup, MessageKind};
+use crate::helper::layout_settings::LayoutSettings;
+use crate::widgets::timeline_item_widget::TimelineItemWidget;
+
+/// The size a thumbnail is shown at
+const THUMBNAIL_SIZE: Size = Size::new(110.0, 66.0);
+/// The width the sample is laid out at, before it is scaled down to the thumbnail
+const SAMPLE_WIDTH: f64 = 300.0;
+const SAMPLE_PADDING: f64 = 5.0;
+/// The sample is shown as this user, so both sides of the conversation are in it
+const SAMPLE_SELF_USER_ID: u32 = 0;
+const SAMPLE_OTHER_USER_ID: u32 = 1;
+/// A fixed time, so the thumbnails don't change from one day to the next
+const SAMPLE_TIMESTAMP: i64 = 1_600_000_000;
+
+type SampleTimeline = im::Vector<MessageGroup>;
+
+/// A small picture of what a layout looks like, shown on the predefined layout buttons.
+///
+/// It is a short sample conversation in the same timeline items as the chat window,
+/// laid out at a normal width with the layout's settings, then scaled down.
+/// It can't be interacted with.
+pub struct LayoutThumbnail {
+    settings: sync::Arc<LayoutSettings>,
+    sample: SampleTimeline,
+    timeline: WidgetPod<SampleTimeline, Box<dyn Widget<SampleTimeline>>>,
+}
+
+impl LayoutThumbnail {
+    /// The pictures are the profile pictures of the other user, then of the self user.
+    pub fn new(settings: LayoutSettings, pictures: [ImageBuf; 2]) -> Self {
+        let timeline = widget::List::new(TimelineItemWidget::new)
+            .with_spacing(crate::GROUP_SPACING_KEY)
+            .padding(SAMPLE_PADDING)
+            .boxed();
+        LayoutThumbnail {
+            settings: sync::Arc::new(settings),
+            sample: build_sample_timeline(pictures),
+            timeline: WidgetPod::new(timeline),
+        }
+    }
+
+    /// Gives the timeline the layout's settings, the same way the chat window does
+    fn thumbnail_env(&self, env: &Env) -> Env {
+        let mut thumbnail_env = env.clone();
+        LayoutSettings::set_env(&self.settings, &mut thumbnail_env);
+        thumbnail_env.set(crate::SELF_USER_ID_KEY, SAMPLE_SELF_USER_ID as u64);
+        thumbnail_env.set(crate::SELF_DISPLAY_NAME_KEY, "");
+        thumbnail_env.set(crate::SELECTED_MESSAGE_KEY, crate::NO_SELECTED_MESSAGE);
+        thumbnail_env
+    }
+}
+
+impl<T: Data> Widget<T> for LayoutThumbnail {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, env: &Env) {
+        match event {
+            // The clicks are for the button the thumbnail is on
+            Event::MouseDown(_) | Event::MouseUp(_) | Event::MouseMove(_) | Event::Wheel(_)
+            | Event::KeyDown(_) | Event::KeyUp(_) | Event::Paste(_) => {},
+            _ => {
+                let thumbnail_env = self.thumbnail_env(env);
+                self.timeline.event(ctx, event, &mut self.sample, &thumbnail_env);
+            },
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, env: &Env) {
+        self.timeline.lifecycle(ctx, event, &self.sample, &self.thumbnail_env(env));
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, _data: &T, env: &Env) {
+        // Only the theme can change, and the timeline items follow it through the env
+        self.timeline.update(ctx, &self.sample, &self.thumbnail_env(env));
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, env: &Env) -> Size {
+        let sample_bc = BoxConstraints::new(Size::new(SAMPLE_WIDTH, 0.0), Size::new(SAMPLE_WIDTH, f64::INFINITY));
+        self.timeline.layout(ctx, &sample_bc, &self.sample, &self.thumbnail_env(env));
+        self.timeline.set_origin(ctx, Point::ORIGIN);
+        bc.constrain(THUMBNAIL_SIZE)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
+        let rect = ctx.size().to_rect();
+        let scale = rect.width() / SAMPLE_WIDTH;
+        let thumbnail_env = self.thumbnail_env(env);
+        ctx.fill(rect, &env.get(druid::theme::WINDOW_BACKGROUND_COLOR));
+        ctx.with_save(|ctx| {
+            ctx.clip(rect);
+            ctx.transform(Affine::scale(scale));
+            // The region to paint is the whole thumbnail, in the sample's coordinates
+            let sample_region = Rect::from_origin_size(Point::ORIGIN, rect.size() / scale);
+            ctx.with_child_ctx(sample_region, |ctx| self.timeline.paint(ctx, &self.sample, &thumbnail_env));
+        });
+        ctx.stroke(rect, &env.get(druid::theme::BORDER_DARK), 1.0);
+    }
+}
+
+/// A two message group from the other user, then a reply
+fn build_sample_timeline(pictures: [ImageBuf; 2]) -> SampleTimeline {
+    let [other_picture, self_picture] = pictures;
+    let mut next_id = 0;
+    let mut group = |user_id: u32, profile_pic: ImageBuf, timestamp: i64, texts: &[&str]| {
+        let messages = texts.iter().enumerate().map(|(i, text)| {
+            let mut message = Message::new(text.to_string(), timestamp + i as i64 * 20, MessageKind::Text);
+            message.id = next_id;
+            message.position_in_group = i as u32;
+            next_id += 1;
+            message
+        }).collect();
+        MessageGroup { user_id, profile_pic, messages }
+    };
+    im::vector![
+        group(SAMPLE_OTHER_USER_ID, other_picture, SAMPLE_TIMESTAMP, &["Have you seen the new layouts?", "There are a lot"]),
+        group(SAMPLE_SELF_USER_ID, self_picture, SAMPLE_TIMESTAMP + 60, &["Yes, they look great"]),
+    ]
+}
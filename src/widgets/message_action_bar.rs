@@ -0,0 +1,135 @@
+use druid::widget::prelude::*;
+use druid::{ArcStr, Point, Rect, Selector, TextLayout};
+use crate::Message;
+use crate::helper::{message_actions, theme};
+
+/// Sent by a message when the pointer moves onto it, so its group can show the action bar.
+/// The payload is the message ID and the area of the message in window coordinates,
+/// which the group turns into its own coordinates when it gets it.
+pub const MESSAGE_HOVERED: Selector<(u64, Rect)> = Selector::new("polysoft.druid-demo.message_hovered");
+
+/// How many of the quick reactions get their own button
+const REACTION_BUTTON_COUNT: usize = 3;
+const BUTTON_SIZE: f64 = 26.0;
+const ICON_FONT_SIZE: f64 = 15.0;
+const BAR_PADDING: f64 = 2.0;
+const BAR_RADIUS: f64 = 6.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ActionBarButton {
+    React(&'static str),
+    Reply,
+    /// Opens the same menu as right-clicking
+    More,
+}
+
+impl ActionBarButton {
+    fn icon(&self) -> &'static str {
+        match self {
+            ActionBarButton::React(emoji) => emoji,
+            ActionBarButton::Reply => "↩",
+            ActionBarButton::More => "⋯",
+        }
+    }
+}
+
+/// The small toolbar shown over the hovered message, with quick reactions, reply, and more actions.
+///
+/// It is not a widget, since it floats over the messages of a group without taking any space.
+/// The group positions it, forwards the pointer events to it, and paints it last.
+pub struct MessageActionBar {
+    buttons: Vec<(ActionBarButton, TextLayout<ArcStr>)>,
+    hot_button: Option<usize>,
+}
+
+impl MessageActionBar {
+    pub fn new() -> Self {
+        let mut buttons: Vec<ActionBarButton> = message_actions::QUICK_REACTIONS.iter()
+            .take(REACTION_BUTTON_COUNT)
+            .map(|emoji| ActionBarButton::React(emoji))
+            .collect();
+        buttons.push(ActionBarButton::Reply);
+        buttons.push(ActionBarButton::More);
+        MessageActionBar {
+            buttons: buttons.into_iter().map(|button| {
+                let mut layout = TextLayout::<ArcStr>::from_text(button.icon());
+                layout.set_text_size(ICON_FONT_SIZE);
+                layout.set_text_color(druid::theme::TEXT_COLOR);
+                (button, layout)
+            }).collect(),
+            hot_button: None,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(
+            self.buttons.len() as f64 * BUTTON_SIZE + BAR_PADDING * 2.0,
+            BUTTON_SIZE + BAR_PADDING * 2.0
+        )
+    }
+
+    /// The area of a button, relative to the top left of the bar
+    fn get_button_rect(&self, index: usize) -> Rect {
+        Rect::from_origin_size(
+            Point::new(BAR_PADDING + index as f64 * BUTTON_SIZE, BAR_PADDING),
+            (BUTTON_SIZE, BUTTON_SIZE)
+        )
+    }
+
+    /// Gets the index of the button at the position, which is relative to the top left of the bar.
+    fn find_button(&self, pos: Point) -> Option<usize> {
+        (0..self.buttons.len()).find(|index| self.get_button_rect(*index).contains(pos))
+    }
+
+    /// Updates the highlighted button. Returns true if it changed, so it needs to be repainted.
+    pub fn set_hot_position(&mut self, pos: Option<Point>) -> bool {
+        let hot_button = pos.and_then(|pos| self.find_button(pos));
+        let changed = hot_button != self.hot_button;
+        self.hot_button = hot_button;
+        changed
+    }
+
+    /// Runs the action of the button at the position, if there is one there.
+    /// Returns true if a button was clicked.
+    pub fn click(&self, ctx: &mut EventCtx, pos: Point, window_pos: Point, message: &Message, is_own_message: bool) -> bool {
+        let button = match self.find_button(pos) {
+            Some(index) => self.buttons[index].0,
+            None => return false,
+        };
+        match button {
+            ActionBarButton::React(emoji) => {
+                ctx.submit_command(message_actions::REACT_TO_MESSAGE.with((message.id, emoji.to_string())));
+            },
+            ActionBarButton::Reply => {
+                ctx.submit_command(message_actions::REPLY_TO_MESSAGE.with(message.id));
+            },
+            ActionBarButton::More => {
+                ctx.show_context_menu(message_actions::build_context_menu(message, is_own_message), window_pos);
+            },
+        }
+        true
+    }
+
+    pub fn rebuild_if_needed(&mut self, ctx: &mut LayoutCtx, env: &Env) {
+        for (_, layout) in self.buttons.iter_mut() {
+            layout.rebuild_if_needed(ctx.text(), env);
+        }
+    }
+
+    pub fn paint(&self, ctx: &mut PaintCtx, origin: Point, env: &Env) {
+        let bar_rect = Rect::from_origin_size(origin, self.size()).to_rounded_rect(BAR_RADIUS);
+        ctx.fill(bar_rect, &env.get(theme::ACTION_BAR_COLOR_KEY));
+        ctx.stroke(bar_rect, &env.get(theme::ACTION_BAR_BORDER_COLOR_KEY), 1.0);
+        for (index, (_, layout)) in self.buttons.iter().enumerate() {
+            let button_rect = self.get_button_rect(index) + origin.to_vec2();
+            if self.hot_button == Some(index) {
+                ctx.fill(button_rect.to_rounded_rect(4.0), &env.get(theme::HOT_BUTTON_COLOR_KEY));
+            }
+            let icon_size = layout.size();
+            layout.draw(ctx, Point::new(
+                button_rect.x0 + (BUTTON_SIZE - icon_size.width) / 2.0,
+                button_rect.y0 + (BUTTON_SIZE - icon_size.height) / 2.0,
+            ));
+        }
+    }
+}
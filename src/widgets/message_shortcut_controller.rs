@@ -0,0 +1,29 @@
+use druid::widget::{Controller, prelude::*};
+use crate::AppState;
+use crate::helper::message_actions;
+
+/// Runs the keyboard shortcuts for the selected message.
+///
+/// Clicking the timeline gives it focus, so the shortcuts work until something else, like the composer,
+/// is clicked. Key events only reach the widget with focus, so typing is never taken over.
+pub struct MessageShortcutController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for MessageShortcutController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::MouseDown(_) => {
+                // Clicked selectable text takes the focus from here, but the keys still pass through on the way to it
+                ctx.request_focus();
+            },
+            Event::KeyDown(key_event) => {
+                if let Some(command) = message_actions::get_shortcut_command(key_event, data) {
+                    ctx.submit_command(command);
+                    ctx.set_handled();
+                    return;
+                }
+            },
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+}
@@ -1,3 +1,19 @@
 pub mod timeline_item_widget;
 pub mod single_message_widget;
-pub mod chat_window_widget;
\ No newline at end of file
+pub mod chat_window_widget;
+pub mod composer_controller;
+pub mod emoji_picker_widget;
+pub mod selectable_text_widget;
+pub mod message_action_bar;
+pub mod settings_save_controller;
+pub mod color_picker;
+pub mod timeline_preview;
+pub mod layout_thumbnail;
+pub mod settings_history_controller;
+pub mod search_filter;
+pub mod responsive_columns;
+pub mod audit_widget;
+pub mod replay_controller;
+pub mod performance_overlay;
+pub mod message_shortcut_controller;
+pub mod benchmark_widget;
\ No newline at end of file
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use druid::widget::prelude::*;
+use druid::{ArcStr, Color, FontDescriptor, FontFamily, Point, Rect, TextLayout};
+use crate::AppState;
+use crate::helper::helper_functions;
+
+/// Frames in this window are counted for the frames per second and the slowest frame
+const FRAME_WINDOW: Duration = Duration::from_secs(1);
+/// Reading the memory use is a file read, so it isn't done on every frame
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+const MARGIN: f64 = 6.0;
+const PADDING: f64 = 4.0;
+const FONT_SIZE: f64 = 11.0;
+
+/// Wraps the root of the chat window to show the frame time and memory use over its top right corner.
+///
+/// The frame time is how long the window took to lay out and paint. Nothing is redrawn just for the overlay,
+/// so it only changes when the window paints anyway, which is all the time while messages are flooding in.
+pub struct PerformanceOverlay<W> {
+    inner: W,
+    text: TextLayout<ArcStr>,
+    /// How long the layout of the frame being drawn took. Zero when only painting.
+    layout_duration: Duration,
+    /// When each recent frame was drawn, and how long it took
+    frames: VecDeque<(Instant, Duration)>,
+    memory_kb: Option<u64>,
+    last_memory_sample: Option<Instant>,
+}
+
+impl<W> PerformanceOverlay<W> {
+    pub fn new(inner: W) -> Self {
+        let mut text = TextLayout::new();
+        text.set_font(FontDescriptor::new(FontFamily::MONOSPACE).with_size(FONT_SIZE));
+        text.set_text_color(Color::WHITE);
+        PerformanceOverlay {
+            inner,
+            text,
+            layout_duration: Duration::ZERO,
+            frames: VecDeque::new(),
+            memory_kb: None,
+            last_memory_sample: None,
+        }
+    }
+
+    fn record_frame(&mut self, now: Instant, frame_duration: Duration) {
+        self.frames.push_back((now, frame_duration));
+        while matches!(self.frames.front(), Some((time, _)) if now - *time > FRAME_WINDOW) {
+            self.frames.pop_front();
+        }
+        if self.last_memory_sample.is_none_or(|sample| now - sample >= MEMORY_SAMPLE_INTERVAL) {
+            self.memory_kb = helper_functions::get_process_memory_kb("VmRSS");
+            self.last_memory_sample = Some(now);
+        }
+    }
+
+    fn get_text(&self, frame_duration: Duration, data: &AppState) -> String {
+        let slowest = self.frames.iter().map(|(_, duration)| *duration).max().unwrap_or_default();
+        let memory = match self.memory_kb {
+            Some(kb) => format!("{:.1} MB", kb as f64 / 1024.0),
+            None => "unknown".to_string(),
+        };
+        format!("frame {:.1} ms, slowest {:.1} ms, {} fps\nmemory {}\n{} messages",
+            frame_duration.as_secs_f64() * 1000.0,
+            slowest.as_secs_f64() * 1000.0,
+            self.frames.len(),
+            memory,
+            data.next_message_id)
+    }
+}
+
+impl<W: Widget<AppState>> Widget<AppState> for PerformanceOverlay<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        self.inner.update(ctx, old_data, data, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, env: &Env) -> Size {
+        let start = Instant::now();
+        let size = self.inner.layout(ctx, bc, data, env);
+        self.layout_duration = start.elapsed();
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        let start = Instant::now();
+        self.inner.paint(ctx, data, env);
+        let now = Instant::now();
+        let frame_duration = self.layout_duration + (now - start);
+        self.layout_duration = Duration::ZERO;
+        self.record_frame(now, frame_duration);
+
+        self.text.set_text(self.get_text(frame_duration, data).into());
+        self.text.rebuild_if_needed(ctx.text(), env);
+        let text_size = self.text.size();
+        let origin = Point::new(ctx.size().width - text_size.width - MARGIN - PADDING, MARGIN + PADDING);
+        let background = Rect::from_origin_size(origin, text_size).inset(PADDING);
+        ctx.fill(background.to_rounded_rect(4.0), &Color::rgba8(0, 0, 0, 180));
+        self.text.draw(ctx, origin);
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Instant;
+use druid::widget::{Controller, prelude::*};
+use druid::TimerToken;
+use crate::AppState;
+use crate::helper::replay::{Replay, CONTROL_REPLAY};
+
+/// Plays a transcript into the timeline while the chat window is open.
+///
+/// The timer is set for the next event rather than ticking, so nothing runs between events,
+/// or at all while the replay is paused or finished.
+pub struct ReplayController {
+    replay: Replay,
+    timer_token: TimerToken,
+    /// When the replay last moved forward, to know how much real time has passed
+    last_advance: Instant,
+}
+
+impl ReplayController {
+    pub fn new(replay: Replay) -> Self {
+        ReplayController {
+            replay,
+            timer_token: TimerToken::INVALID,
+            last_advance: Instant::now(),
+        }
+    }
+
+    fn advance(&mut self, data: &mut AppState) {
+        let now = Instant::now();
+        self.replay.advance(data, now - self.last_advance);
+        self.last_advance = now;
+    }
+
+    /// Replacing the token makes a timer that is already running be ignored
+    fn schedule(&mut self, ctx: &mut EventCtx) {
+        self.timer_token = match self.replay.get_time_until_next_change() {
+            Some(delay) => ctx.request_timer(delay),
+            None => TimerToken::INVALID,
+        };
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ReplayController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::WindowConnected => {
+                // The transcript starts when the window is shown
+                self.last_advance = Instant::now();
+                self.advance(data);
+                self.schedule(ctx);
+            },
+            Event::Timer(token) if *token == self.timer_token => {
+                self.advance(data);
+                self.schedule(ctx);
+                ctx.set_handled();
+                return;
+            },
+            Event::Command(cmd) if cmd.is(CONTROL_REPLAY) => {
+                // Plays what was due before the change, so a speed change only affects what comes after it
+                self.advance(data);
+                self.replay.control(data, *cmd.get_unchecked(CONTROL_REPLAY));
+                self.last_advance = Instant::now();
+                self.schedule(ctx);
+                ctx.set_handled();
+                return;
+            },
+            _ => (),
+        }
+        child.event(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,89 @@
+use druid::widget::prelude::*;
+use druid::{Point, WidgetPod};
+
+/// Lays out its children in as many columns as fit in the width, one column when narrow.
+///
+/// Each child goes at the bottom of the shortest column, so the columns end up about as tall as each other.
+/// Children with no height, like the ones hidden by a search, are skipped.
+pub struct ResponsiveColumns<T> {
+    children: Vec<WidgetPod<T, Box<dyn Widget<T>>>>,
+    min_column_width: f64,
+    spacing: f64,
+}
+
+impl<T: Data> ResponsiveColumns<T> {
+    pub fn new(min_column_width: f64) -> Self {
+        ResponsiveColumns {
+            children: Vec::new(),
+            min_column_width,
+            spacing: 20.0,
+        }
+    }
+
+    pub fn with_child(mut self, child: impl Widget<T> + 'static) -> Self {
+        self.children.push(WidgetPod::new(child).boxed());
+        self
+    }
+
+    /// How many columns fit in the width, never more than there are children
+    fn get_column_count(&self, width: f64) -> usize {
+        let fitting = ((width + self.spacing) / (self.min_column_width + self.spacing)).floor() as usize;
+        fitting.clamp(1, self.children.len().max(1))
+    }
+}
+
+impl<T: Data> Widget<T> for ResponsiveColumns<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        for child in self.children.iter_mut() {
+            child.event(ctx, event, data, env);
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        for child in self.children.iter_mut() {
+            child.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        for child in self.children.iter_mut() {
+            child.update(ctx, data, env);
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let width = if bc.is_width_bounded() {
+            bc.max().width
+        } else {
+            self.min_column_width * self.children.len() as f64
+        };
+        let column_count = self.get_column_count(width);
+        let column_width = ((width - self.spacing * (column_count - 1) as f64) / column_count as f64).max(0.0);
+        let child_bc = BoxConstraints::new(
+            Size::new(column_width, 0.0),
+            Size::new(column_width, f64::INFINITY)
+        );
+
+        let mut column_heights = vec![0.0; column_count];
+        for child in self.children.iter_mut() {
+            let size = child.layout(ctx, &child_bc, data, env);
+            if size.height <= 0.0 {
+                child.set_origin(ctx, Point::ORIGIN);
+                continue;
+            }
+            let (column, height) = column_heights.iter().cloned().enumerate()
+                .fold((0, f64::INFINITY), |shortest, (i, height)| if height < shortest.1 { (i, height) } else { shortest });
+            let y = if height > 0.0 { height + self.spacing } else { 0.0 };
+            child.set_origin(ctx, Point::new(column as f64 * (column_width + self.spacing), y));
+            column_heights[column] = y + size.height;
+        }
+        let height = column_heights.iter().cloned().fold(0.0, f64::max);
+        bc.constrain(Size::new(width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        for child in self.children.iter_mut() {
+            child.paint(ctx, data, env);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use druid::widget::prelude::*;
+use druid::{ArcStr, Point, WidgetPod};
+
+/// The text typed in the search box of the settings window
+pub const SEARCH_KEY: druid::env::Key<ArcStr> = druid::env::Key::new("polysoft.druid-demo.settings_search");
+
+/// Hides its child when it doesn't match the search in the environment.
+///
+/// A hidden child takes no space, so the controls after it move up into its place.
+pub struct SearchFilter<T> {
+    child: WidgetPod<T, Box<dyn Widget<T>>>,
+    /// Lowercase, so the search doesn't depend on case
+    keywords: String,
+    is_shown: bool,
+}
+
+impl<T: Data> SearchFilter<T> {
+    /// The keywords are the words that find the child, usually its label and what it changes
+    pub fn new(child: impl Widget<T> + 'static, keywords: &str) -> Self {
+        SearchFilter {
+            child: WidgetPod::new(child).boxed(),
+            keywords: keywords.to_lowercase(),
+            is_shown: true,
+        }
+    }
+}
+
+/// Every word of the search has to be somewhere in the keywords. An empty search matches everything.
+pub fn matches_search(keywords: &str, search: &str) -> bool {
+    let keywords = keywords.to_lowercase();
+    search.split_whitespace().all(|word| keywords.contains(&word.to_lowercase()))
+}
+
+impl<T: Data> Widget<T> for SearchFilter<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if self.is_shown {
+            self.child.event(ctx, event, data, env);
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.is_shown = matches_search(&self.keywords, &env.get(SEARCH_KEY));
+        }
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        if ctx.env_changed() {
+            let is_shown = matches_search(&self.keywords, &env.get(SEARCH_KEY));
+            if is_shown != self.is_shown {
+                self.is_shown = is_shown;
+                ctx.request_layout();
+            }
+        }
+        self.child.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        // The child is always laid out, so it is ready to be shown again when the search changes
+        let size = self.child.layout(ctx, bc, data, env);
+        self.child.set_origin(ctx, Point::ORIGIN);
+        if self.is_shown {
+            size
+        } else {
+            bc.min()
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        if self.is_shown {
+            self.child.paint(ctx, data, env);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches_search;
+
+    #[test]
+    fn every_word_has_to_match_in_any_case() {
+        let keywords = "IRC-specific Header Width";
+        assert!(matches_search(keywords, ""));
+        assert!(matches_search(keywords, "irc"));
+        assert!(matches_search(keywords, "  Width   irc "));
+        assert!(!matches_search(keywords, "irc font"));
+    }
+}
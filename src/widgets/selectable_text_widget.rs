@@ -0,0 +1,137 @@
+use druid::widget::prelude::*;
+use druid::{ArcStr, Color, Cursor, FontDescriptor, HotKey, KeyOrValue, Point, SysMods, TextLayout};
+use crate::Message;
+use crate::helper::text_selection::{self, SelectionPoint, TextSelection};
+use super::single_message_widget;
+
+/// Same as the padding of druid's label, so the text lines up with the other labels
+const TEXT_X_PADDING: f64 = 2.0;
+
+/// Shows the content of a message, and lets the user select it.
+///
+/// Dragging selects text, and continues into other messages when the pointer moves over them.
+/// A double click selects a word, and a triple click selects the whole message.
+/// The selection itself is kept in the app state, and every message reads it from the env.
+pub struct SelectableTextWidget {
+    layout: TextLayout<ArcStr>,
+}
+
+impl SelectableTextWidget {
+    pub fn new() -> Self {
+        let mut layout = TextLayout::new();
+        layout.set_text_color(druid::theme::TEXT_COLOR);
+        SelectableTextWidget {
+            layout,
+        }
+    }
+
+    pub fn set_font(&mut self, font: impl Into<KeyOrValue<FontDescriptor>>) {
+        self.layout.set_font(font);
+    }
+
+    pub fn set_text_size(&mut self, size: impl Into<KeyOrValue<f64>>) {
+        self.layout.set_text_size(size);
+    }
+
+    pub fn set_text_color(&mut self, color: impl Into<KeyOrValue<Color>>) {
+        self.layout.set_text_color(color);
+    }
+
+    fn text(&self) -> &str {
+        self.layout.text().map_or("", |text| text.as_ref())
+    }
+
+    fn get_offset(&self, pos: Point) -> usize {
+        self.layout.text_position_for_point(pos - druid::Vec2::new(TEXT_X_PADDING, 0.0))
+    }
+
+    fn update_text(&mut self, data: &Message, env: &Env) -> bool {
+        let text = single_message_widget::get_display_text(data, env);
+        if text != self.text() {
+            self.layout.set_text(text.into());
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Widget<Message> for SelectableTextWidget {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Message, env: &Env) {
+        match event {
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                let offset = self.get_offset(mouse_event.pos);
+                let point = |offset| SelectionPoint { message_id: data.id, offset };
+                let selection = match mouse_event.count {
+                    2 => {
+                        let range = text_selection::word_range_at(self.text(), offset);
+                        TextSelection::new(point(range.start), point(range.end))
+                    },
+                    3 => TextSelection::new(point(0), point(self.text().len())),
+                    _ => TextSelection::new(point(offset), point(offset)),
+                };
+                if mouse_event.mods.shift() && mouse_event.count == 1 && env.get(crate::TEXT_SELECTION_KEY).is_some() {
+                    ctx.submit_command(text_selection::EXTEND_TEXT_SELECTION.with(point(offset)));
+                } else {
+                    ctx.submit_command(text_selection::SET_TEXT_SELECTION.with((selection, mouse_event.count)));
+                }
+                // Takes focus so the copy and select all shortcuts come here
+                ctx.request_focus();
+                ctx.set_handled();
+            },
+            Event::MouseMove(mouse_event) => {
+                ctx.set_cursor(&Cursor::IBeam);
+                // The pointer can be dragged over other messages than the one that was clicked,
+                // so the app state decides whether a drag moves the selection, from the click count of the mouse down
+                if mouse_event.buttons.has_left() {
+                    let focus = SelectionPoint { message_id: data.id, offset: self.get_offset(mouse_event.pos) };
+                    ctx.submit_command(text_selection::DRAG_TEXT_SELECTION.with(focus));
+                }
+            },
+            Event::KeyDown(key_event) => {
+                if HotKey::new(SysMods::Cmd, "c").matches(key_event) {
+                    ctx.submit_command(text_selection::COPY_TEXT_SELECTION);
+                    ctx.set_handled();
+                } else if HotKey::new(SysMods::Cmd, "a").matches(key_event) {
+                    ctx.submit_command(text_selection::SELECT_ALL_TEXT);
+                    ctx.set_handled();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Message, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_text(data, env);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &Message, data: &Message, env: &Env) {
+        let text_changed = self.update_text(data, env);
+        if text_changed || self.layout.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        } else if ctx.env_key_changed(&crate::TEXT_SELECTION_KEY) {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &Message, env: &Env) -> Size {
+        self.layout.set_wrap_width(bc.max().width - TEXT_X_PADDING * 2.0);
+        self.layout.rebuild_if_needed(ctx.text(), env);
+        let text_size = self.layout.size();
+        bc.constrain(Size::new(text_size.width + TEXT_X_PADDING * 2.0, text_size.height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Message, env: &Env) {
+        if let Some(selection) = env.get(crate::TEXT_SELECTION_KEY).as_ref() {
+            if let Some(range) = selection.range_for_message(data.id, self.text()) {
+                let selection_color = env.get(druid::theme::SELECTED_TEXT_BACKGROUND_COLOR);
+                for rect in self.layout.rects_for_range(range) {
+                    ctx.fill(rect + druid::Vec2::new(TEXT_X_PADDING, 0.0), &selection_color);
+                }
+            }
+        }
+        self.layout.draw(ctx, Point::new(TEXT_X_PADDING, 0.0));
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync;
+use std::time::Duration;
+use druid::widget::{Controller, prelude::*};
+use druid::{HotKey, KeyEvent, Selector, SysMods, TimerToken};
+use crate::AppState;
+use crate::helper::layout_settings::LayoutSettings;
+
+pub const UNDO_SETTINGS_CHANGE: Selector = Selector::new("polysoft.druid-demo.undo_settings_change");
+pub const REDO_SETTINGS_CHANGE: Selector = Selector::new("polysoft.druid-demo.redo_settings_change");
+
+/// Changes closer together than this are undone as one step, so dragging a slider is a single change
+const GROUP_DELAY: Duration = Duration::from_millis(500);
+/// The oldest changes are forgotten after this many
+const MAX_HISTORY: usize = 100;
+
+/// Keeps the history of the layout settings, so changes made in the settings window can be undone and redone.
+///
+/// Changes from anywhere are recorded, including presets and the config file being edited.
+///
+/// It also runs the undo and redo shortcuts. The window gets focus when it opens or is clicked,
+/// and a text box that is clicked takes it, so the shortcuts never take over undoing typing.
+pub struct SettingsHistoryController {
+    /// The settings before each change, most recent last
+    undo_stack: Vec<sync::Arc<LayoutSettings>>,
+    redo_stack: Vec<sync::Arc<LayoutSettings>>,
+    /// Running while changes are being grouped into the last step
+    group_timer_token: TimerToken,
+    /// Set when the next change is the one made by undoing or redoing, so it isn't recorded
+    restoring: bool,
+}
+
+impl SettingsHistoryController {
+    pub fn new() -> Self {
+        SettingsHistoryController {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            group_timer_token: TimerToken::INVALID,
+            restoring: false,
+        }
+    }
+
+    /// Takes the settings from one stack, keeping the current ones on the other
+    fn restore(&mut self, data: &mut AppState, is_redo: bool) {
+        let (from, to) = if is_redo {
+            (&mut self.redo_stack, &mut self.undo_stack)
+        } else {
+            (&mut self.undo_stack, &mut self.redo_stack)
+        };
+        let settings = match from.pop() {
+            Some(settings) => settings,
+            None => return,
+        };
+        to.push(std::mem::replace(&mut data.layout_settings, settings));
+        self.restoring = !data.layout_settings.same(to.last().unwrap());
+        self.group_timer_token = TimerToken::INVALID;
+    }
+
+    fn undo_or_redo(&mut self, ctx: &mut EventCtx, data: &mut AppState, is_redo: bool) {
+        self.restore(data, is_redo);
+        ctx.set_handled();
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SettingsHistoryController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::Timer(token) if *token == self.group_timer_token => {
+                self.group_timer_token = TimerToken::INVALID;
+                ctx.set_handled();
+                return;
+            },
+            Event::Command(cmd) if cmd.is(UNDO_SETTINGS_CHANGE) || cmd.is(REDO_SETTINGS_CHANGE) => {
+                self.undo_or_redo(ctx, data, cmd.is(REDO_SETTINGS_CHANGE));
+                return;
+            },
+            Event::WindowConnected | Event::MouseDown(_) => {
+                // A clicked text box takes the focus from here
+                ctx.request_focus();
+            },
+            // Only while no text box has focus
+            Event::KeyDown(key_event) if ctx.is_focused() => {
+                if let Some(is_redo) = get_shortcut(key_event) {
+                    self.undo_or_redo(ctx, data, is_redo);
+                    return;
+                }
+            },
+            _ => {},
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(&mut self, child: &mut W, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if !old_data.layout_settings.same(&data.layout_settings) {
+            if self.restoring {
+                self.restoring = false;
+            } else {
+                if self.group_timer_token == TimerToken::INVALID {
+                    if self.undo_stack.len() == MAX_HISTORY {
+                        self.undo_stack.remove(0);
+                    }
+                    self.undo_stack.push(old_data.layout_settings.clone());
+                    self.redo_stack.clear();
+                }
+                // Replacing the token makes the timer that is already running be ignored
+                self.group_timer_token = ctx.request_timer(GROUP_DELAY);
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+/// Gets whether the keys are the shortcut for redo or for undo, or none if they are neither
+fn get_shortcut(key_event: &KeyEvent) -> Option<bool> {
+    if HotKey::new(SysMods::Cmd, "z").matches(key_event) {
+        Some(false)
+    } else if HotKey::new(SysMods::CmdShift, "Z").matches(key_event) {
+        Some(true)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+use druid::widget::{Controller, prelude::*};
+use druid::TimerToken;
+use crate::AppState;
+use crate::helper::config;
+
+/// How long to wait after the last change before saving.
+/// Dragging a slider or a color changes the settings many times a second, so each change isn't saved right away.
+const SAVE_DELAY: Duration = Duration::from_millis(750);
+
+/// Saves the layout settings and the theme to the config file after they are changed.
+///
+/// Any change restarts the delay, so the file is only written once things settle down.
+/// Changes that are still waiting when the app quits are saved by the app delegate.
+pub struct SettingsSaveController {
+    save_timer_token: TimerToken,
+}
+
+impl SettingsSaveController {
+    pub fn new() -> Self {
+        SettingsSaveController {
+            save_timer_token: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SettingsSaveController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::Timer(token) = event {
+            if *token == self.save_timer_token {
+                self.save_timer_token = TimerToken::INVALID;
+                config::save_config(&data.layout_settings, &data.theme);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(&mut self, child: &mut W, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if !old_data.layout_settings.same(&data.layout_settings) || !old_data.theme.same(&data.theme) {
+            // Replacing the token makes the timer that is already running be ignored
+            self.save_timer_token = ctx.request_timer(SAVE_DELAY);
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -2,33 +2,71 @@ use druid::kurbo::Rect;
 use druid::{Widget, widget, WidgetPod};
 use druid::widget::prelude::*;
 use druid::Point;
-use crate::{Message};
-use druid::piet::Color;
+use crate::{Message, MessageKind};
 use crate::LayoutSettings;
 use crate::helper::helper_functions::{self, TimestampFormat};
+use crate::helper::{emoji, message_actions, theme};
+use super::selectable_text_widget::SelectableTextWidget;
+use super::message_action_bar;
+
+/// How much larger messages with only a few emoji are shown
+const JUMBOMOJI_SCALE: f64 = 2.5;
+/// Space between the content and the reply preview or the reactions
+const EXTRA_LABEL_SPACING: f64 = 3.0;
+
+/// Gets the text to show for a message, which depends on the kind of message.
+pub fn get_display_text(item: &Message, env: &Env) -> String {
+    let sender_id = env.get(crate::GROUP_USER_ID_KEY) as u32;
+    format_display_text(item, &helper_functions::get_display_name(sender_id, env))
+}
+
+/// Same as [get_display_text], but with the name of the sender given.
+pub fn format_display_text(item: &Message, sender_name: &str) -> String {
+    match item.kind {
+        MessageKind::Action => {
+            format!("* {} {}", sender_name, item.message)
+        },
+        MessageKind::Text | MessageKind::Notice => {
+            if item.edited {
+                format!("{} (edited)", item.message)
+            } else {
+                item.message.to_string()
+            }
+        },
+    }
+}
+
+/// Gets the text shown for the reactions, like "👍 2   ❤️ 1"
+pub fn get_reactions_text(item: &Message) -> String {
+    let reactions: Vec<String> = item.reactions.iter()
+        .map(|reaction| format!("{} {}", reaction.emoji, reaction.user_ids.len()))
+        .collect();
+    reactions.join("   ")
+}
+
+/// Returns true for regular messages that only have one to three emoji.
+pub fn is_jumbomoji(item: &Message) -> bool {
+    item.kind == MessageKind::Text && emoji::is_jumbomoji(&item.message)
+}
 
 /// A widget that shows a single message
 /// 
 /// It also handles timestamps, the settings menu, reactions, and more.
 pub struct SingleMessageWidget {
-    msg_content_label: WidgetPod<Message, widget::Label<Message>>,
+    msg_content_label: WidgetPod<Message, SelectableTextWidget>,
     timestamp_label: WidgetPod<Message, widget::Label<Message>>,
+    reply_label: WidgetPod<Message, widget::Label<Message>>,
+    reactions_label: WidgetPod<Message, widget::Label<Message>>,
+    /// The window area last sent to the group while hovered, so it's only sent again after the message moved
+    sent_hover_rect: Option<Rect>,
 }
 
 impl SingleMessageWidget {
     pub fn new() -> Self {
-        let msg_content_label = WidgetPod::new(
-            widget::Label::new(|item: &Message, _env: &_| {
-                item.message.to_string()
-            })
-            .with_line_break_mode(widget::LineBreaking::WordWrap)
-            .with_text_size(crate::CONTENT_FONT_SIZE_KEY)
-        );
+        let msg_content_label = WidgetPod::new(SelectableTextWidget::new());
         let timestamp_label = WidgetPod::new(
             widget::Label::new(|item: &Message, env: &Env| {
-                let time_format: TimestampFormat = num_traits::FromPrimitive::from_u64(
-                    env.get::<u64>(crate::SIDE_TIME_FORMAT_KEY)
-                ).expect("Invalid side time format index");
+                let time_format: TimestampFormat = LayoutSettings::from_env(env).side_time_format;
 
                 helper_functions::timestamp_to_display_msg(
                     item.timestamp_epoch_seconds,
@@ -37,12 +75,32 @@ impl SingleMessageWidget {
             })
             .with_line_break_mode(widget::LineBreaking::Overflow)
             .with_text_size(crate::DATETIME_FONT_SIZE_KEY)
-            .with_text_color(crate::DATETIME_COLOR_KEY)
+            .with_text_color(theme::DATETIME_COLOR_KEY)
+        );
+        
+        let reply_label = WidgetPod::new(
+            widget::Label::new(|item: &Message, env: &Env| {
+                match &item.reply_to {
+                    Some(reply) => format!("↪ {}: {}", helper_functions::get_display_name(reply.user_id, env), reply.text),
+                    None => String::new(),
+                }
+            })
+            .with_line_break_mode(widget::LineBreaking::Clip)
+            .with_text_size(crate::DATETIME_FONT_SIZE_KEY)
+            .with_text_color(theme::DATETIME_COLOR_KEY)
+        );
+        let reactions_label = WidgetPod::new(
+            widget::Label::new(|item: &Message, _env: &Env| get_reactions_text(item))
+                .with_line_break_mode(widget::LineBreaking::WordWrap)
+                .with_text_size(crate::DATETIME_FONT_SIZE_KEY)
         );
         
         SingleMessageWidget {
-            msg_content_label: msg_content_label,
-            timestamp_label: timestamp_label
+            msg_content_label,
+            timestamp_label,
+            reply_label,
+            reactions_label,
+            sent_hover_rect: None,
         }
     }
 }
@@ -50,14 +108,24 @@ impl SingleMessageWidget {
 impl Widget<Message> for SingleMessageWidget {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Message, env: &Env) {
         match event {
-            Event::Command(cmd) if cmd.is(crate::REFRESH_UI_SELECTOR) => {
-                ctx.request_layout();
-                ctx.request_paint();
+            Event::MouseDown(mouse_event) if mouse_event.button.is_right() => {
+                let is_own_message = env.get(crate::GROUP_USER_ID_KEY) == env.get(crate::SELF_USER_ID_KEY);
+                ctx.show_context_menu(message_actions::build_context_menu(data, is_own_message), mouse_event.window_pos);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseMove(_) if ctx.is_hot() => {
+                // The message may have moved under the pointer, like after scrolling or when the message above changed
+                if let Some(window_rect) = self.get_hover_rect_to_send(data, ctx.window_origin()) {
+                    ctx.submit_command(message_action_bar::MESSAGE_HOVERED.with((data.id, window_rect)));
+                }
             }
             _ => {}
         }
         self.msg_content_label.event(ctx, event, data, env);
         self.timestamp_label.event(ctx, event, data, env);
+        self.reply_label.event(ctx, event, data, env);
+        self.reactions_label.event(ctx, event, data, env);
     }
 
     fn lifecycle(
@@ -68,19 +136,37 @@ impl Widget<Message> for SingleMessageWidget {
         env: &Env,
     ) {
         match event {
-            LifeCycle::HotChanged(_) => {
-                ctx.request_layout();
+            LifeCycle::HotChanged(is_hot) => {
+                if *is_hot {
+                    // Lets the group show the action bar over this message
+                    if let Some(window_rect) = self.get_hover_rect_to_send(data, ctx.window_origin()) {
+                        ctx.submit_command(message_action_bar::MESSAGE_HOVERED.with((data.id, window_rect)));
+                    }
+                } else {
+                    self.sent_hover_rect = None;
+                }
+                // Only the hover background and the side time change, so there's nothing to lay out
                 ctx.request_paint();
             },
             _ => {}
         }
         self.msg_content_label.lifecycle(ctx, event, data, env);
         self.timestamp_label.lifecycle(ctx, event, data, env);
+        self.reply_label.lifecycle(ctx, event, data, env);
+        self.reactions_label.lifecycle(ctx, event, data, env);
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &Message, data: &Message, env: &Env) {
+        if ctx.env_key_changed(&crate::SELECTED_MESSAGE_KEY) {
+            ctx.request_paint();
+        }
+        if ctx.env_key_changed(&crate::LAYOUT_SETTINGS_KEY) {
+            ctx.request_layout();
+        }
         self.msg_content_label.update(ctx, data, env);
         self.timestamp_label.update(ctx, data, env);
+        self.reply_label.update(ctx, data, env);
+        self.reactions_label.update(ctx, data, env);
     }
 
     fn layout(
@@ -91,16 +177,36 @@ impl Widget<Message> for SingleMessageWidget {
         env: &Env,
     ) -> Size {
         let settings = LayoutSettings::from_env(env);
+        self.set_content_style(data, env);
         // Now position the content label
         let msg_content_bc = helper_functions::to_full_height_area(
             bc.max().width - settings.left_spacing
         );
-        let msg_content_origin = Point::new(settings.left_spacing, 0.0);
-        let msg_size = self.msg_content_label.layout(layout_ctx, &msg_content_bc, data, env);
+        // The message being replied to goes above the content
+        let mut content_y = 0.0;
+        let mut width = 0.0f64;
+        if data.reply_to.is_some() {
+            let reply_size = self.reply_label.layout(layout_ctx, &msg_content_bc, data, env);
+            self.reply_label.set_origin(layout_ctx, Point::new(settings.left_spacing, 0.0));
+            content_y = reply_size.height + EXTRA_LABEL_SPACING;
+            width = width.max(reply_size.width);
+        }
+        let msg_content_origin = Point::new(settings.left_spacing, content_y);
+        let content_size = self.msg_content_label.layout(layout_ctx, &msg_content_bc, data, env);
         self.msg_content_label.set_origin(layout_ctx, msg_content_origin);
+        width = width.max(content_size.width);
+        let mut height = content_y + content_size.height;
+        // And the reactions below it
+        if !data.reactions.is_empty() {
+            let reactions_size = self.reactions_label.layout(layout_ctx, &msg_content_bc, data, env);
+            self.reactions_label.set_origin(layout_ctx, Point::new(settings.left_spacing, height + EXTRA_LABEL_SPACING));
+            height += EXTRA_LABEL_SPACING + reactions_size.height;
+            width = width.max(reactions_size.width);
+        }
+        let msg_size = Size::new(width, height);
         // Now position the timestamp label
         let timestamp_size = self.timestamp_label.layout(layout_ctx, &bc, data, env);
-        let timestamp_y = msg_size.height - timestamp_size.height;
+        let timestamp_y = content_y + content_size.height - timestamp_size.height;
         let mut timestamp_x = 0.0 - timestamp_size.width - settings.left_meta_offset - settings.left_spacing;
         timestamp_x -= settings.bubble_padding;
         let timestamp_origin = Point::new(timestamp_x, timestamp_y);
@@ -115,12 +221,21 @@ impl Widget<Message> for SingleMessageWidget {
         if ctx.is_hot() {
             ctx.fill(
                 self.msg_content_label.layout_rect().inflate(1.5, 1.5),
-                &Color::rgba8(255, 255, 255, 20)
+                &env.get(theme::MESSAGE_HOVER_COLOR_KEY)
             );
         }
+        if env.get(crate::SELECTED_MESSAGE_KEY) == data.id {
+            ctx.fill(self.get_message_rect(data).inflate(1.5, 1.5), &env.get(theme::SELECTED_MESSAGE_COLOR_KEY));
+        }
 
-        self.draw_left_line(ctx, &settings);
+        self.draw_left_line(ctx, &settings, env);
+        if data.reply_to.is_some() {
+            self.reply_label.paint(ctx, data, env);
+        }
         self.msg_content_label.paint(ctx, data, env);
+        if !data.reactions.is_empty() {
+            self.reactions_label.paint(ctx, data, env);
+        }
         // Always paint because it's only when hot,
         // and because it's out of bounds.
         let is_below_profile_pic = self.msg_content_label.layout_rect().height()
@@ -134,13 +249,63 @@ impl Widget<Message> for SingleMessageWidget {
 }
 
 impl SingleMessageWidget {
-    
-    fn draw_left_line(&self, ctx: &mut PaintCtx, settings: &LayoutSettings) {
+
+    /// Sets the font and color of the content label.
+    /// Actions and notices are italic to set them apart from what was said.
+    /// Notices are also colored, since they are often errors.
+    /// Messages with only a few emoji are enlarged.
+    fn set_content_style(&mut self, data: &Message, env: &Env) {
+        let label = self.msg_content_label.widget_mut();
+        if is_jumbomoji(data) {
+            label.set_text_size(env.get(crate::CONTENT_FONT_SIZE_KEY) * JUMBOMOJI_SCALE);
+        } else {
+            label.set_text_size(crate::CONTENT_FONT_SIZE_KEY);
+        }
+        let font = druid::FontDescriptor::new(druid::FontFamily::SYSTEM_UI);
+        match data.kind {
+            MessageKind::Text => {
+                label.set_font(font);
+                label.set_text_color(druid::theme::TEXT_COLOR);
+            },
+            MessageKind::Action => {
+                label.set_font(font.with_style(druid::FontStyle::Italic));
+                label.set_text_color(druid::theme::TEXT_COLOR);
+            },
+            MessageKind::Notice => {
+                label.set_font(font.with_style(druid::FontStyle::Italic));
+                label.set_text_color(theme::NOTICE_COLOR_KEY);
+            },
+        }
+    }
+
+    /// Gets the area of the message in window coordinates, unless it is the same as the one sent last
+    fn get_hover_rect_to_send(&mut self, data: &Message, window_origin: Point) -> Option<Rect> {
+        let window_rect = self.get_message_rect(data) + window_origin.to_vec2();
+        if self.sent_hover_rect == Some(window_rect) {
+            return None;
+        }
+        self.sent_hover_rect = Some(window_rect);
+        Some(window_rect)
+    }
+
+    /// The area taken up by the content, the reply preview, and the reactions
+    fn get_message_rect(&self, data: &Message) -> Rect {
+        let mut rect = self.msg_content_label.layout_rect();
+        if data.reply_to.is_some() {
+            rect = rect.union(self.reply_label.layout_rect());
+        }
+        if !data.reactions.is_empty() {
+            rect = rect.union(self.reactions_label.layout_rect());
+        }
+        rect
+    }
+
+    fn draw_left_line(&self, ctx: &mut PaintCtx, settings: &LayoutSettings, env: &Env) {
         if settings.show_left_line {
             let content_label_rect = self.msg_content_label.layout_rect();
             let line_x0 = content_label_rect.x0 - settings.left_spacing;
             let line_rect = Rect::new(line_x0, content_label_rect.y0, line_x0 + 1.0, content_label_rect.y1);
-            ctx.fill(line_rect, &Color::GRAY);
+            ctx.fill(line_rect, &env.get(theme::LEFT_LINE_COLOR_KEY));
         }
     }
 }
\ No newline at end of file
@@ -1,13 +1,15 @@
+use std::sync;
 use druid::kurbo::{Circle, RoundedRect, BezPath};
 use druid::widget::prelude::*;
 use druid::{Widget, widget, WidgetExt};
-use druid::piet::{Color, kurbo};
+use druid::piet::kurbo;
 use druid::WidgetPod;
 use druid::Point;
 use druid;
-use crate::{MessageGroup, widgets::single_message_widget::SingleMessageWidget};
+use crate::{MessageGroup, widgets::single_message_widget::{self, SingleMessageWidget}};
+use crate::widgets::message_action_bar::{self, MessageActionBar};
 use crate::LayoutSettings;
-use crate::helper::helper_functions;
+use crate::helper::{audit, helper_functions, theme};
 use num_derive;
 
 extern crate chrono;
@@ -16,15 +18,30 @@ pub struct TimelineItemWidget {
     msg_content_labels: WidgetPod<MessageGroup, Box<dyn Widget<MessageGroup>>>,
     sender_name_label: WidgetPod<MessageGroup, widget::Label<MessageGroup>>,
     datetime_label: WidgetPod<MessageGroup, widget::Label<MessageGroup>>,
+    action_bar: MessageActionBar,
+    /// The ID and area of the message the action bar is shown for, in this item's coordinates,
+    /// so it stays right when the timeline scrolls
+    hovered_message: Option<(u64, druid::Rect)>,
+    /// Cleared by any change to the group or the env, so a long timeline is only laid out again where needed
+    cached_layout: Option<CachedLayout>,
+    /// The profile picture, ready to draw, and the picture it was made from
+    profile_pic_image: Option<(druid::ImageBuf, druid::piet::PietImage)>,
+}
+
+/// What the last layout was done with, and the size it gave
+struct CachedLayout {
+    width: f64,
+    settings: sync::Arc<LayoutSettings>,
+    size: Size,
 }
 
-const OTHER_MSG_COLOR: Color = Color::rgb8(74, 74, 76);
-const SELF_MSG_COLOR: Color = Color::rgb8(12, 131, 242);
 const DOT_SIZE: f64 = 1.5;
 const DOT_X_OFFSET: f64 = -0.9;
 const DOT_Y_OFFSET: f64 = 1.0;
+/// Space between the action bar and the edge of the item
+const ACTION_BAR_MARGIN: f64 = 4.0;
 
-#[derive(Clone, Copy, PartialEq, Data, num_derive::FromPrimitive)]
+#[derive(Clone, Copy, PartialEq, Debug, Data, num_derive::FromPrimitive, serde::Serialize, serde::Deserialize)]
 pub enum PictureShape {
     Rectangle = 0,
     RoundedRectangle,
@@ -33,7 +50,7 @@ pub enum PictureShape {
     Octagon,
 }
 
-#[derive(Clone, Copy, PartialEq, Data, num_derive::FromPrimitive)]
+#[derive(Clone, Copy, PartialEq, Debug, Data, num_derive::FromPrimitive, serde::Serialize, serde::Deserialize)]
 pub enum TailShape {
     Straight = 0,
     ConcaveBottom,
@@ -43,7 +60,7 @@ pub enum TailShape {
     Hidden,
 }
 
-#[derive(Clone, Copy, PartialEq, Data, num_derive::FromPrimitive)]
+#[derive(Clone, Copy, PartialEq, Debug, Data, num_derive::FromPrimitive, serde::Serialize, serde::Deserialize)]
 pub enum ItemLayoutOption {
     BubbleExternBottomMeta = 0,
     BubbleInternalBottomMeta,
@@ -52,14 +69,14 @@ pub enum ItemLayoutOption {
     IRCStyle,
 }
 
-#[derive(Clone, Copy, PartialEq, Data, num_derive::FromPrimitive)]
+#[derive(Clone, Copy, PartialEq, Debug, Data, num_derive::FromPrimitive, serde::Serialize, serde::Deserialize)]
 pub enum MetadataLayout {
     LeftSideBySide,
     LeftSideBySideWithDot,
     LeftRightSpaced,
 }
 
-fn make_tail_path(center_x: f64, y_position: f64, shape: TailShape, flip_x: bool, flip_y: bool, tail_size: f64) -> kurbo::BezPath {
+pub(crate) fn make_tail_path(center_x: f64, y_position: f64, shape: TailShape, flip_x: bool, flip_y: bool, tail_size: f64) -> kurbo::BezPath {
     let x_translation = if flip_x { -1.0 } else { 1.0 };
     let y_translation = if flip_y { -1.0 } else { 1.0 };
     let mut path = kurbo::BezPath::new();
@@ -119,7 +136,7 @@ fn make_tail_path(center_x: f64, y_position: f64, shape: TailShape, flip_x: bool
     path
 }
 
-fn make_hexagon_path(start_x: f64, vertical_trim: f64, inset: f64, pic_width: f64) -> kurbo::BezPath {
+pub(crate) fn make_hexagon_path(start_x: f64, vertical_trim: f64, inset: f64, pic_width: f64) -> kurbo::BezPath {
     let mut path = kurbo::BezPath::new();
     let second_x = pic_width * inset;
     let third_x = pic_width * (1.0 - inset);
@@ -138,7 +155,7 @@ fn make_hexagon_path(start_x: f64, vertical_trim: f64, inset: f64, pic_width: f6
     path
 }
 
-fn make_octagon_path(start_x: f64, fraction_from_corner: f64, pic_width: f64) -> kurbo::BezPath {
+pub(crate) fn make_octagon_path(start_x: f64, fraction_from_corner: f64, pic_width: f64) -> kurbo::BezPath {
     let dist_from_corner = pic_width * fraction_from_corner;
     let other_side_pos = pic_width - dist_from_corner;
 
@@ -156,13 +173,184 @@ fn make_octagon_path(start_x: f64, fraction_from_corner: f64, pic_width: f64) ->
     path
 }
 
+/// Where the parts of a timeline item go.
+///
+/// The offscreen renderer places its items with this too, so the golden images test the same placement as the window.
+pub(crate) struct ItemGeometry {
+    pub content_rect: druid::Rect,
+    pub sender_rect: druid::Rect,
+    pub datetime_rect: druid::Rect,
+    /// The size of the whole item
+    pub size: Size,
+}
+
+impl ItemGeometry {
+    /// Places the labels, once their sizes are known
+    pub(crate) fn new(settings: &LayoutSettings, is_self_user: bool, width_available: f64,
+        sender_label_size: Size, datetime_label_size: Size, msg_label_list_size: Size) -> ItemGeometry
+    {
+        let total_metadata_width = settings.get_metadata_width(sender_label_size.width, datetime_label_size.width);
+
+        // Offset in the case of tiny flipped bubbles with tails, since tiny
+        // messages cause the tail to not align with the picture properly
+        let y_top_offset = settings.get_top_y_offset(is_self_user, &sender_label_size, &msg_label_list_size);
+
+        let content_origin = settings.get_content_origin(
+            is_self_user,
+            width_available,
+            y_top_offset,
+            msg_label_list_size.width,
+            total_metadata_width,
+            sender_label_size.height
+        );
+
+        let sender_label_origin = settings.get_sender_origin(
+            is_self_user,
+            width_available,
+            sender_label_size.width,
+            datetime_label_size.width,
+            msg_label_list_size.height,
+            msg_label_list_size.width,
+            y_top_offset
+        );
+
+        // Position to right of sender. Also account for differences in height.
+        let datetime_label_origin = settings.get_datetime_origin(width_available, msg_label_list_size.width,
+            &sender_label_origin, &sender_label_size, &datetime_label_size);
+
+        // The image is at the top left if other, or top right if self (if shown)
+        // Potential future support for bottom images
+        let height = settings.get_total_height(is_self_user, width_available, &sender_label_size, &msg_label_list_size, y_top_offset);
+        ItemGeometry {
+            content_rect: druid::Rect::from_origin_size(content_origin, msg_label_list_size),
+            sender_rect: druid::Rect::from_origin_size(sender_label_origin, sender_label_size),
+            datetime_rect: druid::Rect::from_origin_size(datetime_label_origin, datetime_label_size),
+            size: Size::new(width_available, height),
+        }
+    }
+
+    /// Gets the total space taken up by all labels in the bubble, plus the padding.
+    pub(crate) fn get_bubble_rect(&self, settings: &LayoutSettings) -> druid::Rect {
+        let content_label_rect = self.content_rect;
+        let sender_label_rect = self.sender_rect;
+        let datetime_label_rect = self.datetime_rect;
+        let mut bubble_x0 = content_label_rect.x0 - settings.left_spacing;
+        let mut bubble_x1 = content_label_rect.x1;
+
+        let mut unpadded_bubble_height = content_label_rect.y1 - content_label_rect.y0;
+        if settings.item_layout == ItemLayoutOption::BubbleInternalBottomMeta || settings.item_layout == ItemLayoutOption::BubbleInternalTopMeta {
+            unpadded_bubble_height += sender_label_rect.height();
+            unpadded_bubble_height += settings.metadata_content_spacing;
+            bubble_x0 = bubble_x0.min(sender_label_rect.x0).min(datetime_label_rect.x0);
+            bubble_x1 = bubble_x1.max(sender_label_rect.x1).max(datetime_label_rect.x1);
+        }
+        let bubble_y0 = if settings.item_layout == ItemLayoutOption::BubbleInternalTopMeta {
+            sender_label_rect.y0
+        } else {
+            content_label_rect.y0
+        };
+
+        let bubble_y1 = bubble_y0 + unpadded_bubble_height;
+
+        druid::Rect::new(bubble_x0, bubble_y0, bubble_x1, bubble_y1)
+            .inflate(settings.bubble_padding, settings.bubble_padding)
+    }
+
+    /// Gets the space taken up by the profile picture, whether or not it is shown.
+    /// A flipped bubble has the picture at its bottom instead of at the top of the item.
+    pub(crate) fn get_profile_pic_rect(&self, settings: &LayoutSettings, is_self_user: bool) -> druid::Rect {
+        let x = settings.profile_pic_x_origin(is_self_user, self.size.width, self.sender_rect.size());
+        let y = if settings.is_bubble_flipped(is_self_user) && settings.is_bubble() {
+            0.0f64.max(self.get_bubble_rect(settings).y1 - settings.picture_size)
+        } else {
+            0.0
+        };
+        druid::Rect::new(x, y, x + settings.picture_size, y + settings.picture_size)
+    }
+}
+
+// The parts of an item that aren't labels. They take any render context, so the offscreen renderer draws them the same way.
+
+pub(crate) fn paint_bubble(rc: &mut impl RenderContext, settings: &LayoutSettings, bubble_rect: druid::Rect, bubble_color: &druid::Color) {
+    if settings.is_bubble() {
+        rc.fill(RoundedRect::from_rect(bubble_rect, settings.chat_bubble_radius), bubble_color);
+    }
+}
+
+/// The little arrow/tail that goes from the image to the bubble
+pub(crate) fn paint_bubble_tail(rc: &mut impl RenderContext, settings: &LayoutSettings, is_self_user: bool,
+    bubble_rect: druid::Rect, bubble_color: &druid::Color)
+{
+    if settings.is_bubble() && settings.chat_bubble_tail_shape != TailShape::Hidden {
+        let is_flipped = settings.is_bubble_flipped(is_self_user);
+        let tail_y_position = if is_flipped { bubble_rect.y1 } else { bubble_rect.y0 };
+        let tail_x_position = if is_self_user { bubble_rect.x1 } else { bubble_rect.x0 };
+        rc.fill(make_tail_path(
+            tail_x_position,
+            tail_y_position + settings.get_tail_y_offset(is_self_user),
+            settings.chat_bubble_tail_shape,
+            is_self_user,
+            is_flipped,
+            settings.chat_bubble_tail_size,
+        ), bubble_color);
+    }
+}
+
+pub(crate) fn paint_profile_pic<R: RenderContext>(rc: &mut R, settings: &LayoutSettings, is_self_user: bool,
+    geometry: &ItemGeometry, image: &R::Image)
+{
+    let profile_pic_rect = geometry.get_profile_pic_rect(settings, is_self_user);
+    let profile_pic_x_offset = profile_pic_rect.x0;
+    let pic_y_offset = if settings.is_bubble_flipped(is_self_user) && settings.is_bubble() {
+        profile_pic_rect.y0 - 0.3
+    } else {
+        profile_pic_rect.y0 + 0.3 // For preventing some of the profile pic from showing over the tail
+    };
+    let _ = rc.with_save(|rc| { // Makes it so the clip doesn't mess up the following draws
+        match settings.picture_shape {
+            PictureShape::Rectangle => {},
+            PictureShape::RoundedRectangle => {
+                rc.clip(
+                    RoundedRect::new(profile_pic_x_offset, 0.0,
+                        profile_pic_x_offset + settings.picture_size, settings.picture_size, 4.0)
+                )
+            },
+            PictureShape::Circle => {
+                rc.clip(Circle::new(
+                    Point::new(
+                        profile_pic_x_offset + settings.picture_size / 2.0,
+                        settings.picture_size / 2.0 + pic_y_offset), settings.picture_size / 2.0
+                    )
+                )
+            },
+            PictureShape::Hexagon => {
+                rc.clip(make_hexagon_path(profile_pic_x_offset, 0.08, 0.25, settings.picture_size))
+            },
+            PictureShape::Octagon => {
+                rc.clip(make_octagon_path(profile_pic_x_offset, 0.25, settings.picture_size))
+            },
+        }
+        rc.draw_image(image,
+            druid::Rect::new(profile_pic_x_offset, pic_y_offset,
+                settings.picture_size + profile_pic_x_offset, settings.picture_size + pic_y_offset),
+                druid::piet::InterpolationMode::Bilinear
+        );
+        Ok(())
+    });
+}
+
+pub(crate) fn paint_metadata_dot(rc: &mut impl RenderContext, settings: &LayoutSettings, datetime_rect: druid::Rect, dot_color: &druid::Color) {
+    if settings.metadata_layout == MetadataLayout::LeftSideBySideWithDot {
+        let dot_location = druid::Point::new(datetime_rect.x0 + DOT_X_OFFSET, (datetime_rect.y0 + datetime_rect.y1) / 2.0 + DOT_Y_OFFSET);
+        rc.fill(druid::kurbo::Circle::new(dot_location, DOT_SIZE), dot_color);
+    }
+}
+
 impl TimelineItemWidget {
     pub fn new() -> Self {
         let sender_name_label = WidgetPod::new(
-            widget::Label::new(|item: &MessageGroup, _env: &_| {
-                let mut username = "User".to_string();
-                username.push_str(item.user_id.to_string().as_str());
-                username
+            widget::Label::new(|item: &MessageGroup, env: &Env| {
+                helper_functions::get_display_name(item.user_id, env)
         })
             .with_line_break_mode(widget::LineBreaking::WordWrap)
         );
@@ -171,7 +359,7 @@ impl TimelineItemWidget {
                 if item.messages.len() > 0 {
                     helper_functions::timestamp_to_display_msg(
                         item.messages[0].timestamp_epoch_seconds,
-                        num_traits::FromPrimitive::from_u64(env.get(crate::DATETIME_FORMAT_KEY)).expect("Invalid datetime format index"),
+                        LayoutSettings::from_env(env).datetime_format,
                     ).to_string()
                 } else {
                     "Invalid".to_string()
@@ -184,33 +372,48 @@ impl TimelineItemWidget {
             SingleMessageWidget::new()
         }).with_spacing(crate::SINGLE_MESSAGE_SPACING_KEY);
         let msg_content_labels = WidgetPod::new(
+            // Lets every message know who sent it, since that is only stored in the group.
+            widget::EnvScope::new(
+                |env: &mut Env, group: &MessageGroup| {
+                    env.set(crate::GROUP_USER_ID_KEY, group.user_id as u64);
+                },
+                msg_content_labels_list.lens(MessageGroup::messages)
             // Boxed is needed to make it so you don't get buried in type annotations.
-            msg_content_labels_list.lens(MessageGroup::messages).boxed()
+            ).boxed()
         );
         Self {
             msg_content_labels: msg_content_labels,
             sender_name_label: sender_name_label,
             datetime_label: datetime_label,
+            action_bar: MessageActionBar::new(),
+            hovered_message: None,
+            cached_layout: None,
+            profile_pic_image: None,
         }
     }
 
 }
 
+/// Whether the env changed in a way that changes how an item is laid out: its settings, fonts, sender name, or side
+fn env_changes_layout(ctx: &UpdateCtx) -> bool {
+    ctx.env_key_changed(&crate::LAYOUT_SETTINGS_KEY)
+        || ctx.env_key_changed(&crate::CONTENT_FONT_SIZE_KEY)
+        || ctx.env_key_changed(&crate::SENDER_FONT_SIZE_KEY)
+        || ctx.env_key_changed(&crate::DATETIME_FONT_SIZE_KEY)
+        || ctx.env_key_changed(&crate::SELF_DISPLAY_NAME_KEY)
+        || ctx.env_key_changed(&crate::SELF_USER_ID_KEY)
+}
+
 impl Widget<MessageGroup> for TimelineItemWidget {
 
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut MessageGroup, env: &Env) {
-        match event {
-            Event::Command(cmd) if cmd.is(crate::REFRESH_UI_SELECTOR) => {
-                self.msg_content_labels.event(ctx, event, data, env);
-                ctx.request_layout();
-                ctx.request_paint();
-            }
-            _ => {
-                self.msg_content_labels.event(ctx, event, data, env);
-                self.sender_name_label.event(ctx, event, data, env);
-                self.datetime_label.event(ctx, event, data, env);
-            }
+        if self.handle_action_bar_event(ctx, event, data, env) {
+            return;
         }
+        // Changes to the settings come through the env, so the cached layout is cleared in update
+        self.msg_content_labels.event(ctx, event, data, env);
+        self.sender_name_label.event(ctx, event, data, env);
+        self.datetime_label.event(ctx, event, data, env);
     }
 
     fn lifecycle(
@@ -220,12 +423,29 @@ impl Widget<MessageGroup> for TimelineItemWidget {
         data: &MessageGroup,
         env: &Env,
     ) {
+        if let LifeCycle::HotChanged(false) = event {
+            self.hovered_message = None;
+            self.action_bar.set_hot_position(None);
+            ctx.request_paint();
+        }
         self.msg_content_labels.lifecycle(ctx, event, data, env);
         self.sender_name_label.lifecycle(ctx, event, data, env);
         self.datetime_label.lifecycle(ctx, event, data, env);
     }
 
-    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &MessageGroup, data: &MessageGroup, env: &Env) {
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &MessageGroup, data: &MessageGroup, env: &Env) {
+        // The selections are in the env too, but they only change what is painted
+        if !old_data.same(data) || env_changes_layout(ctx) {
+            self.cached_layout = None;
+            ctx.request_layout();
+        }
+        // The hovered message may have been deleted
+        if let Some((id, _)) = self.hovered_message {
+            if !data.messages.iter().any(|message| message.id == id) {
+                self.hovered_message = None;
+                ctx.request_paint();
+            }
+        }
         self.msg_content_labels.update(ctx, data, env);
         self.sender_name_label.update(ctx, data, env);
         self.datetime_label.update(ctx, data, env);
@@ -239,17 +459,22 @@ impl Widget<MessageGroup> for TimelineItemWidget {
         env: &Env,
     ) -> Size {
         let settings = LayoutSettings::from_env(env);
+        let width_available = bc.max().width;
+        if let Some(cached) = &self.cached_layout {
+            // The children keep where they were put last time
+            if cached.width == width_available && sync::Arc::ptr_eq(&cached.settings, &settings) {
+                return cached.size;
+            }
+        }
+        audit::record(audit::Counter::ItemLayout);
         let is_self_user: bool = env.get(crate::SELF_USER_ID_KEY) as u32 == data.user_id;
 
         self.sender_name_label.widget_mut().set_font(settings.get_metadata_font_descriptor());
         self.datetime_label.widget_mut().set_font(settings.get_metadata_font_descriptor());
         self.sender_name_label.widget_mut().set_text_size(crate::SENDER_FONT_SIZE_KEY);
         self.datetime_label.widget_mut().set_text_size(crate::DATETIME_FONT_SIZE_KEY);
-        self.sender_name_label.widget_mut().set_text_color(settings.get_sender_color(is_self_user));
-        self.datetime_label.widget_mut().set_text_color(settings.get_datetime_color(is_self_user));
-
-        let width_available = bc.max().width;
-
+        self.sender_name_label.widget_mut().set_text_color(theme::sender_color_key(is_self_user));
+        self.datetime_label.widget_mut().set_text_color(theme::datetime_color_key(is_self_user));
 
         // Do the label layouts first since we need to know their sizes
         let sender_label_size = self.sender_name_label.layout(
@@ -265,66 +490,48 @@ impl Widget<MessageGroup> for TimelineItemWidget {
         let msg_label_list_size = self.msg_content_labels.layout(
             layout_ctx, &settings.get_available_content_area(width_available, is_self_user),
             data, env);
-        let total_metadata_width = sender_label_size.width + datetime_label_size.width;
-
-        // Offset in the case of tiny flipped bubbles with tails, since tiny
-        // messages cause the tail to not align with the picture properly
-        let y_top_offset = settings.get_top_y_offset(is_self_user, &sender_label_size, &msg_label_list_size);
-
-        self.msg_content_labels.set_origin(layout_ctx,
-            settings.get_content_origin(
-                is_self_user,
-                width_available,
-                y_top_offset,
-                msg_label_list_size.width,
-                total_metadata_width,
-                sender_label_size.height
-            )
-        );
-
-        let sender_label_origin = settings.get_sender_origin(
-            is_self_user,
-            width_available,
-            sender_label_size.width,
-            datetime_label_size.width,
-            msg_label_list_size.height,
-            msg_label_list_size.width,
-            y_top_offset
-        );
 
-        // Position to right of sender. Also account for differences in height.
-        let datetime_label_origin = settings.get_datetime_origin(width_available, msg_label_list_size.width,
-            &sender_label_origin, &sender_label_size, &datetime_label_size);
-        
-        self.sender_name_label.set_origin(layout_ctx, sender_label_origin);
-        self.datetime_label.set_origin(layout_ctx, datetime_label_origin);
+        let geometry = ItemGeometry::new(&settings, is_self_user, width_available,
+            sender_label_size, datetime_label_size, msg_label_list_size);
+        self.msg_content_labels.set_origin(layout_ctx, geometry.content_rect.origin());
+        self.sender_name_label.set_origin(layout_ctx, geometry.sender_rect.origin());
+        self.datetime_label.set_origin(layout_ctx, geometry.datetime_rect.origin());
+        self.action_bar.rebuild_if_needed(layout_ctx, env);
 
-        // The image is at the top left if other, or top right if self (if shown)
-        // Potential future support for bottom images
-        Size::new(bc.max().width, settings.get_total_height(width_available, &sender_label_size, &msg_label_list_size, y_top_offset))
+        let size = geometry.size;
+        self.cached_layout = Some(CachedLayout { width: width_available, settings, size });
+        size
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &MessageGroup, env: &Env) {
         let settings = LayoutSettings::from_env(env);
         let is_self_user = env.get(crate::SELF_USER_ID_KEY) as u32 == data.user_id;
-    
-        // First, do the calculations and variables
-        self.draw_bubble_background(ctx, &settings, is_self_user);
+        // Enlarged emoji look best on their own, so they get no bubble
+        let only_jumbomoji = data.messages.iter().all(single_message_widget::is_jumbomoji);
+        let geometry = self.get_geometry(ctx.size());
+        let bubble_rect = geometry.get_bubble_rect(&settings);
+        let bubble_color = env.get(theme::bubble_color_key(is_self_user));
+
+        // First, the bubble
+        if !only_jumbomoji {
+            paint_bubble(ctx.render_ctx, &settings, bubble_rect, &bubble_color);
+        }
 
         // Next, the profile pic
-        self.draw_profile_pic(ctx, data, &settings, is_self_user);
+        self.draw_profile_pic(ctx, data, &settings, is_self_user, &geometry);
         // Now the little arrow/tail that goes from the image to the bubble
-        self.draw_bubble_tail(ctx, &settings, is_self_user);
+        if !only_jumbomoji {
+            paint_bubble_tail(ctx.render_ctx, &settings, is_self_user, bubble_rect, &bubble_color);
+        }
 
         // Draw text
         self.msg_content_labels.paint(ctx, data, env);
         self.sender_name_label.paint(ctx, data, env);
         self.datetime_label.paint(ctx, data, env);
-        if settings.metadata_layout == MetadataLayout::LeftSideBySideWithDot {
-            let datetime_position = self.datetime_label.layout_rect();
-            let dot_location = druid::Point::new(datetime_position.x0 + DOT_X_OFFSET, (datetime_position.y0 + datetime_position.y1) / 2.0 + DOT_Y_OFFSET);
-            let dot_color = settings.get_datetime_color(is_self_user);
-            ctx.fill(druid::kurbo::Circle::new(dot_location, DOT_SIZE), &dot_color);
+        paint_metadata_dot(ctx.render_ctx, &settings, geometry.datetime_rect, &env.get(theme::datetime_color_key(is_self_user)));
+        // Last, so it floats over everything else
+        if let Some(action_bar_rect) = self.get_action_bar_rect(ctx.size(), &settings, is_self_user) {
+            self.action_bar.paint(ctx, action_bar_rect.origin(), env);
         }
     }
 
@@ -332,126 +539,100 @@ impl Widget<MessageGroup> for TimelineItemWidget {
 
 impl TimelineItemWidget {
 
-    /// Gets the total space taken up by all labels in the bubble, minus the padding. 
-    /// Return order: x0, x1, y0, y1
-    fn get_bubble_dimensions(&self, settings: &LayoutSettings) -> (f64, f64, f64, f64) {
-        let content_label_rect = self.msg_content_labels.layout_rect();
-        let sender_label_rect = self.sender_name_label.layout_rect();
-        let datetime_label_rect = self.datetime_label.layout_rect();
-        let mut bubble_x0 = content_label_rect.x0 - settings.left_spacing;
-        let mut bubble_x1 = content_label_rect.x1;
-
-        let mut unpadded_bubble_height = content_label_rect.y1 - content_label_rect.y0;
-        if settings.item_layout == ItemLayoutOption::BubbleInternalBottomMeta || settings.item_layout == ItemLayoutOption::BubbleInternalTopMeta {
-            unpadded_bubble_height += sender_label_rect.height();
-            unpadded_bubble_height += settings.metadata_content_spacing;
-            bubble_x0 = bubble_x0.min(sender_label_rect.x0).min(datetime_label_rect.x0);
-            bubble_x1 = bubble_x1.max(sender_label_rect.x1).max(datetime_label_rect.x1);
+    /// Tracks which message is hovered, and handles the pointer over the action bar.
+    /// Returns true if the event was used by the action bar, so the messages below it should not get it.
+    fn handle_action_bar_event(&mut self, ctx: &mut EventCtx, event: &Event, data: &MessageGroup, env: &Env) -> bool {
+        let settings = LayoutSettings::from_env(env);
+        let is_self_user = env.get(crate::SELF_USER_ID_KEY) as u32 == data.user_id;
+        match event {
+            Event::Command(cmd) if cmd.is(message_action_bar::MESSAGE_HOVERED) => {
+                let (id, rect) = *cmd.get_unchecked(message_action_bar::MESSAGE_HOVERED);
+                // Only the group the message is in shows the bar
+                if ctx.is_hot() && data.messages.iter().any(|message| message.id == id) {
+                    self.hovered_message = Some((id, rect - ctx.window_origin().to_vec2()));
+                    ctx.request_paint();
+                }
+                false
+            },
+            Event::MouseMove(mouse_event) => {
+                let bar_rect = self.get_action_bar_rect(ctx.size(), &settings, is_self_user);
+                let pos_in_bar = bar_rect
+                    .filter(|rect| rect.contains(mouse_event.pos))
+                    .map(|rect| mouse_event.pos - rect.origin().to_vec2());
+                if self.action_bar.set_hot_position(pos_in_bar) {
+                    ctx.request_paint();
+                }
+                pos_in_bar.is_some()
+            },
+            Event::MouseDown(mouse_event) => {
+                let bar_rect = match self.get_action_bar_rect(ctx.size(), &settings, is_self_user) {
+                    Some(rect) if rect.contains(mouse_event.pos) => rect,
+                    _ => return false,
+                };
+                let hovered_id = self.hovered_message.map(|(id, _)| id);
+                if let Some(message) = data.messages.iter().find(|message| Some(message.id) == hovered_id) {
+                    let pos_in_bar = mouse_event.pos - bar_rect.origin().to_vec2();
+                    self.action_bar.click(ctx, pos_in_bar, mouse_event.window_pos, message, is_self_user);
+                }
+                ctx.set_handled();
+                true
+            },
+            _ => false,
         }
-        let bubble_y0 = if settings.item_layout == ItemLayoutOption::BubbleInternalTopMeta {
-            sender_label_rect.y0
-        } else {
-            content_label_rect.y0
-        };
-
-        let bubble_y1 = bubble_y0 + unpadded_bubble_height;
-
-        (bubble_x0 - settings.bubble_padding, bubble_x1 + settings.bubble_padding,
-            bubble_y0 - settings.bubble_padding, bubble_y1 + settings.bubble_padding)
     }
 
-    fn get_bubble_color(&self, is_self_user: bool) -> druid::Color {
-        if is_self_user {
-            SELF_MSG_COLOR
+    /// Gets where the action bar goes for the hovered message, or none if no message is hovered.
+    ///
+    /// It floats over the top edge of the message, on the right side of the bubble.
+    /// Without a bubble, it goes on the right side of the item, like in IRC style.
+    /// It is kept inside the item, so it can be clicked, and it never changes the layout.
+    fn get_action_bar_rect(&self, size: Size, settings: &LayoutSettings, is_self_user: bool) -> Option<druid::Rect> {
+        let (_, message_rect) = self.hovered_message?;
+        let bar_size = self.action_bar.size();
+        let x = if settings.is_bubble() {
+            let bubble_rect = self.get_geometry(size).get_bubble_rect(settings);
+            let (bubble_x0, bubble_x1) = (bubble_rect.x0, bubble_rect.x1);
+            if settings.is_right_aligned(is_self_user) {
+                // Grows to the left, away from the profile pic
+                bubble_x1 - bar_size.width
+            } else {
+                // Grows to the right when the bubble is smaller than the bar
+                (bubble_x1 - bar_size.width).max(bubble_x0)
+            }
         } else {
-            OTHER_MSG_COLOR
-        }
-    }
-
-    fn draw_bubble_background(&self, ctx: &mut PaintCtx, settings: &LayoutSettings, is_self_user: bool) {
-        let (bubble_x0, bubble_x1, bubble_y0, bubble_y1) = self.get_bubble_dimensions(settings);
-
-        let bubble_color = self.get_bubble_color(is_self_user);
-        // Draw background
-        if settings.is_bubble() {
-            let background_rect = RoundedRect::new(
-                bubble_x0, bubble_y0, bubble_x1, bubble_y1, settings.chat_bubble_radius
-            );
-            ctx.fill(background_rect, &(bubble_color));
-        }
+            size.width - bar_size.width - ACTION_BAR_MARGIN
+        };
+        let y = message_rect.y0 - bar_size.height / 2.0;
+        let origin = Point::new(
+            x.min(size.width - bar_size.width).max(0.0),
+            y.min(size.height - bar_size.height).max(0.0),
+        );
+        Some(druid::Rect::from_origin_size(origin, bar_size))
     }
 
-    fn draw_bubble_tail(&self, ctx: &mut PaintCtx, settings: &LayoutSettings, is_self_user: bool) {
-        if settings.is_bubble() {
-            let (bubble_x0, bubble_x1, bubble_y0, bubble_y1) = self.get_bubble_dimensions(settings);
-
-            let is_flipped = settings.is_bubble_flipped(is_self_user);
-            let tail_y_position = if is_flipped { bubble_y1 } else { bubble_y0 };
-            let tail_x_position = if is_self_user { bubble_x1 } else { bubble_x0 };
-            let bubble_color = self.get_bubble_color(is_self_user);
-            
-            if settings.chat_bubble_tail_shape != TailShape::Hidden {
-                ctx.fill(make_tail_path(
-                    tail_x_position,
-                    tail_y_position + settings.get_tail_y_offset(is_self_user),
-                    settings.chat_bubble_tail_shape,
-                    is_self_user,
-                    is_flipped,
-                    settings.chat_bubble_tail_size,
-                ), &bubble_color);
-            }
+    /// Gets where the labels were put by the last layout
+    fn get_geometry(&self, size: Size) -> ItemGeometry {
+        ItemGeometry {
+            content_rect: self.msg_content_labels.layout_rect(),
+            sender_rect: self.sender_name_label.layout_rect(),
+            datetime_rect: self.datetime_label.layout_rect(),
+            size,
         }
     }
 
-    fn draw_profile_pic(&self, ctx: &mut PaintCtx, data: &MessageGroup, settings: &LayoutSettings, is_self_user: bool) {
+    fn draw_profile_pic(&mut self, ctx: &mut PaintCtx, data: &MessageGroup, settings: &LayoutSettings, is_self_user: bool,
+        geometry: &ItemGeometry)
+    {
         if !settings.show_picture(is_self_user) {
             return;
         }
-        let profile_pic_x_offset = settings.profile_pic_x_origin(
-            is_self_user,
-            ctx.region().bounding_box().width(),
-            self.sender_name_label.layout_rect().size()
-        );
-        let piet_image = {
-            let image_data = data.profile_pic.clone();
-            image_data.to_image(ctx.render_ctx)
-        };
-        ctx.with_save(|ctx| { // Makes it so the clip doesn't mess up the following draws
-            let pic_y_offset = if settings.is_bubble_flipped(is_self_user) && settings.is_bubble() {
-                let (_, _, _, bubble_y1) = self.get_bubble_dimensions(settings);
-
-                0.0f64.max(bubble_y1 - settings.picture_size) - 0.3
-            } else {
-                0.3 // For preventing some of the profile pic from showing over the tail
-            };
-            match settings.picture_shape {
-                PictureShape::Rectangle => {},
-                PictureShape::RoundedRectangle => {
-                    ctx.clip(
-                        RoundedRect::new(profile_pic_x_offset, 0.0, 
-                            profile_pic_x_offset + settings.picture_size, settings.picture_size, 4.0)
-                    )
-                },
-                PictureShape::Circle => {
-                    ctx.clip(Circle::new(
-                        Point::new(
-                            profile_pic_x_offset + settings.picture_size / 2.0,
-                            settings.picture_size / 2.0 + pic_y_offset), settings.picture_size / 2.0
-                        )
-                    )
-                },
-                PictureShape::Hexagon => {
-                    ctx.clip(make_hexagon_path(profile_pic_x_offset, 0.08, 0.25, settings.picture_size))
-                },
-                PictureShape::Octagon => {
-                    ctx.clip(make_octagon_path(profile_pic_x_offset, 0.25, settings.picture_size))
-                },
-            }
-            ctx.draw_image(&piet_image,
-                druid::Rect::new(profile_pic_x_offset, pic_y_offset,
-                    settings.picture_size + profile_pic_x_offset, settings.picture_size + pic_y_offset),
-                    druid::piet::InterpolationMode::Bilinear
-            );
+        // Making the image to draw copies the whole picture, so it's only done again when the picture changes
+        if !matches!(&self.profile_pic_image, Some((picture, _)) if picture.same(&data.profile_pic)) {
+            self.profile_pic_image = None;
+        }
+        let (_, piet_image) = self.profile_pic_image.get_or_insert_with(|| {
+            (data.profile_pic.clone(), data.profile_pic.to_image(ctx.render_ctx))
         });
+        paint_profile_pic(ctx.render_ctx, settings, is_self_user, geometry, piet_image);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,159 @@
+use druid::widget::prelude::*;
+use druid::{im, widget, ImageBuf, Point, WidgetExt, WidgetPod};
+use crate::{AppState, Message, MessageGroup, MessageKind};
+use crate::helper::layout_settings::LayoutSettings;
+use crate::widgets::timeline_item_widget::TimelineItemWidget;
+
+/// The preview is shown as this user, so both sides of the conversation are in it
+const PREVIEW_SELF_USER_ID: u32 = 0;
+const PREVIEW_OTHER_USER_IDS: [u32; 2] = [1, 2];
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The sample messages, split by the day they were sent
+#[derive(Clone, Data, druid::Lens)]
+struct SampleTimeline {
+    yesterday: im::Vector<MessageGroup>,
+    today: im::Vector<MessageGroup>,
+}
+
+/// A small timeline with sample messages, laid out with the settings being edited.
+///
+/// It uses the same timeline items as the chat window, but can't be interacted with,
+/// so the message actions don't act on messages that aren't in the chat.
+/// Its width is set separately from the window's, to see where the IRC layout stacks.
+pub struct TimelinePreview {
+    sample: SampleTimeline,
+    timeline: WidgetPod<SampleTimeline, Box<dyn Widget<SampleTimeline>>>,
+}
+
+impl TimelinePreview {
+    pub fn new(profile_pics: &im::Vector<ImageBuf>) -> Self {
+        TimelinePreview {
+            sample: build_sample_timeline(profile_pics),
+            timeline: WidgetPod::new(build_timeline()),
+        }
+    }
+
+    /// Gives the timeline the settings being edited, the same way the chat window does
+    fn preview_env(data: &AppState, env: &Env) -> Env {
+        let mut preview_env = env.clone();
+        LayoutSettings::set_env(&data.layout_settings, &mut preview_env);
+        preview_env.set(crate::SELF_USER_ID_KEY, PREVIEW_SELF_USER_ID as u64);
+        preview_env.set(crate::SELF_DISPLAY_NAME_KEY, "");
+        preview_env.set(crate::SELECTED_MESSAGE_KEY, crate::NO_SELECTED_MESSAGE);
+        preview_env
+    }
+}
+
+impl Widget<AppState> for TimelinePreview {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            // Only scrolling is allowed, so there are no menus, selections, or action bars
+            Event::MouseDown(_) | Event::MouseUp(_) | Event::MouseMove(_)
+            | Event::KeyDown(_) | Event::KeyUp(_) | Event::Paste(_) => {},
+            _ => self.timeline.event(ctx, event, &mut self.sample, &TimelinePreview::preview_env(data, env)),
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        self.timeline.lifecycle(ctx, event, &self.sample, &TimelinePreview::preview_env(data, env));
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if !old_data.layout_settings.same(&data.layout_settings) || old_data.preview_width != data.preview_width {
+            ctx.request_layout();
+        }
+        self.timeline.update(ctx, &self.sample, &TimelinePreview::preview_env(data, env));
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, env: &Env) -> Size {
+        let size = Size::new(data.preview_width, bc.max().height);
+        self.timeline.layout(ctx, &BoxConstraints::tight(size), &self.sample, &TimelinePreview::preview_env(data, env));
+        self.timeline.set_origin(ctx, Point::ORIGIN);
+        bc.constrain(size)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &env.get(druid::theme::WINDOW_BACKGROUND_COLOR));
+        ctx.with_save(|ctx| {
+            ctx.clip(rect);
+            self.timeline.paint(ctx, &self.sample, &TimelinePreview::preview_env(data, env));
+        });
+        ctx.stroke(rect, &env.get(druid::theme::BORDER_DARK), 1.0);
+    }
+}
+
+fn build_timeline() -> Box<dyn Widget<SampleTimeline>> {
+    let day = || {
+        widget::List::new(TimelineItemWidget::new)
+            .with_spacing(crate::GROUP_SPACING_KEY)
+    };
+    widget::Scroll::new(
+        widget::Flex::column()
+            .with_child(day().lens(SampleTimeline::yesterday))
+            .with_spacer(crate::GROUP_SPACING_KEY)
+            .with_child(build_date_separator("Today"))
+            .with_spacer(crate::GROUP_SPACING_KEY)
+            .with_child(day().lens(SampleTimeline::today))
+            .padding(5.0)
+    )
+    .vertical()
+    .expand()
+    .boxed()
+}
+
+/// A line across the timeline with the day in the middle
+fn build_date_separator(day: &'static str) -> impl Widget<SampleTimeline> {
+    let line = || {
+        widget::SizedBox::empty()
+            .height(1.0)
+            .expand_width()
+            .background(crate::helper::theme::DATETIME_COLOR_KEY)
+    };
+    widget::Flex::row()
+        .with_flex_child(line(), 1.0)
+        .with_spacer(8.0)
+        .with_child(
+            widget::Label::new(day)
+                .with_text_size(crate::DATETIME_FONT_SIZE_KEY)
+                .with_text_color(crate::helper::theme::DATETIME_COLOR_KEY)
+        )
+        .with_spacer(8.0)
+        .with_flex_child(line(), 1.0)
+}
+
+/// Builds short and long messages from both sides, including a group of several messages.
+fn build_sample_timeline(profile_pics: &im::Vector<ImageBuf>) -> SampleTimeline {
+    let now = chrono::offset::Local::now().timestamp();
+    let yesterday = now - SECONDS_PER_DAY;
+    let [first_other, second_other] = PREVIEW_OTHER_USER_IDS;
+    let mut next_id = 0;
+    let mut group = |user_id: u32, timestamp: i64, texts: &[&str]| {
+        let messages = texts.iter().enumerate().map(|(i, text)| {
+            let mut message = Message::new(text.to_string(), timestamp + i as i64 * 20, MessageKind::Text);
+            message.id = next_id;
+            message.position_in_group = i as u32;
+            next_id += 1;
+            message
+        }).collect();
+        MessageGroup {
+            user_id,
+            profile_pic: profile_pics.get(user_id as usize).cloned().unwrap_or_else(ImageBuf::empty),
+            messages,
+        }
+    };
+    SampleTimeline {
+        yesterday: im::vector![
+            group(first_other, yesterday, &["Did anyone try the new layout settings?"]),
+            group(PREVIEW_SELF_USER_ID, yesterday + 60, &[
+                "Yes! I have been moving the sliders around all morning, and it's a lot easier to see what each \
+                one does to the bubbles, the pictures, and the spacing when the messages are right there.",
+            ]),
+        ],
+        today: im::vector![
+            group(second_other, now - 180, &["Nice", "How does it look with a few messages in a row?", "Like this?"]),
+            group(PREVIEW_SELF_USER_ID, now - 60, &["Looks good", "ok"]),
+        ],
+    }
+}